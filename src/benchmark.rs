@@ -55,9 +55,10 @@ impl<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>> SourcePicker<'a, V, E, G> {
     }
 
     /// Benchmarks PageRank with `max_iters = 20` and `epsilon = 0.0004`
-    pub fn benchmark_kernel_pr(&self) {
+    pub fn benchmark_kernel_pr(&self, num_trials: usize) {
         benchmark_kernel(
             self.graph,
+            num_trials,
             Box::new(|g: &G| {
                 crate::pr::page_rank_pull(g, 20, Some(0.0004));
             }),
@@ -68,18 +69,20 @@ impl<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>> SourcePicker<'a, V, E, G> {
 
     /// Triangle Counting (TC) - Order invariant with possible relabelling
     /// FIXME: Relabelling is not supported yet
-    pub fn benchmark_kernel_tc(&self) {
+    pub fn benchmark_kernel_tc(&self, num_trials: usize) {
         benchmark_kernel(
             self.graph,
+            num_trials,
             Box::new(|g: &G| crate::tc::hybrid(g)),
             Box::new(|| {}),
             Box::new(|| {}),
         );
     }
 
-    pub fn benchmark_kernel_cc(&self) {
+    pub fn benchmark_kernel_cc(&self, num_trials: usize) {
         benchmark_kernel(
             self.graph,
+            num_trials,
             Box::new(|g: &G| {
                 crate::cc::afforest(g, None);
             }),
@@ -89,9 +92,10 @@ impl<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>> SourcePicker<'a, V, E, G> {
     }
 }
 
-/// Benchmarks a given `kernel`
+/// Benchmarks a given `kernel`, running it `num_trials` times
 pub fn benchmark_kernel<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
     graph: &G,
+    num_trials: usize,
     mut kernel: GraphFunc<G>,
     stats: AnalysisFunc,
     verify: VerifyFunc,
@@ -99,16 +103,17 @@ pub fn benchmark_kernel<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
     // graph.print_stats();
     let mut timer = crate::timer::ScopedTimer::new("BENCHMARK");
 
-    for i in 1..=NUM_TRIALS {
+    for i in 1..=num_trials {
         timer.checkpoint(&format!("Trial {}", i));
         let result = kernel(&graph);
         timer.elapsed_since_checkpoint();
     }
 }
 
-/// Benchmarks a given `kernel`
+/// Benchmarks a given `kernel`, running it `num_trials` times
 pub fn benchmark_kernel_with_sp<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
     graph: &'a G,
+    num_trials: usize,
     source_picker: &'a mut SourcePicker<'a, V, E, G>,
     mut kernel: GraphFuncTwo<G, SourcePicker<'a, V, E, G>>,
     stats: AnalysisFunc,
@@ -117,7 +122,7 @@ pub fn benchmark_kernel_with_sp<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
     // graph.print_stats();
     let mut timer = crate::timer::ScopedTimer::new("BENCHMARK");
 
-    for i in 1..=NUM_TRIALS {
+    for i in 1..=num_trials {
         timer.checkpoint(&format!("Trial {}", i));
         let result = kernel(&graph, source_picker);
         timer.elapsed_since_checkpoint();
@@ -32,7 +32,7 @@ use rayon::slice::ParallelSlice;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VisitStatus {
     Negative(NodeId),
     Positive(NodeId),
@@ -125,7 +125,11 @@ fn bitmap_to_queue<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
     queue.slide_window();
 }
 
-pub fn do_bfs<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(graph: &G, source: NodeId) {
+/// Returns the parent of every vertex that was reached from `source`
+pub fn do_bfs<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
+    graph: &G,
+    source: NodeId,
+) -> Vec<VisitStatus> {
     const ALPHA: usize = 15;
     const BETA: usize = 18;
     // let timer = crate::timer::ScopedTimer::new("Init Parent");
@@ -167,4 +171,6 @@ pub fn do_bfs<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(graph: &G, source: No
             queue.slide_window();
         }
     }
+
+    parent
 }
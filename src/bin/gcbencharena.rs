@@ -82,7 +82,15 @@ fn MakeTree(iDepth: i32, graph: &SharedArena) -> Index {
 //     }
 // }
 
-fn PrintDiagnostics() {}
+gapbs::counting_allocator!();
+
+fn PrintDiagnostics() {
+    println!(
+        " Allocated memory={}  Peak memory={}",
+        gapbs::memory::Bytes(gapbs::memory::allocated()),
+        gapbs::memory::Bytes(gapbs::memory::peak())
+    );
+}
 
 fn TimeConstruction(depth: i32) {
     let iNumIters = NumIters(depth);
@@ -65,7 +65,15 @@ fn right_depth(depth: u64, n: NodeModel::WrappedNode) -> u64 {
     }
 }
 
-fn PrintDiagnostics() {}
+gapbs::counting_allocator!();
+
+fn PrintDiagnostics() {
+    println!(
+        " Allocated memory={}  Peak memory={}",
+        gapbs::memory::Bytes(gapbs::memory::allocated()),
+        gapbs::memory::Bytes(gapbs::memory::peak())
+    );
+}
 
 fn TimeConstruction(depth: i32) {
     let iNumIters = NumIters(depth);
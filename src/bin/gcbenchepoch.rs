@@ -45,13 +45,13 @@ fn Populate<'a>(
         };
 
         unsafe {
-            Graph::connect(
+            graph.connect(
                 thisNode.load(Ordering::SeqCst, guard).as_ref().unwrap(),
                 edge_info_l,
                 false,
             );
 
-            Graph::connect(
+            graph.connect(
                 thisNode.load(Ordering::SeqCst, guard).as_ref().unwrap(),
                 edge_info_r,
                 false,
@@ -88,13 +88,13 @@ fn MakeTree<'a>(
         };
 
         unsafe {
-            Graph::connect(
+            graph.connect(
                 result.load(Ordering::SeqCst, guard).as_ref().unwrap(),
                 edge_info_l,
                 false,
             );
 
-            Graph::connect(
+            graph.connect(
                 result.load(Ordering::SeqCst, guard).as_ref().unwrap(),
                 edge_info_r,
                 false,
@@ -131,7 +131,15 @@ fn MakeTree<'a>(
 //     }
 // }
 
-fn PrintDiagnostics() {}
+gapbs::counting_allocator!();
+
+fn PrintDiagnostics() {
+    println!(
+        " Allocated memory={}  Peak memory={}",
+        gapbs::memory::Bytes(gapbs::memory::allocated()),
+        gapbs::memory::Bytes(gapbs::memory::peak())
+    );
+}
 
 fn TimeConstruction<'a>(depth: i32, graph: &'a Graph<'a, usize>) {
     let iNumIters = NumIters(depth);
@@ -12,11 +12,15 @@ use criterion_macro::criterion;
 
 use gapbs::benchmark::{benchmark_kernel, benchmark_kernel_with_sp, SourcePicker};
 use gapbs::bfs;
-use gapbs::builder::BuilderBase;
+use gapbs::builder::{BuilderBase, BuilderConfig};
+use gapbs::generator::Generator;
 use gapbs::graph::CSRGraph;
 use gapbs::graphmodels;
+use gapbs::memory::MemoryScope;
 use gapbs::types::*;
 
+gapbs::counting_allocator!();
+
 // type Graph<'a> = graphmodels::epoch::Graph<'a, usize>;
 type Graph = graphmodels::arena::Graph<usize>;
 
@@ -58,6 +62,7 @@ fn main() {
     println!("Breadth-First Search (BFS) - direction optimizing");
     benchmark_kernel_with_sp(
         &graph,
+        builder.config().num_trials,
         &mut source_picker1,
         Box::new(|g: &Graph, sp| {
             gapbs::bfs::do_bfs(g, sp.pick_next());
@@ -108,27 +113,46 @@ fn main() {
     // );
 }
 
+/// Prints the bytes allocated while a benchmark ran,
+/// and the estimated memory usage of the graph it built
+macro_rules! report_memory {
+    ($name: tt, $memory: expr, $usage: expr) => {{
+        $memory.report($name);
+        if let Some(usage) = $usage {
+            println!("{:<12} {}", "", usage);
+        }
+    }};
+}
+
 macro_rules! bench_generate {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
 
             b.iter(|| {
                 let graph: graphmodel::Graph<usize> = builder.make_graph();
+                // Only measured in the first iteration, which is a warm-up
+                usage.get_or_insert_with(|| graph.memory_usage());
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_ops {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             b.iter_batched(
                 || {
                     let mut builder = BuilderBase::new();
                     let graph: graphmodel::Graph<usize> = builder.make_graph();
+                    usage.get_or_insert_with(|| graph.memory_usage());
                     graph
                 },
                 move |graph| {
@@ -137,17 +161,20 @@ macro_rules! bench_ops {
                 BatchSize::SmallInput,
             )
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_ops_epoch_mt {
     ($name: tt, $group: expr) => {{
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             b.iter_batched(
                 || {
                     let mut builder = BuilderBase::new();
                     let graph: gapbs::graphmodels::epoch::Graph<usize> = builder.make_graph();
-
+                    usage.get_or_insert_with(|| graph.memory_usage());
                     graph
                 },
                 move |graph| {
@@ -156,16 +183,20 @@ macro_rules! bench_ops_epoch_mt {
                 BatchSize::SmallInput,
             )
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_ops_epoch {
     ($name: tt, $group: expr) => {{
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             b.iter_batched(
                 || {
                     let mut builder = BuilderBase::new();
                     let graph: gapbs::graphmodels::epoch::Graph<usize> = builder.make_graph();
+                    usage.get_or_insert_with(|| graph.memory_usage());
                     graph
                 },
                 move |graph| {
@@ -174,17 +205,46 @@ macro_rules! bench_ops_epoch {
                 BatchSize::SmallInput,
             )
         });
+        report_memory!($name, memory, usage);
+    }};
+}
+
+macro_rules! bench_ops_epoch_misses {
+    ($name: tt, $bloom: expr, $group: expr) => {{
+        let memory = MemoryScope::new();
+        let mut usage = None;
+        $group.bench_function($name, |b| {
+            b.iter_batched(
+                || {
+                    let mut builder = BuilderBase::new();
+                    let mut graph: gapbs::graphmodels::epoch::Graph<usize> = builder.make_graph();
+                    if $bloom {
+                        graph.enable_bloom_filter(0.01);
+                    }
+                    usage.get_or_insert_with(|| graph.memory_usage());
+                    graph
+                },
+                move |graph| {
+                    gapbs::ops::ops_epoch_misses(&graph);
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_ops_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             b.iter_batched(
                 || {
                     let mut builder = BuilderBase::new();
                     let graph: graphmodel::Graph<usize> = builder.make_graph();
+                    usage.get_or_insert_with(|| graph.memory_usage());
                     graph
                 },
                 move |graph| {
@@ -193,20 +253,25 @@ macro_rules! bench_ops_mt {
                 BatchSize::SmallInput,
             )
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_bfs {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
 
             b.iter(|| {
                 let mut source_picker = SourcePicker::new(&graph);
                 benchmark_kernel_with_sp(
                     &graph,
+                    builder.config().num_trials,
                     &mut source_picker,
                     Box::new(|g, mut sp| {
                         gapbs::bfs::do_bfs(g, sp.pick_next());
@@ -216,20 +281,25 @@ macro_rules! bench_bfs {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_sssp {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
 
             b.iter(|| {
                 let mut source_picker = SourcePicker::new(&graph);
                 benchmark_kernel_with_sp(
                     &graph,
+                    builder.config().num_trials,
                     &mut source_picker,
                     Box::new(|g, sp| {
                         gapbs::sssp::delta_step(g, sp.pick_next(), 1);
@@ -239,20 +309,25 @@ macro_rules! bench_sssp {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_sssp_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
 
             b.iter(|| {
                 let mut source_picker = SourcePicker::new(&graph);
                 benchmark_kernel_with_sp(
                     &graph,
+                    builder.config().num_trials,
                     &mut source_picker,
                     Box::new(|g, sp| {
                         gapbs::sssp::delta_step_mt(g, sp.pick_next(), 1);
@@ -262,70 +337,88 @@ macro_rules! bench_sssp_mt {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_tc {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
-                source_picker.benchmark_kernel_tc();
+                source_picker.benchmark_kernel_tc(builder.config().num_trials);
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_tc_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
                 benchmark_kernel(
                     &graph,
+                    builder.config().num_trials,
                     Box::new(|g: &graphmodel::Graph<usize>| gapbs::tc::hybrid_mt(g)),
                     Box::new(|| {}),
                     Box::new(|| {}),
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_cc {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
-                source_picker.benchmark_kernel_cc();
+                source_picker.benchmark_kernel_cc(builder.config().num_trials);
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_cc_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
                 benchmark_kernel(
                     &graph,
+                    builder.config().num_trials,
                     Box::new(|g: &graphmodel::Graph<usize>| {
                         gapbs::cc::afforest_mt(g, None);
                     }),
@@ -334,35 +427,44 @@ macro_rules! bench_cc_mt {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_pr {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
-                source_picker.benchmark_kernel_pr();
+                source_picker.benchmark_kernel_pr(builder.config().num_trials);
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_pr_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
             let source_picker = SourcePicker::new(&graph);
 
             b.iter(|| {
                 benchmark_kernel(
                     &graph,
+                    builder.config().num_trials,
                     Box::new(|g: &graphmodel::Graph<usize>| {
                         gapbs::pr::page_rank_pull_mt(g, 20, Some(0.0004));
                     }),
@@ -371,20 +473,25 @@ macro_rules! bench_pr_mt {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_bc {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
 
             b.iter(|| {
                 let mut source_picker = SourcePicker::new(&graph);
                 benchmark_kernel_with_sp(
                     &graph,
+                    builder.config().num_trials,
                     &mut source_picker,
                     Box::new(|g: &graphmodel::Graph<usize>, mut sp| {
                         gapbs::bc::brandes(g, &mut sp, 1);
@@ -394,20 +501,25 @@ macro_rules! bench_bc {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
 macro_rules! bench_bc_mt {
     ($name: tt, $graphmodel: path, $group: expr) => {{
         use $graphmodel as graphmodel;
+        let memory = MemoryScope::new();
+        let mut usage = None;
         $group.bench_function($name, |b| {
             let mut builder = BuilderBase::new();
             let graph: graphmodel::Graph<usize> = builder.make_graph();
+            usage.get_or_insert_with(|| graph.memory_usage());
 
             b.iter(|| {
                 let mut source_picker = SourcePicker::new(&graph);
                 benchmark_kernel_with_sp(
                     &graph,
+                    builder.config().num_trials,
                     &mut source_picker,
                     Box::new(|g: &graphmodel::Graph<usize>, mut sp| {
                         gapbs::bc::brandes_mt(g, &mut sp, 1);
@@ -417,6 +529,7 @@ macro_rules! bench_bc_mt {
                 );
             })
         });
+        report_memory!($name, memory, usage);
     }};
 }
 
@@ -450,6 +563,14 @@ fn bench_ops(c: &mut Criterion) {
     bench_ops!("ARENA", graphmodels::arena, group);
 }
 
+#[cfg(feature = "bloom")]
+#[criterion(custom_criterion())]
+fn bench_bloom(c: &mut Criterion) {
+    let mut group = c.benchmark_group("OPS_MISSES");
+    bench_ops_epoch_misses!("EPOCH", false, group);
+    bench_ops_epoch_misses!("EPOCH_bloom", true, group);
+}
+
 #[cfg(feature = "bfs")]
 #[criterion(custom_criterion())]
 fn bench_bfs(c: &mut Criterion) {
@@ -462,6 +583,8 @@ fn bench_bfs(c: &mut Criterion) {
     bench_bfs!("CC", graphmodels::cc, group);
     bench_bfs!("GC", graphmodels::gc, group);
     bench_bfs!("ARENA", graphmodels::arena, group);
+    bench_bfs!("CSR", graphmodels::csr, group);
+    bench_bfs!("MMAP", graphmodels::mmap, group);
     bench_bfs!("EPOCH", graphmodels::epoch, group);
 }
 
@@ -475,6 +598,8 @@ fn bench_sssp(c: &mut Criterion) {
     bench_sssp!("CC", graphmodels::cc, group);
     bench_sssp!("GC", graphmodels::gc, group);
     bench_sssp!("ARENA", graphmodels::arena, group);
+    bench_sssp!("CSR", graphmodels::csr, group);
+    bench_sssp!("MMAP", graphmodels::mmap, group);
     // bench_sssp_mt!("EPOCH_mt", graphmodels::epoch, group);
     bench_sssp!("EPOCH", graphmodels::epoch, group);
 }
@@ -489,6 +614,8 @@ fn bench_pr(c: &mut Criterion) {
     bench_pr!("CC", graphmodels::cc, group);
     bench_pr!("GC", graphmodels::gc, group);
     bench_pr!("ARENA", graphmodels::arena, group);
+    bench_pr!("CSR", graphmodels::csr, group);
+    bench_pr!("MMAP", graphmodels::mmap, group);
     bench_pr!("EPOCH", graphmodels::epoch, group);
     bench_pr_mt!("EPOCH_mt", graphmodels::epoch, group);
 }
@@ -504,6 +631,8 @@ fn bench_cc(c: &mut Criterion) {
     bench_cc!("CC", graphmodels::cc, group);
     bench_cc!("GC", graphmodels::gc, group);
     bench_cc!("ARENA", graphmodels::arena, group);
+    bench_cc!("CSR", graphmodels::csr, group);
+    bench_cc!("MMAP", graphmodels::mmap, group);
     bench_cc!("EPOCH", graphmodels::epoch, group);
     bench_cc_mt!("EPOCH_mt", graphmodels::epoch, group);
 }
@@ -518,6 +647,8 @@ fn bench_bc(c: &mut Criterion) {
     bench_bc!("CC", graphmodels::cc, group);
     bench_bc!("GC", graphmodels::gc, group);
     bench_bc!("ARENA", graphmodels::arena, group);
+    bench_bc!("CSR", graphmodels::csr, group);
+    bench_bc!("MMAP", graphmodels::mmap, group);
     bench_bc!("EPOCH", graphmodels::epoch, group);
     // bench_bc_mt!("EPOCH_mt", graphmodels::epoch, group);
 }
@@ -533,6 +664,39 @@ fn bench_tc(c: &mut Criterion) {
     bench_tc!("CC", graphmodels::cc, group);
     bench_tc!("GC", graphmodels::gc, group);
     bench_tc!("ARENA", graphmodels::arena, group);
+    bench_tc!("CSR", graphmodels::csr, group);
+    bench_tc!("MMAP", graphmodels::mmap, group);
     bench_tc!("EPOCH", graphmodels::epoch, group);
     bench_tc_mt!("EPOCH_mt", graphmodels::epoch, group);
 }
+
+#[cfg(feature = "mdlist")]
+#[criterion(custom_criterion())]
+fn bench_mdlist(c: &mut Criterion) {
+    use graphmodels::epoch::MDListConfig;
+
+    let mut group = c.benchmark_group("MDLIST");
+    let config = BuilderConfig::default();
+    let edge_list = Generator::with_config(&config).generate(&config.family);
+    let num_nodes = BuilderBase::find_max_node_id(&edge_list) + 1;
+
+    for dimension in &[2, 4, 8, 12, 16] {
+        let md_config = MDListConfig::with_dimension(num_nodes, *dimension);
+        let graph: graphmodels::epoch::Graph<usize> =
+            graphmodels::epoch::Graph::bulk_load_with_config(
+                num_nodes, &edge_list, true, md_config,
+            );
+
+        let name = format!("EPOCH_d{}_b{}", md_config.dimension, md_config.basis);
+        println!("{:<12} {}", name, graph.memory_usage());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let guard = &crossbeam_epoch::pin();
+                let view = graph.read(guard);
+                for (v, e, _) in &edge_list {
+                    black_box(view.has_out_edge(*v, *e));
+                }
+            })
+        });
+    }
+}
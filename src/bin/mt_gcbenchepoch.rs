@@ -136,7 +136,15 @@ fn MakeTree<'a>(
 //     }
 // }
 
-fn PrintDiagnostics() {}
+gapbs::counting_allocator!();
+
+fn PrintDiagnostics() {
+    println!(
+        " Allocated memory={}  Peak memory={}",
+        gapbs::memory::Bytes(gapbs::memory::allocated()),
+        gapbs::memory::Bytes(gapbs::memory::peak())
+    );
+}
 
 fn TimeConstruction<'a>(depth: i32, graph: &'a Graph<'a, usize>) {
     let iNumIters = NumIters(depth);
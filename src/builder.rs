@@ -1,4 +1,5 @@
-use crate::generator::Generator;
+use crate::formats::{self, gap, gap::Serialized};
+use crate::generator::{Family, Generator, RmatConfig};
 use crate::graph::CSRGraph;
 use crate::types::*;
 use rayon::iter::IndexedParallelIterator;
@@ -7,25 +8,231 @@ use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::IntoParallelRefMutIterator;
 use rayon::iter::ParallelExtend;
 use rayon::iter::ParallelIterator;
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Number of degrees that a thread sums at a time in `BuilderBase::prefix_sum`
+const PREFIX_SUM_BLOCK: usize = 1 << 20;
+
+/// Describes the graph that `BuilderBase::make_graph` builds, and how often kernels are run on it.
+/// The default is a directed uniform graph with `2^8` vertices, 10 edges per vertex and weighted edges.
+#[derive(Clone, Debug)]
+pub struct BuilderConfig {
+    /// The generated graph has `2^scale` vertices
+    pub scale: usize,
+    /// Average number of edges per vertex of the generated graph, i.e. the edge factor
+    pub degree: usize,
+    /// Builds an undirected graph, by adding the reverse of every edge, if set,
+    /// and a directed graph otherwise
+    pub symmetrize: bool,
+    /// Kind of graph that is generated
+    pub family: Family,
+    /// Initiator, noise and permutation of an R-MAT graph
+    pub rmat: RmatConfig,
+    /// Assigns a random weight to every edge
+    pub needs_weights: bool,
+    /// Loads the edge list from this file, instead of generating it. The format is picked
+    /// from its extension, and decides if the graph is directed and has its own weights.
+    pub file_name: Option<String>,
+    /// Builds the transposed in-edges of a directed graph, which kernels that pull need.
+    /// Has no effect on an undirected graph, where the in-edges are the out-edges.
+    pub invert: bool,
+    /// Number of times a kernel is run by a benchmark
+    pub num_trials: usize,
+    /// Seed of the generated edge list and its weights
+    pub seed: usize,
+}
+
+impl Default for BuilderConfig {
+    fn default() -> Self {
+        Self {
+            scale: 8,
+            degree: 10,
+            symmetrize: false,
+            family: Family::Uniform,
+            rmat: RmatConfig::default(),
+            needs_weights: true,
+            file_name: None,
+            invert: true,
+            num_trials: 1,
+            seed: crate::K_RAND_SEED,
+        }
+    }
+}
+
+impl BuilderConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    pub fn symmetrize(mut self, symmetrize: bool) -> Self {
+        self.symmetrize = symmetrize;
+        self
+    }
+
+    pub fn family(mut self, family: Family) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Generates a uniform random graph if set, and an R-MAT graph otherwise
+    pub fn uniform(mut self, uniform: bool) -> Self {
+        self.family = if uniform {
+            Family::Uniform
+        } else {
+            Family::Rmat
+        };
+        self
+    }
+
+    pub fn rmat(mut self, rmat: RmatConfig) -> Self {
+        self.rmat = rmat;
+        self
+    }
+
+    pub fn needs_weights(mut self, needs_weights: bool) -> Self {
+        self.needs_weights = needs_weights;
+        self
+    }
+
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn num_trials(mut self, num_trials: usize) -> Self {
+        self.num_trials = num_trials;
+        self
+    }
+
+    pub fn seed(mut self, seed: usize) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// The edges of a graph in compressed sparse row format, as built by `BuilderBase::make_csr`.
+/// The neighbours of `v`, and the weights of the edges to them,
+/// are `neighs[offsets[v]..offsets[v + 1]]`.
+#[derive(Clone, Debug, Default)]
+pub struct Csr {
+    pub offsets: Vec<usize>,
+    pub neighs: Vec<(DestId, Option<Weight>)>,
+}
+
+impl Csr {
+    pub fn num_nodes(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.neighs.len()
+    }
+
+    pub fn degree(&self, v: NodeId) -> usize {
+        self.offsets[v + 1] - self.offsets[v]
+    }
+
+    pub fn neighs(&self, v: NodeId) -> &[(DestId, Option<Weight>)] {
+        &self.neighs[self.offsets[v]..self.offsets[v + 1]]
+    }
+
+    /// Lists the edges `(v, e, w)` of every vertex, in the order they are stored
+    pub fn edge_list(&self) -> EdgeList {
+        let mut edge_list = Vec::with_capacity(self.num_edges());
+        edge_list.par_extend(
+            (0..self.num_nodes())
+                .into_par_iter()
+                .flat_map(|v| self.neighs(v).par_iter().map(move |&(e, w)| (v, e, w))),
+        );
+
+        edge_list
+    }
+
+    /// Sorts the neighbours of every vertex, and removes self-references and parallel edges.
+    ///
+    /// Parallel edges keep their smallest weight, so the result does not depend on the order
+    /// the neighbours were filled in, and both sides of an undirected edge keep the same weight.
+    pub fn squish(&self) -> Csr {
+        let neighs: Vec<Vec<_>> = (0..self.num_nodes())
+            .into_par_iter()
+            .map(|v| {
+                let mut neighs = self.neighs(v).to_vec();
+                neighs.sort_unstable();
+                neighs.dedup_by_key(|(e, _)| *e);
+                neighs.retain(|(e, _)| *e != v);
+                neighs
+            })
+            .collect();
+
+        let degrees: Vec<usize> = neighs.par_iter().map(Vec::len).collect();
+        let offsets = BuilderBase::prefix_sum(&degrees);
+        let mut squished = Vec::with_capacity(offsets[degrees.len()]);
+        squished.par_extend(neighs.into_par_iter().flatten());
+
+        Csr {
+            offsets,
+            neighs: squished,
+        }
+    }
+}
+
+/// Lets the threads of `BuilderBase::make_csr` write to disjoint slots of one `Vec`
+struct Slots<T>(*mut T);
+
+unsafe impl<T: Send> Sync for Slots<T> {}
+
+impl<T> Slots<T> {
+    /// The caller must own slot `i`, no other thread may read or write it
+    unsafe fn write(&self, i: usize, value: T) {
+        self.0.add(i).write(value);
+    }
+}
+
 pub struct BuilderBase {
-    symmetrize: bool,
-    needs_weights: bool,
+    config: BuilderConfig,
     num_nodes: Option<usize>,
 }
 
 impl BuilderBase {
     pub fn new() -> Self {
+        Self::with_config(BuilderConfig::default())
+    }
+
+    pub fn with_config(config: BuilderConfig) -> Self {
         Self {
-            symmetrize: SYMMETRIZE,
+            config,
             num_nodes: None,
-            needs_weights: NEEDS_WEIGHTS, // FIXME:  This is wrong
         }
     }
 
-    pub fn get_source(e: Edge) -> DestId {
-        e.0 // FIXME: e.1 ?
+    pub fn config(&self) -> &BuilderConfig {
+        &self.config
+    }
+
+    /// Sets the number of vertices, instead of deriving it from the largest id in the edge list
+    pub fn set_num_nodes(&mut self, num_nodes: usize) {
+        self.num_nodes = Some(num_nodes);
+    }
+
+    /// The vertex that `e` leaves
+    pub fn get_source(e: Edge) -> NodeId {
+        e.0
     }
 
     pub fn find_max_node_id(edge_list: &EdgeList) -> usize {
@@ -38,6 +245,8 @@ impl BuilderBase {
         max_seen
     }
 
+    /// Counts the out-edges of every vertex, or the in-edges if `transpose` is set.
+    /// Every edge is counted in both directions if the graph is symmetrized.
     pub fn count_degrees(&self, edge_list: &EdgeList, transpose: bool) -> Vec<usize> {
         let mut degrees: Vec<AtomicUsize> = (0..self.num_nodes.expect("`num_nodes` is not set"))
             .into_iter()
@@ -45,11 +254,11 @@ impl BuilderBase {
             .collect();
 
         edge_list.par_iter().for_each(|e| {
-            if self.symmetrize || (!self.symmetrize && !transpose) {
+            if self.config.symmetrize || (!self.config.symmetrize && !transpose) {
                 degrees[e.0].fetch_add(1, Ordering::SeqCst);
             }
 
-            if self.symmetrize || (!self.symmetrize && transpose) {
+            if self.config.symmetrize || (!self.config.symmetrize && transpose) {
                 degrees[e.1].fetch_add(1, Ordering::SeqCst);
             }
         });
@@ -57,6 +266,92 @@ impl BuilderBase {
         degrees.drain(..).map(|d| d.into_inner()).collect()
     }
 
+    /// Sums `degrees` in parallel, into the offset of every vertex followed by the total,
+    /// so that the edges of `v` are at `offsets[v]..offsets[v + 1]`
+    pub fn prefix_sum(degrees: &[usize]) -> Vec<usize> {
+        let block_sums: Vec<usize> = degrees
+            .par_chunks(PREFIX_SUM_BLOCK)
+            .map(|block| block.iter().sum())
+            .collect();
+
+        let mut block_offsets = Vec::with_capacity(block_sums.len());
+        let mut total = 0;
+        for sum in block_sums {
+            block_offsets.push(total);
+            total += sum;
+        }
+
+        let mut offsets = vec![0; degrees.len() + 1];
+        offsets[..degrees.len()]
+            .par_chunks_mut(PREFIX_SUM_BLOCK)
+            .zip(degrees.par_chunks(PREFIX_SUM_BLOCK))
+            .zip(block_offsets.par_iter())
+            .for_each(|((offsets, degrees), &start)| {
+                let mut sum = start;
+                for (offset, degree) in offsets.iter_mut().zip(degrees) {
+                    *offset = sum;
+                    sum += degree;
+                }
+            });
+        offsets[degrees.len()] = total;
+
+        offsets
+    }
+
+    /// Builds the out-edges of every vertex, or the in-edges if `transpose` is set, in parallel.
+    /// The degrees are counted first, so that every thread can claim a slot
+    /// for each of its edges in the neighbours of the vertex.
+    ///
+    /// The neighbours are neither sorted nor squished, see `Csr::squish`.
+    pub fn make_csr(&self, edge_list: &EdgeList, transpose: bool) -> Csr {
+        let degrees = self.count_degrees(edge_list, transpose);
+        let offsets = Self::prefix_sum(&degrees);
+        let cursors: Vec<AtomicUsize> = offsets[..degrees.len()]
+            .iter()
+            .map(|offset| AtomicUsize::new(*offset))
+            .collect();
+
+        let mut neighs = vec![(0, None); offsets[degrees.len()]];
+        let slots = Slots(neighs.as_mut_ptr());
+        let symmetrize = self.config.symmetrize;
+
+        // Every slot is claimed once, from the cursor of the vertex the slot belongs to
+        edge_list.par_iter().for_each(|e| {
+            if symmetrize || !transpose {
+                let slot = cursors[e.0].fetch_add(1, Ordering::Relaxed);
+                unsafe { slots.write(slot, (e.1, e.2)) };
+            }
+
+            if symmetrize || transpose {
+                let slot = cursors[e.1].fetch_add(1, Ordering::Relaxed);
+                unsafe { slots.write(slot, (Self::get_source(*e), e.2)) };
+            }
+        });
+
+        Csr { offsets, neighs }
+    }
+
+    /// Removes self-edges and parallel edges like `Csr::squish`, keeping the smallest weight.
+    /// In a symmetrized graph, `(v, e)` and `(e, v)` are the same edge,
+    /// which is kept as `(v, e)` with `v < e`.
+    pub fn squish_edge_list(&self, edge_list: &EdgeList) -> EdgeList {
+        let mut squished: EdgeList = edge_list
+            .par_iter()
+            .filter(|(v, e, _)| v != e)
+            .map(|&(v, e, w)| {
+                if self.config.symmetrize && e < v {
+                    (e, v, w)
+                } else {
+                    (v, e, w)
+                }
+            })
+            .collect();
+
+        squished.par_sort_unstable();
+        squished.dedup_by_key(|(v, e, _)| (*v, *e));
+        squished
+    }
+
     pub fn make_graph_from_edge_list<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
         &mut self,
         edge_list: &mut EdgeList,
@@ -67,90 +362,285 @@ impl BuilderBase {
             self.num_nodes = Some(Self::find_max_node_id(edge_list) + 1);
         }
 
-        if self.needs_weights {
-            Generator::insert_weights(edge_list)
+        if self.config.needs_weights {
+            Generator::insert_weights(edge_list, self.config.seed)
+        }
+
+        let directed = !self.config.symmetrize;
+        if !G::BUILDS_FROM_CSR {
+            // The model adds the edges one at a time, so the rows would only be read back
+            let num_nodes = self.num_nodes.expect("`num_nodes` is not set");
+            let edge_list = self.squish_edge_list(edge_list);
+            return if !directed {
+                G::build_undirected(num_nodes, &edge_list)
+            } else if self.config.invert {
+                G::build_directed(num_nodes, &edge_list)
+            } else {
+                G::build_directed_without_in_edges(num_nodes, &edge_list)
+            };
         }
 
-        let graph;
-        if self.symmetrize {
-            graph = G::build_directed(
-                self.num_nodes.expect("`num_nodes` is not specified"),
-                edge_list,
-            )
+        let out_edges = self.make_csr(edge_list, false).squish();
+        let in_edges = if directed && self.config.invert {
+            Some(self.make_csr(edge_list, true).squish())
         } else {
-            graph = G::build_undirected(
-                self.num_nodes.expect("`num_nodes` is not specified"),
-                edge_list,
-            )
+            None
+        };
+
+        G::build_from_csr(out_edges, in_edges, directed)
+    }
+
+    /// Builds a graph from a serialized file, whose edges are already squished.
+    /// The file decides if the graph is directed and has weights, none are inserted.
+    pub fn make_graph_from_serialized<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(
+        &mut self,
+        mut graph: Serialized,
+    ) -> G {
+        let timer = crate::timer::ScopedTimer::new("Make Graph");
+
+        if !graph.directed || !self.config.invert {
+            graph.in_edges = None;
+        } else if graph.in_edges.is_none() {
+            let mut builder = BuilderBase::with_config(self.config.clone().symmetrize(false));
+            builder.set_num_nodes(graph.out_edges.num_nodes());
+            let edge_list = graph.out_edges.edge_list();
+            graph.in_edges = Some(builder.make_csr(&edge_list, true).squish());
         }
 
-        graph
+        graph.build()
     }
 
-    fn squish_csr<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(graph: &mut G, transpose: bool) {
-        for v in graph.vertices() {
-            let mut neighs: Vec<_>;
-            if transpose {
-                neighs = graph.in_neigh(v.as_node()).collect();
-                neighs.sort_by(|a, b| a.as_node().partial_cmp(&b.as_node()).unwrap());
-                neighs.dedup_by(|a, b| a.as_node() == b.as_node());
-                neighs.retain(|e| e.as_node() != v.as_node());
-                graph.replace_in_edges(v.as_node(), neighs);
-            } else {
-                neighs = graph.out_neigh(v.as_node()).collect();
-                neighs.sort_by(|a, b| a.as_node().partial_cmp(&b.as_node()).unwrap());
-                neighs.dedup_by(|a, b| a.as_node() == b.as_node());
-                neighs.retain(|e| e.as_node() != v.as_node());
-                graph.replace_out_edges(v.as_node(), neighs);
+    pub fn make_graph<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(&mut self) -> G {
+        if let Some(file_name) = &self.config.file_name {
+            if gap::extension(file_name).is_some() {
+                let graph = gap::read_file(file_name)
+                    .unwrap_or_else(|err| panic!("Could not read {}: {}", file_name, err));
+                return self.make_graph_from_serialized(graph);
             }
+
+            let mut file = formats::read_file(file_name)
+                .unwrap_or_else(|err| panic!("Could not read {}: {}", file_name, err));
+            // The file decides if the graph is directed, and if it has its own weights
+            let config = self
+                .config
+                .clone()
+                .symmetrize(!file.directed)
+                .needs_weights(self.config.needs_weights && !file.weighted);
+            let mut builder = BuilderBase::with_config(config);
+            builder.set_num_nodes(file.num_nodes);
+            return builder.make_graph_from_edge_list(&mut file.edge_list);
         }
+
+        let mut edge_list = Generator::with_config(&self.config).generate(&self.config.family);
+        self.make_graph_from_edge_list(&mut edge_list)
     }
+}
 
-    fn squish_graph<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(&self, graph: &mut G) {
-        Self::squish_csr(graph, false);
-        if graph.directed() {
-            if INVERT {
-                Self::squish_csr(graph, true);
-            }
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+    use crate::graphmodels;
+
+    // Vertex 0 has no edges.
+    // Contains a parallel edge (1, 2) and a self-edge (2, 2).
+    fn edge_list() -> EdgeList {
+        vec![
+            (1, 2, None),
+            (1, 3, None),
+            (2, 3, None),
+            (3, 1, None),
+            (1, 2, None),
+            (2, 2, None),
+        ]
+    }
+
+    fn build<V: AsNode, E: AsNode, G: CSRGraph<V, E>>(symmetrize: bool, invert: bool) -> G {
+        let config = BuilderConfig::new()
+            .needs_weights(false)
+            .symmetrize(symmetrize)
+            .invert(invert);
+        BuilderBase::with_config(config).make_graph_from_edge_list(&mut edge_list())
+    }
+
+    fn neighbours<E: AsNode>(edges: impl Iterator<Item = E>) -> Vec<NodeId> {
+        let mut neighs: Vec<_> = edges.map(|e| e.as_node()).collect();
+        neighs.sort();
+        neighs
+    }
+
+    fn check_directed<V: AsNode, E: AsNode, G: CSRGraph<V, E>>() {
+        let graph: G = build(false, true);
+        assert!(graph.directed());
+        assert_eq!(graph.num_edges(), 4);
+        assert_eq!(graph.num_edges_directed(), 4);
+
+        assert_eq!(graph.out_degree(1), 2);
+        assert_eq!(graph.out_degree(2), 1);
+        assert_eq!(graph.out_degree(3), 1);
+        assert_eq!(neighbours(graph.out_neigh(1)), vec![2, 3]);
+
+        assert_eq!(graph.in_degree(3), 2);
+        assert_eq!(neighbours(graph.in_neigh(1)), vec![3]);
+        assert_eq!(neighbours(graph.in_neigh(2)), vec![1]);
+        assert_eq!(neighbours(graph.in_neigh(3)), vec![1, 2]);
+    }
+
+    fn check_undirected<V: AsNode, E: AsNode, G: CSRGraph<V, E>>() {
+        let graph: G = build(true, false);
+        assert!(!graph.directed());
+        assert_eq!(graph.num_edges(), 3);
+        assert_eq!(graph.num_edges_directed(), 6);
+
+        for v in 1..=3 {
+            assert_eq!(graph.out_degree(v), 2);
+            assert_eq!(graph.in_degree(v), 2);
         }
+        assert_eq!(neighbours(graph.out_neigh(1)), vec![2, 3]);
+        assert_eq!(neighbours(graph.in_neigh(2)), vec![1, 3]);
+        assert_eq!(neighbours(graph.in_neigh(3)), vec![1, 2]);
     }
 
-    pub fn make_graph<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(&mut self) -> G {
-        let mut edge_list;
-        let generator = Generator::new(SCALE, DEGREE);
-        if FILE_NAME != "" {
-            edge_list = generator.generate_edge_list_from_file(FILE_NAME);
-        } else {
-            edge_list = generator.generate_edge_list(UNIFORM);
+    fn check_not_inverted<V: AsNode, E: AsNode, G: CSRGraph<V, E>>() {
+        let graph: G = build(false, false);
+        assert_eq!(graph.num_edges(), 4);
+        assert_eq!(graph.out_degree(1), 2);
+
+        for v in 1..=3 {
+            assert_eq!(graph.in_degree(v), 0);
+            assert_eq!(neighbours(graph.in_neigh(v)), Vec::<NodeId>::new());
         }
+    }
+
+    #[test]
+    fn prefix_sum_of_many_blocks() {
+        let degrees: Vec<usize> = (0..3 * PREFIX_SUM_BLOCK + 7).map(|v| v % 5).collect();
+        let offsets = BuilderBase::prefix_sum(&degrees);
+
+        let mut sum = 0;
+        for (v, degree) in degrees.iter().enumerate() {
+            assert_eq!(offsets[v], sum);
+            sum += degree;
+        }
+        assert_eq!(offsets[degrees.len()], sum);
+    }
+
+    #[test]
+    fn make_csr_squishes_every_vertex() {
+        let mut builder = BuilderBase::with_config(BuilderConfig::new());
+        builder.set_num_nodes(4);
+
+        let out_edges = builder.make_csr(&edge_list(), false).squish();
+        assert_eq!(out_edges.offsets, vec![0, 0, 2, 3, 4]);
+        assert_eq!(out_edges.neighs(1), &[(2, None), (3, None)]);
+
+        let in_edges = builder.make_csr(&edge_list(), true).squish();
+        assert_eq!(in_edges.offsets, vec![0, 0, 1, 2, 4]);
+        assert_eq!(in_edges.neighs(3), &[(1, None), (2, None)]);
+    }
+
+    #[test]
+    fn squish_keeps_the_smallest_weight_of_parallel_edges() {
+        let mut edge_list: EdgeList = (0..1000)
+            .flat_map(|i| vec![(1, 2, Some(100 - i % 50)), (3, 1, Some(2 + i % 7))])
+            .collect();
+        edge_list.push((2, 1, Some(1)));
+
+        let squish = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let mut builder = BuilderBase::with_config(BuilderConfig::new().symmetrize(true));
+            builder.set_num_nodes(4);
+            pool.install(|| builder.make_csr(&edge_list, false).squish())
+        };
+
+        let squished = squish(1);
+        assert_eq!(squished.neighs(1), &[(2, Some(1)), (3, Some(2))]);
+        assert_eq!(squished.neighs(2), &[(1, Some(1))]);
+        assert_eq!(squished.neighs(3), &[(1, Some(2))]);
+
+        for _ in 0..10 {
+            let other = squish(4);
+            assert_eq!(other.offsets, squished.offsets);
+            assert_eq!(other.neighs, squished.neighs);
+        }
+    }
+
+    #[test]
+    fn squish_edge_list_keeps_the_smallest_weight_of_parallel_edges() {
+        let mut edge_list: EdgeList = (0..1000)
+            .flat_map(|i| vec![(1, 2, Some(100 - i % 50)), (3, 1, Some(2 + i % 7))])
+            .collect();
+        edge_list.push((2, 1, Some(1)));
+        edge_list.push((3, 3, Some(0)));
+
+        let builder = BuilderBase::with_config(BuilderConfig::new().symmetrize(true));
+        assert_eq!(
+            builder.squish_edge_list(&edge_list),
+            vec![(1, 2, Some(1)), (1, 3, Some(2))]
+        );
+
+        let builder = BuilderBase::with_config(BuilderConfig::new().symmetrize(false));
+        assert_eq!(
+            builder.squish_edge_list(&edge_list),
+            vec![(1, 2, Some(51)), (2, 1, Some(1)), (3, 1, Some(2))]
+        );
+    }
+
+    #[test]
+    fn make_graph_from_a_file_keeps_the_config() {
+        let file = formats::EdgeFile {
+            directed: false,
+            weighted: true,
+            num_nodes: 5,
+            edge_list: vec![(1, 2, Some(3)), (2, 3, Some(4))],
+        };
+
+        for extension in &["mtx", "wsg"] {
+            let path = std::env::temp_dir().join(format!(
+                "gapbs-builder-{}.{}",
+                std::process::id(),
+                extension
+            ));
+            formats::write_file(&path, &file).unwrap();
+
+            let config = BuilderConfig::new().file_name(path.to_str().unwrap());
+            let mut builder = BuilderBase::with_config(config);
+            for _ in 0..2 {
+                let graph: graphmodels::csr::Graph<usize> = builder.make_graph();
+                assert!(!graph.directed());
+                assert_eq!(graph.num_nodes(), 5);
+            }
+
+            assert!(!builder.config().symmetrize);
+            assert!(builder.config().needs_weights);
+            assert_eq!(builder.num_nodes, None);
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    macro_rules! check_model {
+        ($model:ident, $($check:ident),*) => {
+            mod $model {
+                use super::*;
+                $(
+                    #[test]
+                    fn $check() {
+                        super::$check::<_, _, graphmodels::$model::Graph<usize>>();
+                    }
+                )*
+            }
+        };
+    }
 
-        let mut graph = self.make_graph_from_edge_list(&mut edge_list);
-        self.squish_graph(&mut graph);
-        graph
-    }
-
-    // pub fn make_csr<G: CSRGraph>(
-    //     &self,
-    //     edge_list: &EdgeList,
-    //     transpose: bool,
-    // ) -> G {
-    //     // let degrees = self.count_degrees(edge_list, transpose);
-    //     let num_nodes = self.num_nodes.expect("Num nodes not set");
-    //     let mut neighs = Vec::with_capacity(num_nodes);
-    //     neighs.par_extend(
-    //         edge_list
-    //             .par_iter()
-    //             .map(|e| {
-    //                 if self.symmetrize || (!self.symmetrize && !transpose) {
-    //                     return e.1;
-    //                 }
-
-    //                 if self.symmetrize || (!self.symmetrize && transpose) {
-    //                     unimplemented!("Should call GetSource(e)");
-    //                 }
-
-    //                 unreachable!("OOPS, should not be reachable");
-    //             })
-    //     )
-    // }
+    check_model!(rc, check_directed, check_undirected, check_not_inverted);
+    check_model!(arc, check_directed, check_undirected, check_not_inverted);
+    check_model!(cc, check_directed, check_undirected, check_not_inverted);
+    check_model!(gc, check_directed, check_undirected, check_not_inverted);
+    check_model!(arena, check_directed, check_undirected, check_not_inverted);
+    check_model!(csr, check_directed, check_undirected, check_not_inverted);
+    check_model!(mmap, check_directed, check_undirected, check_not_inverted);
+    check_model!(epoch, check_directed, check_undirected, check_not_inverted);
 }
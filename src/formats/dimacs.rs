@@ -0,0 +1,166 @@
+//! Reads and writes the `.gr` files of the
+//! [9th DIMACS Implementation Challenge](http://www.diag.uniroma1.it/challenge9/format.shtml).
+//!
+//! A line `c ...` is a comment, `p sp <vertices> <arcs>` gives the size of the graph,
+//! and every arc is a line `a <u> <v> <weight>`. The graph is directed and ids start at 1.
+
+use super::{invalid_line, parse_count, parse_id, parse_weight, weight_of, EdgeFile};
+use std::io::{self, BufRead, Write};
+
+pub fn read<R: BufRead>(reader: R) -> io::Result<EdgeFile> {
+    // The number of vertices and arcs, and the line they are on
+    let mut problem = None;
+    let mut edge_list = Vec::new();
+    let mut num_lines = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        num_lines = number;
+
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            None | Some("c") => continue,
+            Some("p") if problem.is_none() => {
+                if parts.next() != Some("sp") {
+                    return Err(invalid_line(number, "expected `p sp <vertices> <arcs>`"));
+                }
+
+                let num_nodes = parse_count(parts.next(), "vertices", number)?;
+                let num_arcs = parse_count(parts.next(), "arcs", number)?;
+                problem = Some((num_nodes, num_arcs, number));
+                edge_list.reserve(num_arcs);
+            }
+            Some("p") => return Err(invalid_line(number, "the graph has two `p` lines")),
+            Some("a") => {
+                let num_nodes = match problem {
+                    Some((num_nodes, _, _)) => num_nodes,
+                    None => return Err(invalid_line(number, "an arc before the `p` line")),
+                };
+
+                let u = parse_id(parts.next(), 2, number)?;
+                let v = parse_id(parts.next(), 3, number)?;
+                if u >= num_nodes || v >= num_nodes {
+                    return Err(invalid_line(
+                        number,
+                        format!("the graph only has {} vertices", num_nodes),
+                    ));
+                }
+
+                let weight = parse_weight(parts.next(), number)?;
+                edge_list.push((u, v, Some(weight)));
+            }
+            Some(kind) => return Err(invalid_line(number, format!("unknown line `{}`", kind))),
+        }
+    }
+
+    let (num_nodes, num_arcs, number) =
+        problem.ok_or_else(|| invalid_line(num_lines, "missing the `p` line"))?;
+    if edge_list.len() != num_arcs {
+        return Err(invalid_line(
+            number,
+            format!(
+                "the graph has {} arcs, but {} are listed",
+                num_arcs,
+                edge_list.len()
+            ),
+        ));
+    }
+
+    Ok(EdgeFile {
+        directed: true,
+        weighted: true,
+        num_nodes,
+        edge_list,
+    })
+}
+
+/// Writes every edge as an arc, an unweighted edge is given the weight 1.
+/// The edges of an undirected graph are written in both directions.
+pub fn write<W: Write>(mut writer: W, file: &EdgeFile) -> io::Result<()> {
+    let num_arcs = if file.directed {
+        file.edge_list.len()
+    } else {
+        file.edge_list.len() * 2
+    };
+    writeln!(writer, "p sp {} {}", file.num_nodes, num_arcs)?;
+
+    for edge in &file.edge_list {
+        let weight = if file.weighted { weight_of(edge)? } else { 1 };
+        writeln!(writer, "a {} {} {}", edge.0 + 1, edge.1 + 1, weight)?;
+        if !file.directed {
+            writeln!(writer, "a {} {} {}", edge.1 + 1, edge.0 + 1, weight)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture;
+
+    fn read_str(file: &str) -> io::Result<EdgeFile> {
+        read(file.as_bytes())
+    }
+
+    #[test]
+    fn reads_fixture() {
+        let file = read_str(&fixture("tiny.gr")).unwrap();
+        assert!(file.directed);
+        assert_eq!(file.num_nodes, 5);
+        assert_eq!(
+            file.edge_list,
+            vec![
+                (0, 1, Some(3)),
+                (0, 2, Some(1)),
+                (1, 2, Some(2)),
+                (2, 3, Some(4)),
+                (3, 4, Some(2)),
+                (4, 0, Some(1)),
+                (2, 1, Some(5)),
+            ]
+        );
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(read(bytes.as_slice()).unwrap(), file);
+    }
+
+    #[test]
+    fn writes_undirected_edges_as_two_arcs() {
+        let file = EdgeFile {
+            directed: false,
+            weighted: false,
+            num_nodes: 2,
+            edge_list: vec![(0, 1, None)],
+        };
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "p sp 2 2\na 1 2 1\na 2 1 1\n"
+        );
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let err = read_str("c arcs\na 1 2 1\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 2: an arc before the `p` line");
+
+        let err = read_str("p sp 2 1\na 1 3 1\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: the graph only has 2 vertices");
+
+        let err = read_str("p sp 2 1\n\na 1 2\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: missing weight");
+
+        let err = read_str("p sp 2 2\na 1 2 1\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: the graph has 2 arcs, but 1 are listed"
+        );
+    }
+}
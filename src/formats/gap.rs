@@ -0,0 +1,469 @@
+//! Reads and writes the serialized graphs of the GAP Benchmark Suite,
+//! `.sg` files without weights and `.wsg` files with them.
+//!
+//! A file holds a built graph, so loading it skips parsing and squishing an edge list.
+//! The header is one byte that is `1` if the graph is directed, the number of out-edges
+//! and the number of vertices, both as an `i64`. It is followed by the offsets (`i64`)
+//! and neighbours (`i32`, and an `i32` weight in a `.wsg`) of the out-edges, and by the
+//! in-edges in the same layout if the graph is directed. Numbers are little-endian.
+
+use super::EdgeFile;
+use crate::builder::{BuilderBase, BuilderConfig, Csr};
+use crate::graph::CSRGraph;
+use crate::types::*;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Layout of a serialized graph, in bytes
+pub const HEADER_SIZE: usize = 1 + 2 * OFFSET_SIZE;
+pub const OFFSET_SIZE: usize = 8;
+pub const ID_SIZE: usize = 4;
+
+/// The header of a serialized graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub directed: bool,
+    /// Number of out-edges, every edge of an undirected graph is stored in both directions
+    pub num_edges: usize,
+    pub num_nodes: usize,
+}
+
+impl Header {
+    /// Parses the first `HEADER_SIZE` bytes of a serialized graph
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the graph is truncated",
+            ));
+        }
+
+        let directed = match bytes[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_data("the first byte is not a bool")),
+        };
+
+        let num_edges = usize::try_from(read_i64(&bytes[1..]))
+            .map_err(|_| invalid_data("the number of edges is negative"))?;
+        let num_nodes = usize::try_from(read_i64(&bytes[1 + OFFSET_SIZE..]))
+            .map_err(|_| invalid_data("the number of vertices is negative"))?;
+
+        Ok(Self {
+            directed,
+            num_edges,
+            num_nodes,
+        })
+    }
+}
+
+/// Size of a neighbour, which is followed by its weight in a `.wsg` file
+pub fn neigh_size(weighted: bool) -> usize {
+    if weighted {
+        2 * ID_SIZE
+    } else {
+        ID_SIZE
+    }
+}
+
+/// A built graph, as it is stored in a serialized file
+#[derive(Clone, Debug)]
+pub struct Serialized {
+    pub directed: bool,
+    pub out_edges: Csr,
+    /// The in-edges of a directed graph, GAP always stores them
+    pub in_edges: Option<Csr>,
+}
+
+impl Serialized {
+    /// Copies the out-edges of every vertex of a built graph, without their weights
+    pub fn from_graph<V, E: AsNode, G: CSRGraph<V, E>>(graph: &G) -> Self {
+        Self::from_neighs(graph, |_| None)
+    }
+
+    /// Copies the out-edges of every vertex of a built graph, with their weights
+    pub fn from_weighted_graph<V, E: AsNode + WeightedEdge, G: CSRGraph<V, E>>(graph: &G) -> Self {
+        Self::from_neighs(graph, |e| Some(e.get_weight()))
+    }
+
+    fn from_neighs<V, E: AsNode, G: CSRGraph<V, E>>(
+        graph: &G,
+        weight: impl Fn(&E) -> Option<Weight>,
+    ) -> Self {
+        let mut edge_list = Vec::with_capacity(graph.num_edges_directed());
+        for v in 0..graph.num_nodes() {
+            edge_list.extend(graph.out_neigh(v).map(|e| (v, e.as_node(), weight(&e))));
+        }
+
+        // The neighbours of some models are not sorted, and the in-edges
+        // are transposed as not every model stores them
+        Self::squish(&edge_list, graph.num_nodes(), graph.directed(), false)
+    }
+
+    /// Builds the squished edges of a file, and the in-edges if it is directed
+    pub fn from_edge_file(file: &EdgeFile) -> Self {
+        Self::squish(
+            &file.edge_list,
+            file.num_nodes,
+            file.directed,
+            !file.directed,
+        )
+    }
+
+    fn squish(edge_list: &EdgeList, num_nodes: usize, directed: bool, symmetrize: bool) -> Self {
+        let config = BuilderConfig::new()
+            .symmetrize(symmetrize)
+            .needs_weights(false);
+        let mut builder = BuilderBase::with_config(config);
+        builder.set_num_nodes(num_nodes);
+
+        let out_edges = builder.make_csr(edge_list, false).squish();
+        let in_edges = if directed {
+            Some(builder.make_csr(edge_list, true).squish())
+        } else {
+            None
+        };
+
+        Self {
+            directed,
+            out_edges,
+            in_edges,
+        }
+    }
+
+    /// Builds a graph of any model, without squishing its edges again
+    pub fn build<V, E, G: CSRGraph<V, E>>(self) -> G {
+        G::build_from_csr(self.out_edges, self.in_edges, self.directed)
+    }
+
+    /// Checks if every edge has a weight
+    pub fn is_weighted(&self) -> bool {
+        std::iter::once(&self.out_edges)
+            .chain(&self.in_edges)
+            .all(|csr| csr.neighs.iter().all(|(_, w)| w.is_some()))
+    }
+}
+
+impl From<Serialized> for EdgeFile {
+    fn from(graph: Serialized) -> Self {
+        let weighted = graph.is_weighted();
+        let mut edge_list = graph.out_edges.edge_list();
+        if !graph.directed {
+            // Every edge is stored in both directions
+            edge_list.retain(|(v, e, _)| v < e);
+        }
+
+        Self {
+            directed: graph.directed,
+            weighted,
+            num_nodes: graph.out_edges.num_nodes(),
+            edge_list,
+        }
+    }
+}
+
+/// Checks if `path` is a `.sg` or `.wsg` file, and if it has weights
+pub fn extension<P: AsRef<Path>>(path: P) -> Option<bool> {
+    let extension = path
+        .as_ref()
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("sg") => Some(false),
+        Some("wsg") => Some(true),
+        _ => None,
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Reads exactly `len` bytes, without allocating them up front
+/// in case the header of a truncated file is wrong
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the graph is truncated",
+        ));
+    }
+
+    Ok(bytes)
+}
+
+fn read_i64(bytes: &[u8]) -> i64 {
+    let mut number = [0; OFFSET_SIZE];
+    number.copy_from_slice(&bytes[..OFFSET_SIZE]);
+    i64::from_le_bytes(number)
+}
+
+fn read_i32(bytes: &[u8]) -> i32 {
+    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Reads a neighbour, and checks that it is a vertex of a graph with `num_nodes` vertices
+pub fn read_id(bytes: &[u8], num_nodes: usize) -> io::Result<NodeId> {
+    match usize::try_from(read_i32(bytes)) {
+        Ok(id) if id < num_nodes => Ok(id),
+        _ => Err(invalid_data("a neighbour is not a vertex of the graph")),
+    }
+}
+
+/// Reads the weight that follows a neighbour in a `.wsg` file
+pub fn read_weight(bytes: &[u8]) -> io::Result<Weight> {
+    Weight::try_from(read_i32(&bytes[ID_SIZE..])).map_err(|_| invalid_data("a weight is negative"))
+}
+
+fn read_csr<R: Read>(
+    reader: &mut R,
+    num_nodes: usize,
+    num_edges: usize,
+    weighted: bool,
+) -> io::Result<Csr> {
+    let offsets = read_bytes(reader, (num_nodes + 1) * OFFSET_SIZE)?
+        .par_chunks_exact(OFFSET_SIZE)
+        .map(|bytes| {
+            usize::try_from(read_i64(bytes)).map_err(|_| invalid_data("an offset is negative"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    if offsets[0] != 0
+        || offsets[num_nodes] != num_edges
+        || offsets
+            .par_windows(2)
+            .any(|offsets| offsets[0] > offsets[1])
+    {
+        return Err(invalid_data(
+            "the offsets do not increase from 0 to the number of edges",
+        ));
+    }
+
+    let size = neigh_size(weighted);
+    let neighs = read_bytes(reader, num_edges * size)?
+        .par_chunks_exact(size)
+        .map(|bytes| {
+            let e = read_id(bytes, num_nodes)?;
+            if !weighted {
+                return Ok((e, None));
+            }
+
+            Ok((e, Some(read_weight(bytes)?)))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(Csr { offsets, neighs })
+}
+
+/// Reads a graph, a directed graph without in-edges is also accepted
+pub fn read<R: BufRead>(mut reader: R, weighted: bool) -> io::Result<Serialized> {
+    let Header {
+        directed,
+        num_edges,
+        num_nodes,
+    } = Header::parse(&read_bytes(&mut reader, HEADER_SIZE)?)?;
+
+    let out_edges = read_csr(&mut reader, num_nodes, num_edges, weighted)?;
+    let in_edges = if directed && !reader.fill_buf()?.is_empty() {
+        Some(read_csr(&mut reader, num_nodes, num_edges, weighted)?)
+    } else {
+        None
+    };
+
+    if !reader.fill_buf()?.is_empty() {
+        return Err(invalid_data("the file continues after the graph"));
+    }
+
+    Ok(Serialized {
+        directed,
+        out_edges,
+        in_edges,
+    })
+}
+
+fn write_csr<W: Write>(writer: &mut W, csr: &Csr, weighted: bool) -> io::Result<()> {
+    for &offset in &csr.offsets {
+        writer.write_all(&(offset as i64).to_le_bytes())?;
+    }
+
+    for &(e, weight) in &csr.neighs {
+        writer.write_all(&(e as i32).to_le_bytes())?;
+        if weighted {
+            let weight = weight.ok_or_else(|| invalid_input("an edge has no weight"))?;
+            let weight = i32::try_from(weight)
+                .map_err(|_| invalid_input("a weight does not fit in an `i32`"))?;
+            writer.write_all(&weight.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a graph, and fails if it has more vertices than GAP's 32-bit ids can hold,
+/// or if `weighted` is set and an edge has no weight
+pub fn write<W: Write>(mut writer: W, graph: &Serialized, weighted: bool) -> io::Result<()> {
+    let num_nodes = i32::try_from(graph.out_edges.num_nodes())
+        .map_err(|_| invalid_input("the vertices do not fit in an `i32`"))?;
+
+    writer.write_all(&[graph.directed as u8])?;
+    writer.write_all(&(graph.out_edges.num_edges() as i64).to_le_bytes())?;
+    writer.write_all(&i64::from(num_nodes).to_le_bytes())?;
+
+    write_csr(&mut writer, &graph.out_edges, weighted)?;
+    if let (true, Some(in_edges)) = (graph.directed, &graph.in_edges) {
+        write_csr(&mut writer, in_edges, weighted)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a `.sg` or `.wsg` file
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Serialized> {
+    let weighted = extension(&path).ok_or_else(|| invalid_input("expected a .sg or .wsg file"))?;
+    read(BufReader::new(File::open(path)?), weighted)
+}
+
+/// Writes a `.sg` or `.wsg` file
+pub fn write_file<P: AsRef<Path>>(path: P, graph: &Serialized) -> io::Result<()> {
+    let weighted = extension(&path).ok_or_else(|| invalid_input("expected a .sg or .wsg file"))?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, graph, weighted)?;
+    writer.flush()
+}
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+    use crate::graphmodels;
+
+    fn serialized(directed: bool) -> Serialized {
+        let edge_list = vec![
+            (0, 1, Some(3)),
+            (0, 2, Some(1)),
+            (1, 2, Some(2)),
+            (2, 0, Some(4)),
+        ];
+        let config = BuilderConfig::new()
+            .symmetrize(!directed)
+            .needs_weights(false);
+        let mut builder = BuilderBase::with_config(config);
+        builder.set_num_nodes(3);
+
+        Serialized {
+            directed,
+            out_edges: builder.make_csr(&edge_list, false).squish(),
+            in_edges: if directed {
+                Some(builder.make_csr(&edge_list, true).squish())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn round_trip(graph: &Serialized, weighted: bool) -> Serialized {
+        let mut bytes = Vec::new();
+        write(&mut bytes, graph, weighted).unwrap();
+        read(bytes.as_slice(), weighted).unwrap()
+    }
+
+    #[test]
+    fn writes_gap_layout() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &serialized(false), false).unwrap();
+
+        // Header, 4 offsets and 6 neighbours
+        assert_eq!(bytes.len(), 17 + 4 * 8 + 6 * 4);
+        assert_eq!(bytes[0], 0);
+        assert_eq!(bytes[1..9], 6i64.to_le_bytes());
+        assert_eq!(bytes[9..17], 3i64.to_le_bytes());
+        assert_eq!(bytes[17 + 8..17 + 16], 2i64.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trips_weights_and_in_edges() {
+        let graph = serialized(true);
+        let read = round_trip(&graph, true);
+        assert!(read.directed);
+        assert!(read.is_weighted());
+        assert_eq!(read.out_edges.offsets, graph.out_edges.offsets);
+        assert_eq!(read.out_edges.neighs, graph.out_edges.neighs);
+
+        let in_edges = read.in_edges.unwrap();
+        assert_eq!(in_edges.neighs, graph.in_edges.unwrap().neighs);
+
+        let read = round_trip(&serialized(false), false);
+        assert!(!read.directed);
+        assert!(read.in_edges.is_none());
+        assert_eq!(read.out_edges.neighs[0], (1, None));
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &serialized(true), false).unwrap();
+
+        let err = read(&bytes[..bytes.len() - 1], false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut wrong = bytes.clone();
+        wrong[17 + 4 * 8] = 7;
+        let err = read(wrong.as_slice(), false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut graph = serialized(true);
+        graph.out_edges.neighs[0].1 = None;
+        let err = write(Vec::new(), &graph, true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn converts_edge_files() {
+        let file = EdgeFile {
+            directed: false,
+            weighted: true,
+            num_nodes: 3,
+            edge_list: vec![
+                (0, 1, Some(3)),
+                (0, 2, Some(1)),
+                (1, 2, Some(2)),
+                (2, 0, Some(4)),
+            ],
+        };
+
+        let graph = Serialized::from_edge_file(&file);
+        assert_eq!(graph.out_edges.offsets, serialized(false).out_edges.offsets);
+        assert_eq!(
+            EdgeFile::from(graph).edge_list,
+            vec![(0, 1, Some(3)), (0, 2, Some(1)), (1, 2, Some(2))]
+        );
+    }
+
+    #[test]
+    fn exports_and_builds_models() {
+        let graph = serialized(true);
+        let rc: graphmodels::rc::Graph<usize> = graph.clone().build();
+        let exported = Serialized::from_weighted_graph(&rc);
+        assert_eq!(exported.out_edges.neighs, graph.out_edges.neighs);
+        assert_eq!(
+            exported.in_edges.unwrap().neighs,
+            graph.in_edges.unwrap().neighs
+        );
+
+        let arena: graphmodels::arena::Graph<usize> = serialized(false).build();
+        let exported = Serialized::from_weighted_graph(&arena);
+        assert_eq!(exported.out_edges.offsets, vec![0, 2, 4, 6]);
+        assert!(!exported.directed);
+    }
+}
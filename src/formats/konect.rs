@@ -0,0 +1,349 @@
+//! Reads the `out.*` files of the [KONECT](http://konect.cc/) collection.
+//!
+//! A file starts with a header, e.g. `% asym positive`, and optionally a line
+//! `% <edges> <vertices> <vertices>` with its size, which counts vertices without edges.
+//! Every other line is an edge `v e`, followed by a weight and a timestamp
+//! if the network has them. Ids start at 1.
+
+use super::{invalid_line, parse_id, parse_real_weight, weight_of, EdgeFile};
+use crate::types::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// How the third column of a KONECT file is read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weights {
+    /// No weights, an edge may be listed more than once (`unweighted`, `multiweighted`)
+    Unweighted,
+    /// Positive integers, e.g. how often an edge occurs (`positive`, `posweighted`, `multiposweighted`)
+    Positive,
+    /// Real numbers (`weighted`, `rating`), which are rounded to the nearest integer
+    Real,
+    /// `+1` or `-1` (`signed`, `multisigned`), the sign cannot be stored as a weight and is dropped
+    Signed,
+    /// The edges are events at the timestamp in the fourth column (`dynamic`)
+    Dynamic,
+    /// The file has no header, the third column is used where it is a positive integer
+    Unknown,
+}
+
+impl Weights {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unweighted" | "multiweighted" => Some(Weights::Unweighted),
+            "positive" | "posweighted" | "multiposweighted" => Some(Weights::Positive),
+            "weighted" | "rating" => Some(Weights::Real),
+            "signed" | "multisigned" => Some(Weights::Signed),
+            "dynamic" => Some(Weights::Dynamic),
+            _ => None,
+        }
+    }
+
+    /// Checks if the edges that are read have weights
+    pub fn is_weighted(&self) -> bool {
+        match self {
+            Weights::Positive | Weights::Real => true,
+            _ => false,
+        }
+    }
+}
+
+/// The first line of a KONECT file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// `asym`, the other formats are undirected
+    pub directed: bool,
+    /// `bip`, the edges connect a vertex on the left to a vertex on the right
+    pub bipartite: bool,
+    pub weights: Weights,
+}
+
+impl Default for Header {
+    /// Used for files without a header
+    fn default() -> Self {
+        Self {
+            directed: true,
+            bipartite: false,
+            weights: Weights::Unknown,
+        }
+    }
+}
+
+impl Header {
+    /// Parses `% <format> <weights>`, returns `None` if `line` is some other comment
+    fn parse(line: &str, number: usize) -> io::Result<Option<Self>> {
+        let mut parts = line.trim_start_matches('%').split_whitespace();
+        let (directed, bipartite) = match parts.next() {
+            Some("sym") => (false, false),
+            Some("asym") => (true, false),
+            Some("bip") => (false, true),
+            _ => return Ok(None),
+        };
+
+        let weights = match parts.next() {
+            Some(name) => Weights::parse(name)
+                .ok_or_else(|| invalid_line(number, format!("unknown weights `{}`", name)))?,
+            None => return Err(invalid_line(number, "the header has no weights")),
+        };
+
+        Ok(Some(Self {
+            directed,
+            bipartite,
+            weights,
+        }))
+    }
+}
+
+/// Parses `% <edges> <left> <right>`, returns `None` if `line` is some other comment.
+/// The left and right vertices are the same in a graph that is not bipartite.
+fn parse_size(line: &str) -> Option<(usize, usize)> {
+    let parts: Vec<&str> = line.trim_start_matches('%').split_whitespace().collect();
+    match parts.as_slice() {
+        [edges, left, right] => {
+            edges.parse::<usize>().ok()?;
+            Some((left.parse().ok()?, right.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// The edges of a KONECT file, with 0-based ids
+#[derive(Clone, Debug)]
+pub struct Konect {
+    pub header: Header,
+    pub num_nodes: usize,
+    pub edge_list: EdgeList,
+}
+
+fn parse_weight(part: Option<&str>, weights: Weights, number: usize) -> io::Result<Option<Weight>> {
+    let part = match (part, weights) {
+        (Some(part), Weights::Positive) | (Some(part), Weights::Real) => part,
+        // Like `Weights::Positive`, but a weight that is not positive is dropped instead of rejected
+        (Some(part), Weights::Unknown) => {
+            return Ok(part.parse::<Weight>().ok().filter(|w| *w > 0))
+        }
+        (None, Weights::Positive) | (None, Weights::Real) => {
+            return Err(invalid_line(number, "missing weight"))
+        }
+        _ => return Ok(None),
+    };
+
+    if weights == Weights::Positive {
+        return match part.parse::<Weight>() {
+            Ok(weight) if weight > 0 => Ok(Some(weight)),
+            _ => Err(invalid_line(
+                number,
+                format!("`{}` is not a positive weight", part),
+            )),
+        };
+    }
+
+    parse_real_weight(Some(part), number).map(Some)
+}
+
+pub fn read<R: BufRead>(reader: R) -> io::Result<Konect> {
+    let mut header = None;
+    let mut size = None;
+    let mut edge_list = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        let line = line.trim();
+
+        if line.starts_with('%') {
+            // Only the first line is a header, and the second its size,
+            // the other comments are skipped
+            if number == 1 {
+                header = Header::parse(line, number)?;
+            } else if number == 2 {
+                size = parse_size(line);
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let weights = header.unwrap_or_default().weights;
+        let mut parts = line.split_whitespace();
+        let v = parse_id(parts.next(), 1, number)?;
+        let e = parse_id(parts.next(), 2, number)?;
+        let weight = parse_weight(parts.next(), weights, number)?;
+        // Timestamps and other columns are not used
+        edge_list.push((v, e, weight));
+    }
+
+    let header = header.unwrap_or_default();
+    let mut num_nodes = 0;
+    if header.bipartite {
+        // The left and right vertices both start at 1, the right ones are placed after the left
+        let num_left = edge_list.iter().map(|e| e.0 + 1).max().unwrap_or(0);
+        let num_left = std::cmp::max(num_left, size.map_or(0, |(left, _)| left));
+        for e in edge_list.iter_mut() {
+            e.1 += num_left;
+        }
+        num_nodes = num_left + size.map_or(0, |(_, right)| right);
+    } else if let Some((left, right)) = size {
+        num_nodes = std::cmp::max(left, right);
+    }
+
+    for e in edge_list.iter() {
+        num_nodes = std::cmp::max(num_nodes, std::cmp::max(e.0, e.1) + 1);
+    }
+
+    Ok(Konect {
+        header,
+        num_nodes,
+        edge_list,
+    })
+}
+
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Konect> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// Writes the edges with ids that start at 1, after the header `% sym` or `% asym`
+/// and the size of the graph
+pub fn write<W: Write>(mut writer: W, file: &EdgeFile) -> io::Result<()> {
+    let format = if file.directed { "asym" } else { "sym" };
+    let weights = if file.weighted {
+        "posweighted"
+    } else {
+        "unweighted"
+    };
+    writeln!(writer, "% {} {}", format, weights)?;
+    writeln!(
+        writer,
+        "% {} {} {}",
+        file.edge_list.len(),
+        file.num_nodes,
+        file.num_nodes
+    )?;
+
+    for edge in &file.edge_list {
+        write!(writer, "{} {}", edge.0 + 1, edge.1 + 1)?;
+        if file.weighted {
+            write!(writer, " {}", weight_of(edge)?)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+impl From<Konect> for EdgeFile {
+    fn from(konect: Konect) -> Self {
+        Self {
+            directed: konect.header.directed,
+            weighted: konect.header.weights.is_weighted(),
+            num_nodes: konect.num_nodes,
+            edge_list: konect.edge_list,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_str(file: &str) -> io::Result<Konect> {
+        read(file.as_bytes())
+    }
+
+    #[test]
+    fn reads_header_and_one_based_ids() {
+        let konect = read_str("% sym unweighted\n% 3 3 3\n1 2\n2 3 \n\n3\t1\n").unwrap();
+        assert!(!konect.header.directed);
+        assert_eq!(konect.header.weights, Weights::Unweighted);
+        assert_eq!(konect.num_nodes, 3);
+        assert_eq!(
+            konect.edge_list,
+            vec![(0, 1, None), (1, 2, None), (2, 0, None)]
+        );
+    }
+
+    #[test]
+    fn reads_weights_and_skips_timestamps() {
+        let konect = read_str("% asym positive\n1 2 3 920588400\n2 1 1 927846000\n").unwrap();
+        assert!(konect.header.directed);
+        assert_eq!(konect.edge_list, vec![(0, 1, Some(3)), (1, 0, Some(1))]);
+
+        let konect = read_str("% asym weighted\n1 2  1.261404\n1 3  21.9353\n").unwrap();
+        assert_eq!(konect.edge_list, vec![(0, 1, Some(1)), (0, 2, Some(22))]);
+
+        let konect = read_str("% sym signed\n1 2 +1\n2 3 -1\n").unwrap();
+        assert_eq!(konect.edge_list, vec![(0, 1, None), (1, 2, None)]);
+    }
+
+    #[test]
+    fn places_right_vertices_after_left() {
+        let konect = read_str("% bip unweighted\n1 1\n2 1\n2 3\n").unwrap();
+        assert!(konect.header.bipartite);
+        assert_eq!(konect.num_nodes, 5);
+        assert_eq!(
+            konect.edge_list,
+            vec![(0, 2, None), (1, 2, None), (1, 4, None)]
+        );
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let err = read_str("% asym unweighted\n1 2\n1 x\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 3: `x` is not an id");
+
+        let err = read_str("1 2\n0 1\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: ids start at 1");
+
+        let err = read_str("% asym positive\n1 2\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: missing weight");
+
+        let err = read_str("% asym heavy\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: unknown weights `heavy`");
+    }
+
+    #[test]
+    fn writes_header_and_one_based_ids() {
+        let file = EdgeFile {
+            directed: false,
+            weighted: true,
+            num_nodes: 3,
+            edge_list: vec![(0, 1, Some(4)), (1, 2, Some(1))],
+        };
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.clone()).unwrap(),
+            "% sym posweighted\n% 2 3 3\n1 2 4\n2 3 1\n"
+        );
+        assert_eq!(EdgeFile::from(read(bytes.as_slice()).unwrap()), file);
+    }
+
+    #[test]
+    fn size_counts_vertices_without_edges() {
+        let konect = read_str("% sym unweighted\n% 2 5 5\n1 2\n2 3\n").unwrap();
+        assert_eq!(konect.num_nodes, 5);
+
+        let konect = read_str("% bip unweighted\n% 2 3 4\n1 1\n2 1\n").unwrap();
+        assert_eq!(konect.num_nodes, 7);
+        assert_eq!(konect.edge_list, vec![(0, 3, None), (1, 3, None)]);
+
+        // Only the second line is the size
+        let konect = read_str("% sym unweighted\n1 2\n% 2 5 5\n").unwrap();
+        assert_eq!(konect.num_nodes, 2);
+    }
+
+    #[test]
+    fn reads_files_without_header() {
+        let konect = read_str("1 2 +2 920588400\n1 3 0\n2 3\n").unwrap();
+        assert_eq!(konect.header, Header::default());
+        assert_eq!(
+            konect.edge_list,
+            vec![(0, 1, Some(2)), (0, 2, None), (1, 2, None)]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Reads and writes the graphs of [METIS](http://glaros.dtc.umn.edu/gkhome/views/metis),
+//! as described in section 4.1.1 of its manual.
+//!
+//! The first line is `<vertices> <edges> [fmt [ncon]]`, and line `i` after it lists the
+//! neighbours of vertex `i`. An empty line is a vertex without neighbours, so only lines
+//! that start with `%` are skipped. The graph is undirected, every edge is listed by both
+//! of its vertices, and ids start at 1.
+
+use super::{invalid_line, parse_count, parse_id, parse_weight, weight_of, EdgeFile};
+use crate::types::*;
+use std::io::{self, BufRead, Write};
+
+/// The first line of a METIS graph
+struct Header {
+    num_nodes: usize,
+    num_edges: usize,
+    /// Every vertex starts with its size, which is skipped
+    sizes: bool,
+    /// Number of weights before the neighbours of a vertex, which are skipped
+    vertex_weights: usize,
+    edge_weights: bool,
+}
+
+impl Header {
+    fn parse(line: &str, number: usize) -> io::Result<Self> {
+        let mut parts = line.split_whitespace();
+        let num_nodes = parse_count(parts.next(), "vertices", number)?;
+        let num_edges = parse_count(parts.next(), "edges", number)?;
+
+        // `fmt` has up to three digits, for sizes, vertex weights and edge weights
+        let fmt = parts.next().unwrap_or("0");
+        if fmt.len() > 3 || fmt.chars().any(|c| c != '0' && c != '1') {
+            return Err(invalid_line(number, format!("`{}` is not a fmt", fmt)));
+        }
+        let fmt = format!("{:0>3}", fmt);
+        let flag = |i: usize| fmt.as_bytes()[i] == b'1';
+
+        let vertex_weights = match parts.next() {
+            Some(ncon) => parse_count(Some(ncon), "vertex weights", number)?,
+            None if flag(1) => 1,
+            None => 0,
+        };
+
+        Ok(Self {
+            num_nodes,
+            num_edges,
+            sizes: flag(0),
+            vertex_weights: if flag(1) { vertex_weights } else { 0 },
+            edge_weights: flag(2),
+        })
+    }
+}
+
+pub fn read<R: BufRead>(reader: R) -> io::Result<EdgeFile> {
+    // The header, and the line it is on
+    let mut header: Option<(Header, usize)> = None;
+    let mut edge_list = Vec::new();
+    let mut v = 0;
+    // Number of neighbours listed, where every edge is listed twice
+    let mut num_listed = 0;
+    let mut num_lines = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        num_lines = number;
+
+        if line.trim_start().starts_with('%') {
+            continue;
+        }
+
+        let header = match &header {
+            Some((header, _)) => header,
+            None if line.trim().is_empty() => continue,
+            None => {
+                header = Some((Header::parse(&line, number)?, number));
+                continue;
+            }
+        };
+
+        if v == header.num_nodes {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Err(invalid_line(
+                number,
+                format!("the graph only has {} vertices", header.num_nodes),
+            ));
+        }
+
+        let skip = header.sizes as usize + header.vertex_weights;
+        let mut parts = line.split_whitespace();
+        for column in 1..=skip {
+            parse_count(parts.next(), &format!("column {}", column), number)?;
+        }
+
+        let mut column = skip + 1;
+        while let Some(part) = parts.next() {
+            let e = parse_id(Some(part), column, number)?;
+            if e >= header.num_nodes {
+                return Err(invalid_line(
+                    number,
+                    format!("the graph only has {} vertices", header.num_nodes),
+                ));
+            }
+
+            if e == v {
+                return Err(invalid_line(number, "a vertex cannot neighbour itself"));
+            }
+
+            let weight = if header.edge_weights {
+                column += 1;
+                Some(parse_weight(parts.next(), number)?)
+            } else {
+                None
+            };
+
+            // The edge is also listed by `e`, only one of them is kept
+            if v < e {
+                edge_list.push((v, e, weight));
+            }
+
+            num_listed += 1;
+            column += 1;
+        }
+
+        v += 1;
+    }
+
+    let (header, number) = header.ok_or_else(|| invalid_line(num_lines, "missing the header"))?;
+    if v < header.num_nodes {
+        return Err(invalid_line(
+            num_lines,
+            format!(
+                "the graph has {} vertices, but {} are listed",
+                header.num_nodes, v
+            ),
+        ));
+    }
+
+    if num_listed != 2 * header.num_edges || edge_list.len() != header.num_edges {
+        return Err(invalid_line(
+            number,
+            format!(
+                "the graph has {} edges, but {} neighbours are listed",
+                header.num_edges, num_listed
+            ),
+        ));
+    }
+
+    Ok(EdgeFile {
+        directed: false,
+        weighted: header.edge_weights,
+        num_nodes: header.num_nodes,
+        edge_list,
+    })
+}
+
+/// Writes the sorted neighbours of every vertex. Self-edges and parallel edges cannot be
+/// stored, and are dropped. A directed graph cannot be written.
+pub fn write<W: Write>(mut writer: W, file: &EdgeFile) -> io::Result<()> {
+    if file.directed {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "METIS only stores undirected graphs",
+        ));
+    }
+
+    let mut neighs: Vec<Vec<(DestId, Option<Weight>)>> = vec![Vec::new(); file.num_nodes];
+    for edge in file.edge_list.iter().filter(|edge| edge.0 != edge.1) {
+        let weight = if file.weighted {
+            Some(weight_of(edge)?)
+        } else {
+            None
+        };
+
+        neighs[edge.0].push((edge.1, weight));
+        neighs[edge.1].push((edge.0, weight));
+    }
+
+    for neighs in neighs.iter_mut() {
+        neighs.sort_by_key(|(e, _)| *e);
+        neighs.dedup_by_key(|(e, _)| *e);
+    }
+
+    let num_edges = neighs.iter().map(Vec::len).sum::<usize>() / 2;
+    if file.weighted {
+        writeln!(writer, "{} {} 001", file.num_nodes, num_edges)?;
+    } else {
+        writeln!(writer, "{} {}", file.num_nodes, num_edges)?;
+    }
+
+    for neighs in neighs {
+        let line: Vec<_> = neighs
+            .iter()
+            .map(|(e, w)| match w {
+                Some(w) => format!("{} {}", e + 1, w),
+                None => format!("{}", e + 1),
+            })
+            .collect();
+        writeln!(writer, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture;
+
+    fn read_str(file: &str) -> io::Result<EdgeFile> {
+        read(file.as_bytes())
+    }
+
+    #[test]
+    fn reads_fixture() {
+        let file = read_str(&fixture("tiny.graph")).unwrap();
+        assert!(!file.directed);
+        assert!(file.weighted);
+        assert_eq!(file.num_nodes, 5);
+        assert_eq!(
+            file.edge_list,
+            vec![
+                (0, 1, Some(3)),
+                (0, 2, Some(1)),
+                (0, 4, Some(1)),
+                (1, 2, Some(2)),
+                (2, 3, Some(4)),
+                (3, 4, Some(2)),
+            ]
+        );
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(read(bytes.as_slice()).unwrap(), file);
+    }
+
+    #[test]
+    fn reads_empty_lines_and_vertex_weights() {
+        let file = read_str("% vertex 2 is isolated\n3 1\n3\n\n1\n").unwrap();
+        assert_eq!(file.num_nodes, 3);
+        assert_eq!(file.edge_list, vec![(0, 2, None)]);
+
+        let file = read_str("3 1 011 2\n1 1 3 5\n2 2\n4 4 1 5\n").unwrap();
+        assert!(file.weighted);
+        assert_eq!(file.edge_list, vec![(0, 2, Some(5))]);
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let err = read_str("2 1\n2\n1 2\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 3: a vertex cannot neighbour itself");
+
+        let err = read_str("2 1\n3\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: the graph only has 2 vertices");
+
+        let err = read_str("3 1\n2\n1\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: the graph has 3 vertices, but 2 are listed"
+        );
+
+        let err = read_str("2 2\n2\n1\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: the graph has 2 edges, but 2 neighbours are listed"
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! Every reader returns `io::Result`, a line that cannot be parsed
+//! is reported as `io::ErrorKind::InvalidData`, with its line number.
+
+use crate::types::*;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Shortest paths graphs of the 9th DIMACS Implementation Challenge
+pub mod dimacs;
+/// Built graphs, serialized as by the GAP Benchmark Suite
+pub mod gap;
+/// The collection of networks from the University of Koblenz
+pub mod konect;
+/// Adjacency lists of the METIS graph partitioner
+pub mod metis;
+/// Sparse matrices in coordinate format, as in the SuiteSparse Matrix Collection
+pub mod mtx;
+/// Edge lists of the Stanford Network Analysis Project
+pub mod snap;
+
+/// The edges of a file, with ids that start at 0
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EdgeFile {
+    /// The edges of an undirected graph are only listed in one direction
+    pub directed: bool,
+    /// Every edge has a weight
+    pub weighted: bool,
+    pub num_nodes: usize,
+    pub edge_list: EdgeList,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Konect,
+    MatrixMarket,
+    Dimacs,
+    Metis,
+    Snap,
+    /// A `.sg` or `.wsg` file of the GAP Benchmark Suite
+    Serialized,
+}
+
+impl Format {
+    /// Picks the format from the extension of `path`,
+    /// a file with any other extension is read as KONECT
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("mtx") => Format::MatrixMarket,
+            Some("gr") => Format::Dimacs,
+            Some("graph") | Some("metis") => Format::Metis,
+            Some("txt") | Some("snap") => Format::Snap,
+            Some("sg") | Some("wsg") => Format::Serialized,
+            _ => Format::Konect,
+        }
+    }
+}
+
+/// Reads a file in the format of its extension
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<EdgeFile> {
+    let reader = BufReader::new(File::open(&path)?);
+    match Format::from_path(&path) {
+        Format::Konect => konect::read(reader).map(EdgeFile::from),
+        Format::MatrixMarket => mtx::read(reader),
+        Format::Dimacs => dimacs::read(reader),
+        Format::Metis => metis::read(reader),
+        Format::Snap => snap::read(reader),
+        Format::Serialized => {
+            gap::read(reader, gap::extension(&path) == Some(true)).map(EdgeFile::from)
+        }
+    }
+}
+
+/// Writes a file in the format of its extension
+pub fn write_file<P: AsRef<Path>>(path: P, file: &EdgeFile) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(&path)?);
+    match Format::from_path(&path) {
+        Format::Konect => konect::write(&mut writer, file)?,
+        Format::MatrixMarket => mtx::write(&mut writer, file)?,
+        Format::Dimacs => dimacs::write(&mut writer, file)?,
+        Format::Metis => metis::write(&mut writer, file)?,
+        Format::Snap => snap::write(&mut writer, file)?,
+        Format::Serialized => {
+            let weighted = gap::extension(&path) == Some(true);
+            gap::write(
+                &mut writer,
+                &gap::Serialized::from_edge_file(file),
+                weighted,
+            )?
+        }
+    }
+
+    writer.flush()
+}
+
+/// An error for line `line` (1-based) of the file that is read
+fn invalid_line(line: usize, msg: impl AsRef<str>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, msg.as_ref()),
+    )
+}
+
+/// Parses column `column` (1-based) of a line as an id that starts at 1, and makes it 0-based
+fn parse_id(part: Option<&str>, column: usize, line: usize) -> io::Result<NodeId> {
+    let part = part.ok_or_else(|| invalid_line(line, format!("missing column {}", column)))?;
+    match part.parse::<NodeId>() {
+        Ok(id) if id > 0 => Ok(id - 1),
+        Ok(_) => Err(invalid_line(line, "ids start at 1")),
+        Err(_) => Err(invalid_line(line, format!("`{}` is not an id", part))),
+    }
+}
+
+/// Parses a number of vertices or edges, named `name` in the error
+fn parse_count(part: Option<&str>, name: &str, line: usize) -> io::Result<usize> {
+    let part = part.ok_or_else(|| invalid_line(line, format!("missing {}", name)))?;
+    part.parse::<usize>()
+        .map_err(|_| invalid_line(line, format!("`{}` is not a number of {}", part, name)))
+}
+
+fn parse_weight(part: Option<&str>, line: usize) -> io::Result<Weight> {
+    let part = part.ok_or_else(|| invalid_line(line, "missing weight"))?;
+    part.parse::<Weight>()
+        .map_err(|_| invalid_line(line, format!("`{}` is not a weight of at least 0", part)))
+}
+
+/// Parses a real weight, and rounds it to the nearest integer
+fn parse_real_weight(part: Option<&str>, line: usize) -> io::Result<Weight> {
+    let part = part.ok_or_else(|| invalid_line(line, "missing weight"))?;
+    match part.parse::<f64>() {
+        Ok(weight) if weight >= 0.0 && weight.is_finite() => Ok(weight.round() as Weight),
+        _ => Err(invalid_line(
+            line,
+            format!("`{}` is not a weight of at least 0", part),
+        )),
+    }
+}
+
+/// The weight of an edge that is written to a weighted file
+fn weight_of(edge: &Edge) -> io::Result<Weight> {
+    edge.2.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the edge ({}, {}) has no weight", edge.0, edge.1),
+        )
+    })
+}
+
+/// Reads a fixture under `datasets/`
+#[cfg(test)]
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("datasets")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_format_from_extension() {
+        assert_eq!(Format::from_path("datasets/tiny.mtx"), Format::MatrixMarket);
+        assert_eq!(Format::from_path("USA-road-d.NY.gr"), Format::Dimacs);
+        assert_eq!(Format::from_path("tiny.graph"), Format::Metis);
+        assert_eq!(Format::from_path("ca-AstroPh.TXT"), Format::Snap);
+        assert_eq!(Format::from_path("twitter.wsg"), Format::Serialized);
+        assert_eq!(Format::from_path("datasets/dolphins.out"), Format::Konect);
+        assert_eq!(Format::from_path("out.dolphins"), Format::Konect);
+    }
+
+    #[test]
+    fn fixtures_are_the_same_graph() {
+        let datasets = Path::new(env!("CARGO_MANIFEST_DIR")).join("datasets");
+        let mut edges: Vec<Vec<_>> = ["tiny.mtx", "tiny.graph", "tiny.txt"]
+            .iter()
+            .map(|name| {
+                let file = read_file(datasets.join(name)).unwrap();
+                assert!(!file.directed);
+                assert_eq!(file.num_nodes, 5);
+                let mut edges: Vec<_> = file
+                    .edge_list
+                    .iter()
+                    .map(|&(v, e, _)| (v.min(e), v.max(e)))
+                    .collect();
+                edges.sort();
+                edges
+            })
+            .collect();
+
+        edges.dedup();
+        assert_eq!(edges.len(), 1);
+    }
+
+    #[test]
+    fn every_format_keeps_an_isolated_last_vertex() {
+        let file = EdgeFile {
+            directed: false,
+            weighted: true,
+            num_nodes: 5,
+            edge_list: vec![(0, 1, Some(2)), (1, 2, Some(3))],
+        };
+
+        for name in &["out", "mtx", "gr", "graph", "txt", "wsg"] {
+            let path = std::env::temp_dir().join(format!(
+                "gapbs-isolated-{}.{}",
+                std::process::id(),
+                name
+            ));
+            write_file(&path, &file).unwrap();
+            let read = read_file(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(read.unwrap().num_nodes, 5, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Reads and writes [Matrix Market](https://math.nist.gov/MatrixMarket/formats.html) files.
+//!
+//! Only sparse matrices in `coordinate` format are graphs, with an entry `i j` for the edge
+//! from vertex `i` to vertex `j`. The entries of a `symmetric` matrix are the edges of an
+//! undirected graph, and only its lower triangle is stored.
+
+use super::EdgeFile;
+use super::{invalid_line, parse_count, parse_id, parse_real_weight, parse_weight, weight_of};
+use std::io::{self, BufRead, Write};
+
+/// The type of the values of the entries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    /// There are no values, only the positions of the entries
+    Pattern,
+    Integer,
+    /// Rounded to the nearest integer
+    Real,
+}
+
+/// Parses `%%MatrixMarket matrix coordinate <field> <symmetry>`,
+/// and returns the field and if the matrix is directed
+fn parse_banner(line: &str, number: usize) -> io::Result<(Field, bool)> {
+    let words: Vec<_> = line.split_whitespace().map(str::to_lowercase).collect();
+    if words.len() != 5 || words[0] != "%%matrixmarket" || words[1] != "matrix" {
+        return Err(invalid_line(
+            number,
+            "expected `%%MatrixMarket matrix coordinate <field> <symmetry>`",
+        ));
+    }
+
+    if words[2] != "coordinate" {
+        return Err(invalid_line(
+            number,
+            format!("`{}` matrices are not graphs, only `coordinate`", words[2]),
+        ));
+    }
+
+    let field = match words[3].as_str() {
+        "pattern" => Field::Pattern,
+        "integer" => Field::Integer,
+        "real" => Field::Real,
+        field => {
+            return Err(invalid_line(
+                number,
+                format!("unsupported field `{}`", field),
+            ))
+        }
+    };
+
+    let directed = match words[4].as_str() {
+        "general" => true,
+        "symmetric" => false,
+        symmetry => {
+            return Err(invalid_line(
+                number,
+                format!("unsupported symmetry `{}`", symmetry),
+            ))
+        }
+    };
+
+    Ok((field, directed))
+}
+
+pub fn read<R: BufRead>(reader: R) -> io::Result<EdgeFile> {
+    let mut banner = None;
+    // The number of rows, columns and entries, and the line they are on
+    let mut size = None;
+    let mut edge_list = Vec::new();
+    let mut num_lines = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        let line = line.trim();
+        num_lines = number;
+
+        if number == 1 {
+            banner = Some(parse_banner(line, number)?);
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (rows, columns, _, _) = match size {
+            Some(size) => size,
+            None => {
+                let rows = parse_count(parts.next(), "rows", number)?;
+                let columns = parse_count(parts.next(), "columns", number)?;
+                let entries = parse_count(parts.next(), "entries", number)?;
+                size = Some((rows, columns, entries, number));
+                edge_list.reserve(entries);
+                continue;
+            }
+        };
+
+        let v = parse_id(parts.next(), 1, number)?;
+        let e = parse_id(parts.next(), 2, number)?;
+        if v >= rows || e >= columns {
+            return Err(invalid_line(
+                number,
+                format!("the entry is outside the {} x {} matrix", rows, columns),
+            ));
+        }
+
+        let weight = match banner.map(|(field, _)| field) {
+            Some(Field::Integer) => Some(parse_weight(parts.next(), number)?),
+            Some(Field::Real) => Some(parse_real_weight(parts.next(), number)?),
+            _ => None,
+        };
+
+        edge_list.push((v, e, weight));
+    }
+
+    let (field, directed) = banner.ok_or_else(|| invalid_line(1, "the file is empty"))?;
+    let (rows, columns, entries, number) =
+        size.ok_or_else(|| invalid_line(num_lines, "missing the size of the matrix"))?;
+    if edge_list.len() != entries {
+        return Err(invalid_line(
+            number,
+            format!(
+                "the matrix has {} entries, but {} are listed",
+                entries,
+                edge_list.len()
+            ),
+        ));
+    }
+
+    Ok(EdgeFile {
+        directed,
+        weighted: field != Field::Pattern,
+        num_nodes: std::cmp::max(rows, columns),
+        edge_list,
+    })
+}
+
+/// Writes a square `integer` or `pattern` matrix,
+/// and moves the edges of an undirected graph to the lower triangle
+pub fn write<W: Write>(mut writer: W, file: &EdgeFile) -> io::Result<()> {
+    let field = if file.weighted { "integer" } else { "pattern" };
+    let symmetry = if file.directed {
+        "general"
+    } else {
+        "symmetric"
+    };
+    writeln!(
+        writer,
+        "%%MatrixMarket matrix coordinate {} {}",
+        field, symmetry
+    )?;
+    writeln!(
+        writer,
+        "{} {} {}",
+        file.num_nodes,
+        file.num_nodes,
+        file.edge_list.len()
+    )?;
+
+    for edge in &file.edge_list {
+        let (v, e) = if file.directed || edge.0 >= edge.1 {
+            (edge.0, edge.1)
+        } else {
+            (edge.1, edge.0)
+        };
+
+        write!(writer, "{} {}", v + 1, e + 1)?;
+        if file.weighted {
+            write!(writer, " {}", weight_of(edge)?)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture;
+
+    fn read_str(file: &str) -> io::Result<EdgeFile> {
+        read(file.as_bytes())
+    }
+
+    fn round_trip(file: &EdgeFile) -> EdgeFile {
+        let mut bytes = Vec::new();
+        write(&mut bytes, file).unwrap();
+        read(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn reads_symmetric_fixture() {
+        let file = read_str(&fixture("tiny.mtx")).unwrap();
+        assert!(!file.directed);
+        assert!(file.weighted);
+        assert_eq!(file.num_nodes, 5);
+        assert_eq!(
+            file.edge_list,
+            vec![
+                (1, 0, Some(3)),
+                (2, 0, Some(1)),
+                (2, 1, Some(2)),
+                (3, 2, Some(4)),
+                (4, 3, Some(2)),
+                (4, 0, Some(1)),
+            ]
+        );
+        assert_eq!(round_trip(&file), file);
+    }
+
+    #[test]
+    fn reads_general_fields() {
+        let file = read_str("%%MatrixMarket matrix coordinate pattern general\n3 3 2\n1 2\n3 1\n")
+            .unwrap();
+        assert!(file.directed);
+        assert!(!file.weighted);
+        assert_eq!(file.edge_list, vec![(0, 1, None), (2, 0, None)]);
+        assert_eq!(round_trip(&file), file);
+
+        let file =
+            read_str("%%MatrixMarket matrix coordinate real general\n%\n2 2 1\n1 2 2.6\n").unwrap();
+        assert_eq!(file.edge_list, vec![(0, 1, Some(3))]);
+    }
+
+    #[test]
+    fn writes_lower_triangle() {
+        let file = EdgeFile {
+            directed: false,
+            weighted: false,
+            num_nodes: 3,
+            edge_list: vec![(0, 1, None), (2, 1, None)],
+        };
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "%%MatrixMarket matrix coordinate pattern symmetric\n3 3 2\n2 1\n3 2\n"
+        );
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let err = read_str("%%MatrixMarket matrix array real general\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "line 1: `array` matrices are not graphs, only `coordinate`"
+        );
+
+        let err =
+            read_str("%%MatrixMarket matrix coordinate pattern general\n2 2 1\n1 3\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: the entry is outside the 2 x 2 matrix"
+        );
+
+        let err = read_str("%%MatrixMarket matrix coordinate integer general\n2 2 2\n1 2 1\n")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: the matrix has 2 entries, but 1 are listed"
+        );
+    }
+}
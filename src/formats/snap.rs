@@ -0,0 +1,145 @@
+//! Reads and writes the edge lists of [SNAP](https://snap.stanford.edu/data/).
+//!
+//! Every line is an edge `<from> <to>`, separated by a tab or spaces, and ids start at 0.
+//! The comments at the top start with `#`, and say if the graph is undirected,
+//! and how many vertices it has, e.g. `# Nodes: 18772 Edges: 396160`.
+//! A graph is read as directed if they do not.
+
+use super::{invalid_line, EdgeFile};
+use crate::types::*;
+use std::io::{self, BufRead, Write};
+
+/// Parses column `column` (1-based) of an edge as an id
+fn parse_node(part: Option<&str>, column: usize, number: usize) -> io::Result<NodeId> {
+    let part = part.ok_or_else(|| invalid_line(number, format!("missing column {}", column)))?;
+    part.parse::<NodeId>()
+        .map_err(|_| invalid_line(number, format!("`{}` is not an id", part)))
+}
+
+/// Parses the number of vertices of `# Nodes: <vertices> Edges: <edges>`,
+/// which counts vertices without edges
+fn parse_nodes(line: &str) -> Option<usize> {
+    let mut parts = line.trim_start_matches('#').split_whitespace();
+    match parts.next() {
+        Some("Nodes:") => parts.next()?.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn read<R: BufRead>(reader: R) -> io::Result<EdgeFile> {
+    let mut directed = true;
+    let mut num_nodes = 0;
+    let mut edge_list = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        let line = line.trim();
+
+        if line.starts_with('#') {
+            // e.g. `# Undirected graph: ../../data/output/ca-AstroPh.txt`
+            if line.to_lowercase().contains("undirected") {
+                directed = false;
+            }
+            num_nodes = std::cmp::max(num_nodes, parse_nodes(line).unwrap_or(0));
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let v = parse_node(parts.next(), 1, number)?;
+        let e = parse_node(parts.next(), 2, number)?;
+        // Timestamps and other columns are not used
+        num_nodes = std::cmp::max(num_nodes, std::cmp::max(v, e) + 1);
+        edge_list.push((v, e, None));
+    }
+
+    Ok(EdgeFile {
+        directed,
+        weighted: false,
+        num_nodes,
+        edge_list,
+    })
+}
+
+/// Writes the edges separated by tabs, the weights are dropped
+pub fn write<W: Write>(mut writer: W, file: &EdgeFile) -> io::Result<()> {
+    if file.directed {
+        writeln!(writer, "# Directed graph")?;
+    } else {
+        writeln!(writer, "# Undirected graph")?;
+    }
+    writeln!(
+        writer,
+        "# Nodes: {} Edges: {}",
+        file.num_nodes,
+        file.edge_list.len()
+    )?;
+    writeln!(writer, "# FromNodeId\tToNodeId")?;
+
+    for (v, e, _) in &file.edge_list {
+        writeln!(writer, "{}\t{}", v, e)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture;
+
+    fn read_str(file: &str) -> io::Result<EdgeFile> {
+        read(file.as_bytes())
+    }
+
+    #[test]
+    fn reads_fixture() {
+        let file = read_str(&fixture("tiny.txt")).unwrap();
+        assert!(!file.directed);
+        assert!(!file.weighted);
+        assert_eq!(file.num_nodes, 5);
+        assert_eq!(
+            file.edge_list,
+            vec![
+                (0, 1, None),
+                (0, 2, None),
+                (1, 2, None),
+                (2, 3, None),
+                (3, 4, None),
+                (0, 4, None),
+            ]
+        );
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &file).unwrap();
+        assert_eq!(read(bytes.as_slice()).unwrap(), file);
+    }
+
+    #[test]
+    fn reads_directed_edges_with_extra_columns() {
+        let file = read_str("# Directed graph\n0 3 1082008561\n\n3  0 \n").unwrap();
+        assert!(file.directed);
+        assert_eq!(file.num_nodes, 4);
+        assert_eq!(file.edge_list, vec![(0, 3, None), (3, 0, None)]);
+    }
+
+    #[test]
+    fn reads_number_of_nodes() {
+        let file = read_str("# Directed graph\n# Nodes: 6 Edges: 1\n0 1\n").unwrap();
+        assert_eq!(file.num_nodes, 6);
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let err = read_str("# Directed graph\n0 1\n1\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 3: missing column 2");
+
+        let err = read_str("0 -1\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: `-1` is not an id");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::builder::BuilderConfig;
+use crate::formats;
 use crate::types::*;
 
 use rand::distributions::Standard;
@@ -6,20 +8,179 @@ use rand::seq::SliceRandom;
 use rayon::iter::IndexedParallelIterator;
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::IntoParallelRefMutIterator;
-use rayon::iter::ParallelExtend;
 use rayon::iter::ParallelIterator;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use rayon::slice::ParallelSliceMut;
+
+/// Number of edges that are generated from the same random stream
+const BLOCK_SIZE: usize = 1 << 18;
+
+/// Number of vertices whose edges are generated from the same random stream
+const VERTEX_BLOCK_SIZE: usize = 1 << 12;
+
+/// The kind of graph that a `Generator` makes. Every family has `2^scale` vertices,
+/// except the grids, which have the largest number of vertices that fits a square or cube.
+///
+/// The families that generate undirected graphs list every edge once, from the lower id.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Family {
+    /// `degree` edges per vertex, between uniformly random vertices
+    Uniform,
+    /// `degree` edges per vertex, placed by the R-MAT generator, see `RmatConfig`
+    Rmat,
+    /// Erdős–Rényi `G(n, p)`, every pair of vertices is connected with probability `p`
+    ErdosRenyi { p: f64 },
+    /// Barabási–Albert preferential attachment, every vertex is connected to `m` earlier vertices,
+    /// picked in proportion to their degree
+    BarabasiAlbert { m: usize },
+    /// Watts–Strogatz small world, a ring where every vertex is connected to its `k / 2`
+    /// next neighbours, and every edge is moved to a random vertex with probability `beta`
+    WattsStrogatz { k: usize, beta: f64 },
+    /// A lattice, every vertex is connected to its next neighbour along each of the `dimensions`
+    Grid { dimensions: usize },
+    /// A 2D lattice, where every edge is kept with probability `keep`, and a cell is crossed by
+    /// a diagonal with probability `1 - keep`. Like a road network, it has a low degree
+    /// and a long diameter.
+    RoadMesh { keep: f64 },
+    /// `0 - 1 - ... - (n - 1)`
+    Path,
+    /// A path that is closed by `(n - 1) - 0`
+    Cycle,
+    /// Vertex 0 connected to every other vertex
+    Star,
+    /// Every pair of vertices connected
+    Complete,
+    /// Every vertex `v` connected to its children `2v + 1` and `2v + 2`
+    BinaryTree,
+}
+
+/// What a random stream is used for, so that the streams of a seed are independent
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Purpose {
+    Edges = 1,
+    Weights = 2,
+    Permutation = 3,
+    Noise = 4,
+}
+
+/// The finalizer of SplitMix64, which spreads every bit of `x` over the result
+fn split_mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Default for Family {
+    fn default() -> Self {
+        Family::Uniform
+    }
+}
+
+/// Parameters of the R-MAT (Kronecker) generator.
+///
+/// Every edge is placed by recursively picking one of the four quadrants of the adjacency matrix,
+/// the top left with probability `a`, the top right with `b`, the bottom left with `c`,
+/// and the bottom right with the rest. The default is the initiator of Graph500.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RmatConfig {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    /// Moves up to this much probability between the quadrants, differently at every level,
+    /// which smooths out the staircase shape of the degree distribution
+    pub noise: f64,
+    /// Shuffles the vertex ids, so that the vertices with the most edges are not the lowest ids
+    pub permute: bool,
+}
+
+impl Default for RmatConfig {
+    fn default() -> Self {
+        Self {
+            a: 0.57,
+            b: 0.19,
+            c: 0.19,
+            noise: 0.0,
+            permute: true,
+        }
+    }
+}
+
+impl RmatConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn initiator(mut self, a: f64, b: f64, c: f64) -> Self {
+        assert!(
+            a >= 0.0 && b >= 0.0 && c >= 0.0 && a + b + c <= 1.0,
+            "The probabilities of the initiator must be positive and sum to at most 1"
+        );
+        self.a = a;
+        self.b = b;
+        self.c = c;
+        self
+    }
+
+    pub fn noise(mut self, noise: f64) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn permute(mut self, permute: bool) -> Self {
+        self.permute = permute;
+        self
+    }
+
+    /// Probability of the bottom right quadrant
+    pub fn d(&self) -> f64 {
+        1.0 - self.a - self.b - self.c
+    }
+
+    /// Picks the initiator of every level, as the cumulative probabilities of the first three quadrants.
+    ///
+    /// The noise moves probability from the diagonal to `b` and `c`, or back,
+    /// the same way as the noisy stochastic Kronecker graphs of Seshadhri, Pinar and Kolda.
+    fn levels(&self, scale: usize, rng: &mut StdRng) -> Vec<[f64; 3]> {
+        let d = self.d();
+        let max_noise = f64::min((self.a + d) / 2.0, f64::min(self.b, self.c));
+        assert!(
+            self.noise >= 0.0 && self.noise <= max_noise,
+            "The noise of the R-MAT generator must be between 0 and {}",
+            max_noise
+        );
+
+        (0..scale)
+            .map(|_| {
+                if self.noise == 0.0 {
+                    return [self.a, self.a + self.b, self.a + self.b + self.c];
+                }
+
+                let mu = rng.gen_range(-self.noise, self.noise);
+                let a = self.a - 2.0 * mu * self.a / (self.a + d);
+                let b = self.b + mu;
+                let c = self.c + mu;
+                [a, a + b, a + b + c]
+            })
+            .collect()
+    }
+}
 
 pub struct Generator {
     scale: usize,
     num_nodes: usize,
     num_edges: usize,
     block_size: usize,
+    seed: usize,
+    rmat: RmatConfig,
 }
 
 impl Generator {
     pub fn new(scale: usize, degree: usize) -> Self {
+        Self::with_seed(scale, degree, crate::K_RAND_SEED)
+    }
+
+    /// A generator whose edge lists only depend on `scale`, `degree` and `seed`
+    pub fn with_seed(scale: usize, degree: usize, seed: usize) -> Self {
         let num_nodes = 1 << scale;
         let num_edges = num_nodes * degree;
 
@@ -27,16 +188,29 @@ impl Generator {
             scale,
             num_nodes,
             num_edges,
-            block_size: 1 << 18,
+            block_size: BLOCK_SIZE,
+            seed,
+            rmat: RmatConfig::default(),
         }
     }
 
+    /// A generator for the scale, degree, seed and R-MAT parameters of `config`
+    pub fn with_config(config: &BuilderConfig) -> Self {
+        let mut generator = Self::with_seed(config.scale, config.degree, config.seed);
+        generator.rmat = config.rmat.clone();
+        generator
+    }
+
+    /// Every block of edges gets its own stream, derived from the seed, the purpose
+    /// and the index of the block, so the edges are the same no matter which thread generates them
+    fn block_rng(seed: usize, purpose: Purpose, block: usize) -> StdRng {
+        let state = split_mix64(split_mix64(seed as u64) ^ purpose as u64);
+        SeedableRng::seed_from_u64(split_mix64(state ^ block as u64))
+    }
+
     pub fn permutate_ids(&self, edge_list: &mut EdgeList) {
         let mut permutation: Vec<NodeId> = (0..self.num_nodes).into_par_iter().collect();
-        // let mut rng: StdRng = SeedableRng::seed_from_u64(crate::K_RAND_SEED as u64);
-
-        // FIXME: Change to custom seed?
-        let mut rng = rand::thread_rng();
+        let mut rng = Self::block_rng(self.seed, Purpose::Permutation, 0);
 
         permutation.shuffle(&mut rng);
 
@@ -47,120 +221,281 @@ impl Generator {
     }
 
     fn make_uniform_edge_list(&self) -> EdgeList {
-        let mut edge_list = Vec::with_capacity(self.num_edges);
+        let mut edge_list = vec![(0, 0, None); self.num_edges];
         let uniform_distribution = rand::distributions::Uniform::from(0..self.num_nodes);
-        edge_list.par_extend(
-            (0..self.num_edges)
-                .into_par_iter()
-                .step_by(self.block_size)
-                .flat_map(|block| {
-                    (block..std::cmp::min(block + self.block_size, self.num_edges))
-                        .into_par_iter()
-                        .map(move |_| {
-                            // let mut rng = SeedableRng::seed_from_u64(
-                            //     (crate::K_RAND_SEED + block / self.block_size) as u64,
-                            // );
-
-                            // FIXME: change to custom seed?
-                            let mut rng = rand::thread_rng();
-                            (
-                                uniform_distribution.sample(&mut rng),
-                                uniform_distribution.sample(&mut rng),
-                                None,
-                            )
-                        })
-                }),
-        );
+
+        edge_list
+            .par_chunks_mut(self.block_size)
+            .enumerate()
+            .for_each(|(block, edges)| {
+                let mut rng = Self::block_rng(self.seed, Purpose::Edges, block);
+                for e in edges.iter_mut() {
+                    *e = (
+                        uniform_distribution.sample(&mut rng),
+                        uniform_distribution.sample(&mut rng),
+                        None,
+                    );
+                }
+            });
 
         edge_list
     }
 
     fn make_rmat_edge_list(&self) -> EdgeList {
-        const A: f64 = 0.57;
-        const B: f64 = 0.19;
-        const C: f64 = 0.19;
-        let mut edge_list = Vec::with_capacity(self.num_edges);
-
-        edge_list.par_extend(
-            (0..self.num_edges)
-                .into_par_iter()
-                .step_by(self.block_size)
-                .flat_map(|block| {
-                    (block..std::cmp::min(block + self.block_size, self.num_edges))
-                        .into_par_iter()
-                        .map(move |_| {
-                            let mut src = 0;
-                            let mut dst = 0;
-
-                            for depth in 0..self.scale {
-                                let rand_point: f64 = StdRng::from_entropy().sample(Standard);
-                                src = src << 1;
-                                dst = dst << 1;
-
-                                if rand_point < A + B {
-                                    if rand_point > A {
-                                        dst += 1;
-                                    }
-                                } else {
-                                    src += 1;
-                                    if rand_point > A + B + C {
-                                        dst += 1;
-                                    }
-                                }
+        // Every block uses the same levels, so they are picked before the edges
+        let mut rng = Self::block_rng(self.seed, Purpose::Noise, 0);
+        let levels = self.rmat.levels(self.scale, &mut rng);
+        let mut edge_list = vec![(0, 0, None); self.num_edges];
+
+        edge_list
+            .par_chunks_mut(self.block_size)
+            .enumerate()
+            .for_each(|(block, edges)| {
+                let mut rng = Self::block_rng(self.seed, Purpose::Edges, block);
+                for e in edges.iter_mut() {
+                    let mut src = 0;
+                    let mut dst = 0;
+
+                    for [a, ab, abc] in levels.iter() {
+                        let rand_point: f64 = rng.sample(Standard);
+                        src = src << 1;
+                        dst = dst << 1;
+
+                        if rand_point < *ab {
+                            if rand_point >= *a {
+                                dst += 1;
+                            }
+                        } else {
+                            src += 1;
+                            if rand_point >= *abc {
+                                dst += 1;
                             }
+                        }
+                    }
 
-                            (src, dst, None)
-                        })
-                }),
-        );
+                    *e = (src, dst, None);
+                }
+            });
+
+        if self.rmat.permute {
+            self.permutate_ids(&mut edge_list);
+        }
 
-        self.permutate_ids(&mut edge_list);
         edge_list
     }
 
-    pub fn generate_edge_list(&self, uniform: bool) -> EdgeList {
-        let timer = crate::timer::ScopedTimer::new("Generate");
-        let edge_list = if uniform {
-            self.make_uniform_edge_list()
-        } else {
-            self.make_rmat_edge_list()
-        };
+    /// Generates every edge of `(v, e)` or `(e, v)` for the vertices of one block at a time,
+    /// and concatenates the blocks in order
+    fn make_vertex_blocks<F>(&self, num_nodes: usize, edges_of: F) -> EdgeList
+    where
+        F: Fn(NodeId, &mut StdRng, &mut EdgeList) + Sync,
+    {
+        let num_blocks = (num_nodes + VERTEX_BLOCK_SIZE - 1) / VERTEX_BLOCK_SIZE;
+        let blocks: Vec<EdgeList> = (0..num_blocks)
+            .into_par_iter()
+            .map(|block| {
+                let mut rng = Self::block_rng(self.seed, Purpose::Edges, block);
+                let mut edges = Vec::new();
+                let end = std::cmp::min((block + 1) * VERTEX_BLOCK_SIZE, num_nodes);
+                for v in block * VERTEX_BLOCK_SIZE..end {
+                    edges_of(v, &mut rng, &mut edges);
+                }
+                edges
+            })
+            .collect();
 
-        edge_list
+        blocks.concat()
     }
 
-    pub fn generate_edge_list_from_file(&self, file: &str) -> EdgeList {
-        let mut edge_list = Vec::new();
+    fn make_erdos_renyi_edge_list(&self, p: f64) -> EdgeList {
+        assert!(
+            p > 0.0 && p <= 1.0,
+            "The edge probability of G(n, p) must be in (0, 1]"
+        );
+        let n = self.num_nodes;
+        let log_q = (1.0 - p).ln();
 
-        let file = File::open(file).unwrap();
-        let reader = BufReader::new(file);
+        self.make_vertex_blocks(n, |v, rng, edges| {
+            // Skips the pairs that are not connected, the gaps between edges are geometric
+            let mut e = v;
+            loop {
+                let r: f64 = rng.sample(Standard);
+                let skip = ((1.0 - r).ln() / log_q).floor();
+                if skip >= (n - e - 1) as f64 {
+                    break;
+                }
 
-        for line in reader.lines() {
-            let line = line.unwrap();
-            let line_parts: Vec<_> = line.split(|c| c == ' ' || c == '\t').collect();
+                e += skip as usize + 1;
+                edges.push((v, e, None));
+            }
+        })
+    }
 
-            let connection = (
-                line_parts[0].parse::<usize>().unwrap(),
-                line_parts[1].parse::<usize>().unwrap(),
-                line_parts.get(2).and_then(|x| x.parse::<usize>().ok()),
-            );
+    fn make_barabasi_albert_edge_list(&self, m: usize) -> EdgeList {
+        let n = self.num_nodes;
+        assert!(
+            m >= 1 && m < n,
+            "Barabási–Albert needs between 1 and n - 1 edges per vertex"
+        );
+
+        // Every vertex is attached to the graph after the previous one, which cannot be done in parallel
+        let mut rng = Self::block_rng(self.seed, Purpose::Edges, 0);
+        let mut edge_list = Vec::with_capacity(m * (n - m));
+        // A vertex is listed once for every edge it has,
+        // so a random endpoint is picked in proportion to its degree
+        let mut endpoints: Vec<NodeId> = Vec::with_capacity(2 * m * (n - m));
+        let mut targets: Vec<NodeId> = (0..m).collect();
 
-            edge_list.push(connection);
+        for v in m..n {
+            for t in targets.iter() {
+                edge_list.push((*t, v, None));
+                endpoints.push(*t);
+                endpoints.push(v);
+            }
+
+            targets.clear();
+            while targets.len() < m {
+                let t = endpoints[rng.gen_range(0, endpoints.len())];
+                if !targets.contains(&t) {
+                    targets.push(t);
+                }
+            }
         }
 
         edge_list
     }
 
-    pub fn insert_weights(edge_list: &mut EdgeList) {
-        let uniform_distribution = rand::distributions::Uniform::from(1..256);
-        let mut rng = rand::thread_rng();
+    fn make_watts_strogatz_edge_list(&self, k: usize, beta: f64) -> EdgeList {
+        let n = self.num_nodes;
+        assert!(
+            k >= 2 && k < n,
+            "Watts–Strogatz needs between 2 and n - 1 neighbours per vertex"
+        );
+        let uniform_distribution = rand::distributions::Uniform::from(0..n);
+
+        self.make_vertex_blocks(n, |v, rng, edges| {
+            for j in 1..=k / 2 {
+                let mut e = (v + j) % n;
+                if rng.sample::<f64, _>(Standard) < beta {
+                    e = uniform_distribution.sample(rng);
+                    while e == v {
+                        e = uniform_distribution.sample(rng);
+                    }
+                }
+                edges.push((std::cmp::min(v, e), std::cmp::max(v, e), None));
+            }
+        })
+    }
+
+    /// Number of vertices along every side of a grid with `dimensions`
+    fn grid_side(&self, dimensions: usize) -> usize {
+        let mut side = (self.num_nodes as f64)
+            .powf(1.0 / dimensions as f64)
+            .round() as usize;
+        while side.pow(dimensions as u32) > self.num_nodes {
+            side -= 1;
+        }
+
+        side
+    }
+
+    fn make_grid_edge_list(&self, dimensions: usize) -> EdgeList {
+        assert!(dimensions >= 1, "A grid needs at least one dimension");
+        let side = self.grid_side(dimensions);
+
+        self.make_vertex_blocks(side.pow(dimensions as u32), |v, _, edges| {
+            let mut stride = 1;
+            for _ in 0..dimensions {
+                if (v / stride) % side + 1 < side {
+                    edges.push((v, v + stride, None));
+                }
+                stride *= side;
+            }
+        })
+    }
+
+    fn make_road_mesh_edge_list(&self, keep: f64) -> EdgeList {
+        let side = self.grid_side(2);
+
+        self.make_vertex_blocks(side * side, |v, rng, edges| {
+            let (x, y) = (v % side, v / side);
+            if x + 1 < side && rng.sample::<f64, _>(Standard) < keep {
+                edges.push((v, v + 1, None));
+            }
+
+            if y + 1 < side && rng.sample::<f64, _>(Standard) < keep {
+                edges.push((v, v + side, None));
+            }
 
-        let el_len = edge_list.len();
+            if x + 1 < side && y + 1 < side && rng.sample::<f64, _>(Standard) >= keep {
+                edges.push((v, v + side + 1, None));
+            }
+        })
+    }
+
+    fn make_shape_edge_list(&self, family: &Family) -> EdgeList {
+        let n = self.num_nodes;
 
-        for e in edge_list.iter_mut() {
-            *e = (e.0, e.1, Some(uniform_distribution.sample(&mut rng)));
+        self.make_vertex_blocks(n, |v, _, edges| match family {
+            Family::Path | Family::Cycle if v + 1 < n => edges.push((v, v + 1, None)),
+            Family::Cycle if n > 2 => edges.push((0, v, None)),
+            Family::Star if v > 0 => edges.push((0, v, None)),
+            Family::Complete => edges.extend((v + 1..n).map(|e| (v, e, None))),
+            Family::BinaryTree => edges.extend(
+                (2 * v + 1..=2 * v + 2)
+                    .filter(|e| *e < n)
+                    .map(|e| (v, e, None)),
+            ),
+            _ => {}
+        })
+    }
+
+    pub fn generate(&self, family: &Family) -> EdgeList {
+        let timer = crate::timer::ScopedTimer::new("Generate");
+        match family {
+            Family::Uniform => self.make_uniform_edge_list(),
+            Family::Rmat => self.make_rmat_edge_list(),
+            Family::ErdosRenyi { p } => self.make_erdos_renyi_edge_list(*p),
+            Family::BarabasiAlbert { m } => self.make_barabasi_albert_edge_list(*m),
+            Family::WattsStrogatz { k, beta } => self.make_watts_strogatz_edge_list(*k, *beta),
+            Family::Grid { dimensions } => self.make_grid_edge_list(*dimensions),
+            Family::RoadMesh { keep } => self.make_road_mesh_edge_list(*keep),
+            Family::Path | Family::Cycle | Family::Star | Family::Complete | Family::BinaryTree => {
+                self.make_shape_edge_list(family)
+            }
         }
     }
+
+    pub fn generate_edge_list(&self, uniform: bool) -> EdgeList {
+        if uniform {
+            self.generate(&Family::Uniform)
+        } else {
+            self.generate(&Family::Rmat)
+        }
+    }
+
+    /// Loads the edges of a file in the format of its extension, panics if it cannot be read
+    pub fn generate_edge_list_from_file(&self, file: &str) -> EdgeList {
+        formats::read_file(file)
+            .unwrap_or_else(|err| panic!("Could not read {}: {}", file, err))
+            .edge_list
+    }
+
+    /// Assigns a weight in `1..256` to every edge, the weights only depend on `seed`
+    /// and the position of the edge in the list
+    pub fn insert_weights(edge_list: &mut EdgeList, seed: usize) {
+        let uniform_distribution = rand::distributions::Uniform::from(1..256);
+
+        edge_list
+            .par_chunks_mut(BLOCK_SIZE)
+            .enumerate()
+            .for_each(|(block, edges)| {
+                let mut rng = Self::block_rng(seed, Purpose::Weights, block);
+                for e in edges.iter_mut() {
+                    *e = (e.0, e.1, Some(uniform_distribution.sample(&mut rng)));
+                }
+            });
+    }
 }
 
 #[cfg(test)]
@@ -179,4 +514,145 @@ mod tests {
         let edge_list = generator.generate_edge_list(true);
         assert_eq!(edge_list.len(), 11 << 1);
     }
+
+    #[test]
+    fn rmat_follows_initiator() {
+        let config = BuilderConfig::new()
+            .scale(6)
+            .degree(4)
+            .rmat(RmatConfig::new().initiator(0.0, 1.0, 0.0).permute(false));
+        let edge_list = Generator::with_config(&config).generate_edge_list(false);
+
+        // Always picking the top right quadrant connects the first vertex to the last
+        assert_eq!(edge_list.len(), 4 << 6);
+        assert!(edge_list.iter().all(|e| *e == (0, (1 << 6) - 1, None)));
+    }
+
+    #[test]
+    fn rmat_noise_keeps_edges_in_range() {
+        let rmat = RmatConfig::new().initiator(0.45, 0.22, 0.22).noise(0.1);
+        let config = BuilderConfig::new().scale(10).degree(8).rmat(rmat);
+        let edge_list = Generator::with_config(&config).generate_edge_list(false);
+
+        assert!(edge_list.iter().all(|e| e.0 < 1 << 10 && e.1 < 1 << 10));
+        assert_eq!(
+            edge_list,
+            Generator::with_config(&config).generate_edge_list(false)
+        );
+        assert_ne!(
+            edge_list,
+            Generator::with_config(&config.clone().rmat(RmatConfig::new()))
+                .generate_edge_list(false)
+        );
+    }
+
+    #[test]
+    fn shapes_have_known_edges() {
+        let generator = Generator::new(4, 1);
+        let n = 16;
+
+        let path = generator.generate(&Family::Path);
+        assert_eq!(
+            path,
+            (0..n - 1).map(|v| (v, v + 1, None)).collect::<EdgeList>()
+        );
+
+        let cycle = generator.generate(&Family::Cycle);
+        assert_eq!(cycle.len(), n);
+        assert!(cycle.contains(&(0, n - 1, None)));
+
+        let star = generator.generate(&Family::Star);
+        assert_eq!(star.len(), n - 1);
+        assert!(star.iter().all(|e| e.0 == 0));
+
+        assert_eq!(generator.generate(&Family::Complete).len(), n * (n - 1) / 2);
+
+        let tree = generator.generate(&Family::BinaryTree);
+        assert_eq!(tree.len(), n - 1);
+        assert!(tree.iter().all(|e| e.0 == (e.1 - 1) / 2));
+    }
+
+    #[test]
+    fn grids_connect_next_neighbours() {
+        // 4 x 4 vertices
+        let grid = Generator::new(4, 1).generate(&Family::Grid { dimensions: 2 });
+        assert_eq!(grid.len(), 2 * 4 * 3);
+        assert!(grid.iter().all(|e| e.1 - e.0 == 1 || e.1 - e.0 == 4));
+
+        // 5 x 5 x 5 vertices, the largest cube with at most 2^7 vertices
+        let grid = Generator::new(7, 1).generate(&Family::Grid { dimensions: 3 });
+        assert_eq!(grid.len(), 3 * 25 * 4);
+        assert!(grid.iter().all(|e| e.1 < 125));
+    }
+
+    #[test]
+    fn random_families_are_seeded() {
+        let families = [
+            Family::ErdosRenyi { p: 0.01 },
+            Family::BarabasiAlbert { m: 3 },
+            Family::WattsStrogatz { k: 4, beta: 0.2 },
+            Family::RoadMesh { keep: 0.8 },
+        ];
+        let n = 1 << 10;
+
+        for family in families.iter() {
+            let edge_list = Generator::with_seed(10, 1, 7).generate(family);
+            assert_eq!(edge_list, Generator::with_seed(10, 1, 7).generate(family));
+            assert_ne!(edge_list, Generator::with_seed(10, 1, 8).generate(family));
+            assert!(edge_list.iter().all(|e| e.0 < e.1 && e.1 < n));
+        }
+
+        let generator = Generator::new(10, 1);
+        let erdos_renyi = generator.generate(&Family::ErdosRenyi { p: 0.01 });
+        let expected = 0.01 * (n * (n - 1) / 2) as f64;
+        assert!((erdos_renyi.len() as f64 - expected).abs() < 0.1 * expected);
+
+        let barabasi_albert = generator.generate(&Family::BarabasiAlbert { m: 3 });
+        assert_eq!(barabasi_albert.len(), 3 * (n - 3));
+
+        let watts_strogatz = generator.generate(&Family::WattsStrogatz { k: 4, beta: 0.2 });
+        assert_eq!(watts_strogatz.len(), 2 * n);
+    }
+
+    #[test]
+    fn same_seed_same_edge_list() {
+        let generate = |threads: usize, seed: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let generator = Generator::with_seed(12, 80, seed);
+            pool.install(|| {
+                let mut edge_list = generator.generate_edge_list(false);
+                Generator::insert_weights(&mut edge_list, seed);
+                edge_list
+            })
+        };
+
+        // More than one block of edges, so that the blocks run on different threads
+        let edge_list = generate(1, crate::K_RAND_SEED);
+        assert!(edge_list.len() > BLOCK_SIZE);
+        assert!(edge_list == generate(4, crate::K_RAND_SEED));
+        assert!(edge_list != generate(4, crate::K_RAND_SEED + 1));
+    }
+
+    #[test]
+    fn streams_do_not_overlap() {
+        let first = |seed, purpose, block| Generator::block_rng(seed, purpose, block).next_u64();
+
+        // The next block of one seed is not the first block of the next seed
+        assert_ne!(first(7, Purpose::Edges, 1), first(8, Purpose::Edges, 0));
+
+        let purposes = [
+            Purpose::Edges,
+            Purpose::Weights,
+            Purpose::Permutation,
+            Purpose::Noise,
+        ];
+        for (i, a) in purposes.iter().enumerate() {
+            for b in purposes[i + 1..].iter() {
+                assert_ne!(first(7, *a, 0), first(7, *b, 0), "{:?} {:?}", a, b);
+            }
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::builder::Csr;
+use crate::memory::MemoryUsage;
 use crate::types::*;
 
 pub type Range<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
@@ -9,9 +11,43 @@ pub trait CSRGraph<V, E> {
     fn op_delete_vertex(&self, v: NodeId);
     fn op_find_vertex(&self, v: NodeId);
 
+    /// Whether the model overrides `build_from_csr`. The builder only makes
+    /// the compressed sparse rows for these models, the others are built
+    /// from the squished edge list.
+    const BUILDS_FROM_CSR: bool = false;
+
+    /// Builds a directed graph, with the transposed in-edges of every vertex
     fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self;
+    /// Builds an undirected graph, where every edge is also added in reverse
     fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self;
 
+    /// Builds a directed graph without in-edges, for kernels that never read `in_neigh`
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self
+    where
+        Self: Sized;
+
+    /// Builds a graph from the squished out-edges of every vertex, and the in-edges
+    /// if a directed graph is inverted. An undirected graph stores every edge
+    /// in both directions in `out_edges`.
+    ///
+    /// Adds the edges one at a time by default,
+    /// models that can fill their adjacency in parallel override it.
+    fn build_from_csr(out_edges: Csr, in_edges: Option<Csr>, directed: bool) -> Self
+    where
+        Self: Sized,
+    {
+        let num_nodes = out_edges.num_nodes();
+        let mut edge_list = out_edges.edge_list();
+        if !directed {
+            edge_list.retain(|(v, e, _)| v < e);
+            Self::build_undirected(num_nodes, &edge_list)
+        } else if in_edges.is_some() {
+            Self::build_directed(num_nodes, &edge_list)
+        } else {
+            Self::build_directed_without_in_edges(num_nodes, &edge_list)
+        }
+    }
+
     fn directed(&self) -> bool;
 
     fn num_nodes(&self) -> usize;
@@ -25,6 +61,8 @@ pub trait CSRGraph<V, E> {
     fn out_neigh(&self, v: NodeId) -> Range<E>;
 
     fn print_stats(&self);
+    /// Estimates the bytes held by the graph
+    fn memory_usage(&self) -> MemoryUsage;
 
     fn vertices(&self) -> Range<V>;
 
@@ -1,7 +1,14 @@
+use crate::builder::Csr;
 use crate::graph::{CSRGraph, Range};
+use crate::memory::{self, MemoryUsage};
 use crate::types::*;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 use std::cell::Cell;
 use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
@@ -104,38 +111,89 @@ impl<T> Node<T> {
 pub struct Graph<T> {
     vertices: RwLock<BTreeMap<usize, WrappedNode<T>>>,
     num_nodes: usize,
-    num_edges: usize,
+    n_edges: AtomicUsize,
     directed: bool,
 }
 
-impl<'a, T: 'a + Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
+impl<'a, T: 'a + Clone + Send + Sync> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
+    const BUILDS_FROM_CSR: bool = true;
+
     fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(true);
+        let graph = Graph::new(true);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
 
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, true);
-            graph.num_edges += 1;
         }
         graph
     }
 
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        let graph = Graph::new(true);
+        for v in 0..num_nodes {
+            graph.add_vertex(v, None);
+        }
+
+        for (v, e, w) in edge_list {
+            graph.add_out_edge(*v, *e, w);
+        }
+
+        graph
+    }
+
     fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(false);
+        let graph = Graph::new(false);
         // println!("Building undirected, with {} nodes", num_nodes);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, false);
-            graph.num_edges += 1;
         }
 
         graph
     }
 
+    fn build_from_csr(out_edges: Csr, in_edges: Option<Csr>, directed: bool) -> Self {
+        let graph = Graph::new(directed);
+        let nodes: Vec<_> = (0..out_edges.num_nodes())
+            .into_par_iter()
+            .map(|v| Node::new(v, None))
+            .collect();
+
+        let edges = |csr: &Csr, v: NodeId| -> BTreeMap<usize, WrappedNode<T>> {
+            csr.neighs(v)
+                .iter()
+                .map(|(e, w)| (*e, WrappedNode::from_node(Arc::clone(&nodes[*e]), w)))
+                .collect()
+        };
+
+        // Every vertex is only locked by the thread that fills its edges
+        nodes.par_iter().enumerate().for_each(|(v, node)| {
+            let mut node = node.write().expect("Could not write");
+            node.out_edges = edges(&out_edges, v);
+            if let Some(in_edges) = &in_edges {
+                node.in_edges = edges(in_edges, v);
+            }
+        });
+
+        *graph.vertices.write().expect("Could not write") = nodes
+            .into_iter()
+            .enumerate()
+            .map(|(v, node)| (v, WrappedNode::from_node(node, &None)))
+            .collect();
+
+        let num_edges = out_edges.num_edges();
+        graph.n_edges.store(
+            if directed { num_edges } else { num_edges / 2 },
+            Ordering::SeqCst,
+        );
+
+        graph
+    }
+
     fn directed(&self) -> bool {
         self.directed
     }
@@ -145,14 +203,14 @@ impl<'a, T: 'a + Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     }
 
     fn num_edges(&self) -> usize {
-        unimplemented!();
+        self.n_edges.load(Ordering::SeqCst)
     }
 
     fn num_edges_directed(&self) -> usize {
         if self.directed {
-            self.num_edges
+            self.num_edges()
         } else {
-            self.num_edges * 2
+            self.num_edges() * 2
         }
     }
 
@@ -165,7 +223,10 @@ impl<'a, T: 'a + Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     }
 
     fn in_degree(&self, v: NodeId) -> usize {
-        println!("Graph inversion is probably disabled... in in_degree()");
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
         if let Some(found) = self.vertices.read().expect("Could not read").get(&v) {
             found.read().expect("Could not read").in_edges.len()
         } else {
@@ -187,6 +248,10 @@ impl<'a, T: 'a + Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     }
 
     fn in_neigh(&self, v: NodeId) -> Range<WrappedNode<T>> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
         if let Some(vertex) = self.vertices.read().expect("Could not read").get(&v) {
             let mut edges = Vec::new();
             for edge in vertex.read().expect("Could not read").in_edges.values() {
@@ -206,6 +271,32 @@ impl<'a, T: 'a + Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
         println!("---------------------------");
     }
 
+    fn memory_usage(&self) -> MemoryUsage {
+        let vertices = self.vertices.read().expect("Could not read");
+        let mut usage = MemoryUsage::default();
+
+        let (index, overhead) = memory::btree_map::<usize, WrappedNode<T>>(vertices.len());
+        usage.vertices += index;
+        usage.overhead += overhead;
+
+        for node in vertices.values() {
+            let node = node.read().expect("Could not read");
+            usage.vertices += size_of::<Node<T>>();
+            usage.overhead += memory::ALLOCATION_HEADER;
+            // The strong and weak counts of the `Arc`, and the state of the `RwLock`
+            usage.descriptors +=
+                size_of::<RwLock<Node<T>>>() - size_of::<Node<T>>() + 2 * size_of::<usize>();
+
+            for edges in &[&node.in_edges, &node.out_edges] {
+                let (bytes, overhead) = memory::btree_map::<usize, WrappedNode<T>>(edges.len());
+                usage.edges += bytes;
+                usage.overhead += overhead;
+            }
+        }
+
+        usage
+    }
+
     fn vertices(&self) -> Range<WrappedNode<T>> {
         let mut edges = Vec::new();
         for edge in self.vertices.read().expect("Could not read").values() {
@@ -269,7 +360,7 @@ impl<T> Graph<T> {
         Graph {
             vertices: RwLock::new(BTreeMap::new()),
             num_nodes: 0,
-            num_edges: 0,
+            n_edges: AtomicUsize::new(0),
             directed,
         }
     }
@@ -282,6 +373,21 @@ impl<T> Graph<T> {
             .map(|v| Arc::clone(v))
     }
 
+    /// Checks if `e` is in the out-edges of `vertex`
+    pub fn has_out_edge(&self, vertex: usize, e: usize) -> bool {
+        self.vertices
+            .read()
+            .expect("Could not read")
+            .get(&vertex)
+            .map_or(false, |found| {
+                found
+                    .read()
+                    .expect("Could not read")
+                    .out_edges
+                    .contains_key(&e)
+            })
+    }
+
     pub fn add_vertex(&self, node_id: usize, value: Option<T>) -> Arc<RwLock<Node<T>>> {
         let new_node = Node::new(node_id, value);
         self.vertices
@@ -304,12 +410,24 @@ impl<T> Graph<T> {
                 Node::add_in_edge(&edge_node, &vertex_node, weight);
             }
 
-            Node::add_out_edge(&vertex_node, &edge_node, weight);
+            if Node::add_out_edge(&vertex_node, &edge_node, weight) {
+                self.n_edges.fetch_add(1, Ordering::SeqCst);
+            }
         } else {
             // panic!("Could not add edge, one or both of the nodes you are trying to connect does not exist");
         }
     }
 
+    /// Adds the out-edge `vertex -> edge` of a directed graph, without its in-edge
+    pub fn add_out_edge(&self, vertex: usize, edge: usize, weight: &Option<usize>) {
+        let lock = self.vertices.read().expect("Could not read");
+        if let (Some(vertex_node), Some(edge_node)) = (lock.get(&vertex), lock.get(&edge)) {
+            if Node::add_out_edge(vertex_node, edge_node, weight) {
+                self.n_edges.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
     pub fn connect(
         &mut self,
         vertex_node: &Arc<RwLock<Node<T>>>,
@@ -323,7 +441,9 @@ impl<T> Graph<T> {
             Node::add_in_edge(&edge_node, &vertex_node, weight);
         }
 
-        Node::add_out_edge(&vertex_node, &edge_node, weight);
+        if Node::add_out_edge(&vertex_node, &edge_node, weight) {
+            self.n_edges.fetch_add(1, Ordering::SeqCst);
+        }
     }
 
     pub fn bfs(&self, start: usize, goal: Option<usize>) -> usize {
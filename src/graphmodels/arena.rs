@@ -1,17 +1,38 @@
+use crate::builder::Csr;
 use crate::graph::{CSRGraph, Range};
+use crate::memory::{self, MemoryUsage};
 use crate::types::*;
 use generational_arena::{Arena, Index};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
 
 type Weight = usize;
 
-#[derive(Clone, Hash, Eq, PartialEq)]
+#[derive(Clone)]
 pub struct CustomIndex {
     index: Index,
     weight: Option<Weight>,
 }
 
+// An edge is identified by its target alone, so that the same edge
+// is not stored twice with different weights
+impl PartialEq for CustomIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for CustomIndex {}
+
+impl Hash for CustomIndex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
 impl AsNode for CustomIndex {
     fn as_node(&self) -> NodeId {
         self.index.into_raw_parts().0
@@ -51,38 +72,94 @@ pub struct Graph<T> {
     directed: bool,
     cache: RefCell<BTreeMap<NodeId, Index>>,
     n_edges: Cell<usize>,
-    num_edges: usize,
 }
 
 impl<'a, T: Clone> CSRGraph<CustomIndex, CustomIndex> for Graph<T> {
+    const BUILDS_FROM_CSR: bool = true;
+
     fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(true);
+        let graph = Graph::new(true);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
 
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, true);
-            graph.num_edges += 1;
+        }
+
+        graph
+    }
+
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        let graph = Graph::new(true);
+        for v in 0..num_nodes {
+            graph.add_vertex(v, None);
+        }
+
+        for (v, e, w) in edge_list {
+            graph.add_out_edge(*v, *e, w);
         }
 
         graph
     }
 
     fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(false);
+        let graph = Graph::new(false);
         // println!("Building undirected, with {} nodes", num_nodes);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, false);
-            graph.num_edges += 1;
         }
 
         graph
     }
 
+    fn build_from_csr(out_edges: Csr, in_edges: Option<Csr>, directed: bool) -> Self {
+        let graph = Graph::new(directed);
+        let indices: Vec<Index> = (0..out_edges.num_nodes())
+            .map(|v| graph.add_vertex(v, None))
+            .collect();
+
+        // The arena cannot be shared between threads,
+        // so the edge sets are built in parallel before they are moved into it
+        let edge_sets = |csr: &Csr| -> Vec<HashSet<CustomIndex>> {
+            (0..csr.num_nodes())
+                .into_par_iter()
+                .map(|v| {
+                    csr.neighs(v)
+                        .iter()
+                        .map(|(e, w)| CustomIndex {
+                            index: indices[*e],
+                            weight: *w,
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        let out_sets = edge_sets(&out_edges);
+        let in_sets = in_edges.as_ref().map(edge_sets);
+        {
+            let mut vertices = graph.vertices.borrow_mut();
+            for (index, edges) in indices.iter().zip(out_sets) {
+                vertices[*index].out_edges = edges;
+            }
+
+            for (index, edges) in indices.iter().zip(in_sets.into_iter().flatten()) {
+                vertices[*index].in_edges = edges;
+            }
+        }
+
+        let num_edges = out_edges.num_edges();
+        graph
+            .n_edges
+            .set(if directed { num_edges } else { num_edges / 2 });
+
+        graph
+    }
+
     fn directed(&self) -> bool {
         self.directed
     }
@@ -97,9 +174,9 @@ impl<'a, T: Clone> CSRGraph<CustomIndex, CustomIndex> for Graph<T> {
 
     fn num_edges_directed(&self) -> usize {
         if self.directed {
-            self.num_edges
+            self.num_edges()
         } else {
-            self.num_edges * 2
+            self.num_edges() * 2
         }
     }
 
@@ -117,7 +194,10 @@ impl<'a, T: Clone> CSRGraph<CustomIndex, CustomIndex> for Graph<T> {
     }
 
     fn in_degree(&self, v: NodeId) -> usize {
-        println!("Graph inversion is probably disabled... in in_degree()");
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
         if let Some(found) = self.get_vertex(v) {
             self.vertices
                 .borrow()
@@ -144,6 +224,10 @@ impl<'a, T: Clone> CSRGraph<CustomIndex, CustomIndex> for Graph<T> {
     }
 
     fn in_neigh(&self, v: NodeId) -> Range<CustomIndex> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
         if let Some(found) = self.get_vertex(v) {
             let mut edges = Vec::new();
             for edge in &self.vertices.borrow().get(found.index).unwrap().in_edges {
@@ -163,6 +247,33 @@ impl<'a, T: Clone> CSRGraph<CustomIndex, CustomIndex> for Graph<T> {
         println!("---------------------------");
     }
 
+    fn memory_usage(&self) -> MemoryUsage {
+        let vertices = self.vertices.borrow();
+        let mut usage = MemoryUsage::default();
+
+        // Every entry of the arena stores its generation next to the node
+        let (entries, unused) =
+            memory::vec::<(u64, ArenaNode<T>)>(vertices.len(), vertices.capacity());
+        let generations = vertices.len() * size_of::<u64>();
+        usage.vertices += entries - generations;
+        usage.descriptors += generations;
+        usage.overhead += unused;
+
+        let (cache, overhead) = memory::btree_map::<NodeId, Index>(self.cache.borrow().len());
+        usage.vertices += cache;
+        usage.overhead += overhead;
+
+        for (_, node) in vertices.iter() {
+            for edges in &[&node.in_edges, &node.out_edges] {
+                let (bytes, overhead) = memory::hash_set(edges);
+                usage.edges += bytes;
+                usage.overhead += overhead;
+            }
+        }
+
+        usage
+    }
+
     fn vertices(&self) -> Range<CustomIndex> {
         let mut edges = Vec::new();
         for (idx, edge) in self.vertices.borrow().iter() {
@@ -242,7 +353,6 @@ impl<T> Graph<T> {
             directed,
             cache: RefCell::new(BTreeMap::new()),
             n_edges: Cell::new(0),
-            num_edges: 0,
         }
     }
 
@@ -282,6 +392,11 @@ impl<T> Graph<T> {
     }
 
     pub fn add_edge(&self, node1: usize, node2: usize, weight: &Option<Weight>, directed: bool) {
+        // Disable self-edges
+        if node1 == node2 {
+            return;
+        }
+
         if let (Some(vertex), Some(edge)) = (self.get_vertex(node1), self.get_vertex(node2)) {
             if !directed {
                 self.vertices
@@ -326,6 +441,28 @@ impl<T> Graph<T> {
         }
     }
 
+    /// Adds the out-edge `node1 -> node2` of a directed graph, without its in-edge
+    pub fn add_out_edge(&self, node1: usize, node2: usize, weight: &Option<Weight>) {
+        // Disable self-edges
+        if node1 == node2 {
+            return;
+        }
+
+        if let (Some(vertex), Some(edge)) = (self.get_vertex(node1), self.get_vertex(node2)) {
+            let mut vertices = self.vertices.borrow_mut();
+            if let Some(vx) = vertices.get_mut(vertex.index) {
+                let edge = CustomIndex {
+                    index: edge.index,
+                    weight: *weight,
+                };
+
+                if vx.out_edges.insert(edge) {
+                    self.n_edges.update(|x| x + 1);
+                }
+            }
+        }
+    }
+
     pub fn bfs(&self, start: usize, goal: Option<usize>) -> usize {
         unimplemented!("NO OLD BFS");
     }
@@ -1,8 +1,14 @@
 use crate::graph::{CSRGraph, Range};
+use crate::memory::{self, MemoryUsage};
 use crate::types::*;
 use bacon_rajan_cc::{Cc, Trace, Tracer, Weak};
 use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::mem::size_of;
+
+/// Bytes that `Cc` stores in front of the value: the strong and weak counts,
+/// and the buffered flag and color used by the cycle collector
+const CC_HEADER: usize = 3 * size_of::<usize>();
 
 #[derive(Clone)]
 pub struct WrappedNode<T: 'static> {
@@ -106,34 +112,44 @@ impl<T> Node<T> {
 pub struct Graph<T: 'static> {
     vertices: RefCell<BTreeMap<usize, WrappedNode<T>>>,
     n_edges: Cell<usize>,
-    num_edges: usize,
     directed: bool,
 }
 
 impl<'a, T: Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(true);
+        let graph = Graph::new(true);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
 
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, true);
-            graph.num_edges += 1;
+        }
+
+        graph
+    }
+
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        let graph = Graph::new(true);
+        for v in 0..num_nodes {
+            graph.add_vertex(v, None);
+        }
+
+        for (v, e, w) in edge_list {
+            graph.add_out_edge(*v, *e, w);
         }
 
         graph
     }
 
     fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(false);
+        let graph = Graph::new(false);
         // println!("Building undirected, with {} nodes", num_nodes);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, false);
-            graph.num_edges += 1;
         }
 
         graph
@@ -153,9 +169,9 @@ impl<'a, T: Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
 
     fn num_edges_directed(&self) -> usize {
         if self.directed {
-            self.num_edges
+            self.num_edges()
         } else {
-            self.num_edges * 2
+            self.num_edges() * 2
         }
     }
 
@@ -168,7 +184,10 @@ impl<'a, T: Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     }
 
     fn in_degree(&self, v: NodeId) -> usize {
-        println!("Graph inversion is probably disabled... in in_degree()");
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
         if let Some(found) = self.vertices.borrow().get(&v) {
             found.borrow().in_edges.len()
         } else {
@@ -190,6 +209,10 @@ impl<'a, T: Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
     }
 
     fn in_neigh(&self, v: NodeId) -> Range<WrappedNode<T>> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
         if let Some(vertex) = self.vertices.borrow().get(&v) {
             let mut edges = Vec::new();
             for edge in vertex.borrow().in_edges.values() {
@@ -209,6 +232,31 @@ impl<'a, T: Clone> CSRGraph<WrappedNode<T>, WrappedNode<T>> for Graph<T> {
         println!("---------------------------");
     }
 
+    fn memory_usage(&self) -> MemoryUsage {
+        let vertices = self.vertices.borrow();
+        let mut usage = MemoryUsage::default();
+
+        let (index, overhead) = memory::btree_map::<usize, WrappedNode<T>>(vertices.len());
+        usage.vertices += index;
+        usage.overhead += overhead;
+
+        for node in vertices.values() {
+            let node = node.borrow();
+            usage.vertices += size_of::<Node<T>>();
+            usage.overhead += memory::ALLOCATION_HEADER;
+            // The counts and the color of the `Cc`, and the borrow flag of the `RefCell`
+            usage.descriptors += size_of::<RefCell<Node<T>>>() - size_of::<Node<T>>() + CC_HEADER;
+
+            for edges in &[&node.in_edges, &node.out_edges] {
+                let (bytes, overhead) = memory::btree_map::<usize, WrappedNode<T>>(edges.len());
+                usage.edges += bytes;
+                usage.overhead += overhead;
+            }
+        }
+
+        usage
+    }
+
     fn vertices(&self) -> Range<WrappedNode<T>> {
         let mut edges = Vec::new();
         for edge in self.vertices.borrow().values() {
@@ -271,7 +319,6 @@ impl<T> Graph<T> {
         Graph {
             vertices: RefCell::new(BTreeMap::new()),
             n_edges: Cell::new(0),
-            num_edges: 0,
             directed,
         }
     }
@@ -308,6 +355,18 @@ impl<T> Graph<T> {
         }
     }
 
+    /// Adds the out-edge `vertex -> edge` of a directed graph, without its in-edge
+    pub fn add_out_edge(&self, vertex: usize, edge: usize, weight: &Option<usize>) {
+        if let (Some(vertex_node), Some(edge_node)) = (
+            self.vertices.borrow().get(&vertex),
+            self.vertices.borrow().get(&edge),
+        ) {
+            if Node::add_out_edge(vertex_node, edge_node, weight) {
+                self.n_edges.update(|x| x + 1);
+            }
+        }
+    }
+
     pub fn connect(
         &self,
         vertex_node: &Cc<RefCell<Node<T>>>,
@@ -0,0 +1,245 @@
+use crate::builder::{BuilderBase, BuilderConfig, Csr};
+use crate::graph::{CSRGraph, Range};
+use crate::memory::{self, MemoryUsage};
+use crate::types::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// A vertex, or the target of an edge and its weight
+#[derive(Clone, Copy, Debug)]
+pub struct CsrNode {
+    node_id: NodeId,
+    weight: Option<Weight>,
+}
+
+impl AsNode for CsrNode {
+    fn as_node(&self) -> NodeId {
+        self.node_id
+    }
+}
+
+impl WeightedEdge for CsrNode {
+    fn get_weight(&self) -> usize {
+        self.weight.expect("Weights must be assigned before used")
+    }
+
+    fn set_weight(&mut self, weight: usize) {
+        self.weight.replace(weight);
+    }
+}
+
+/// A graph in compressed sparse row format, as used by the GAP Benchmark Suite.
+///
+/// The edges are stored in one array that is filled in parallel when the graph is built,
+/// and cannot be changed after that. The vertices do not hold any values.
+pub struct Graph<T> {
+    out_edges: Csr,
+    in_edges: Option<Csr>,
+    directed: bool,
+    values: PhantomData<T>,
+}
+
+impl<T> Graph<T> {
+    fn from_edge_list(
+        num_nodes: usize,
+        edge_list: &EdgeList,
+        directed: bool,
+        invert: bool,
+    ) -> Self {
+        let config = BuilderConfig::new()
+            .symmetrize(!directed)
+            .needs_weights(false);
+        let mut builder = BuilderBase::with_config(config);
+        builder.set_num_nodes(num_nodes);
+
+        let out_edges = builder.make_csr(edge_list, false).squish();
+        let in_edges = if directed && invert {
+            Some(builder.make_csr(edge_list, true).squish())
+        } else {
+            None
+        };
+
+        Self::build_from_csr(out_edges, in_edges, directed)
+    }
+
+    fn neighs(csr: &Csr, v: NodeId) -> Range<CsrNode> {
+        Box::new(
+            csr.neighs(v)
+                .iter()
+                .map(|&(node_id, weight)| CsrNode { node_id, weight }),
+        )
+    }
+
+    pub fn find_vertex(&self, v: NodeId) -> Option<CsrNode> {
+        if v < self.num_nodes() {
+            Some(CsrNode {
+                node_id: v,
+                weight: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn bfs(&self, start: NodeId, goal: Option<NodeId>) -> usize {
+        let mut queue = VecDeque::new();
+        let mut discovered = vec![false; self.out_edges.num_nodes()];
+
+        discovered[start] = true;
+        queue.push_back(start);
+        let mut num_discovered = 1;
+
+        while let Some(v) = queue.pop_front() {
+            for &(e, _) in self.out_edges.neighs(v) {
+                if goal == Some(e) {
+                    return num_discovered;
+                }
+
+                if !discovered[e] {
+                    discovered[e] = true;
+                    num_discovered += 1;
+                    queue.push_back(e);
+                }
+            }
+        }
+
+        num_discovered
+    }
+}
+
+impl<T> CSRGraph<CsrNode, CsrNode> for Graph<T> {
+    const BUILDS_FROM_CSR: bool = true;
+
+    fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, true, true)
+    }
+
+    fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, false, false)
+    }
+
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, true, false)
+    }
+
+    fn build_from_csr(out_edges: Csr, in_edges: Option<Csr>, directed: bool) -> Self {
+        Self {
+            out_edges,
+            in_edges,
+            directed,
+            values: PhantomData,
+        }
+    }
+
+    fn directed(&self) -> bool {
+        self.directed
+    }
+
+    fn num_nodes(&self) -> usize {
+        self.out_edges.num_nodes()
+    }
+
+    fn num_edges(&self) -> usize {
+        if self.directed {
+            self.out_edges.num_edges()
+        } else {
+            self.out_edges.num_edges() / 2
+        }
+    }
+
+    fn num_edges_directed(&self) -> usize {
+        self.out_edges.num_edges()
+    }
+
+    fn out_degree(&self, v: NodeId) -> usize {
+        self.out_edges.degree(v)
+    }
+
+    fn in_degree(&self, v: NodeId) -> usize {
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
+        self.in_edges
+            .as_ref()
+            .map_or(0, |in_edges| in_edges.degree(v))
+    }
+
+    fn in_neigh(&self, v: NodeId) -> Range<CsrNode> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
+        match &self.in_edges {
+            Some(in_edges) => Self::neighs(in_edges, v),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn out_neigh(&self, v: NodeId) -> Range<CsrNode> {
+        Self::neighs(&self.out_edges, v)
+    }
+
+    fn print_stats(&self) {
+        println!("---------- GRAPH ----------");
+        println!("  Num Nodes          - {:?}", self.num_nodes());
+        println!("  Num Edges          - {:?}", self.num_edges_directed());
+        println!("---------------------------");
+    }
+
+    fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+
+        for csr in std::iter::once(&self.out_edges).chain(&self.in_edges) {
+            let (offsets, unused) = memory::vec::<usize>(csr.offsets.len(), csr.offsets.capacity());
+            usage.vertices += offsets;
+            usage.overhead += unused;
+
+            let (neighs, unused) =
+                memory::vec::<(DestId, Option<Weight>)>(csr.neighs.len(), csr.neighs.capacity());
+            usage.edges += neighs;
+            usage.overhead += unused;
+        }
+
+        usage
+    }
+
+    fn vertices(&self) -> Range<CsrNode> {
+        Box::new((0..self.num_nodes()).map(|node_id| CsrNode {
+            node_id,
+            weight: None,
+        }))
+    }
+
+    fn old_bfs(&self, v: NodeId) {
+        self.bfs(v, None);
+    }
+
+    fn replace_out_edges(&self, v: NodeId, edges: Vec<CsrNode>) {
+        panic!("The edges of a CSR graph cannot be changed after it is built");
+    }
+
+    fn replace_in_edges(&self, v: NodeId, edges: Vec<CsrNode>) {
+        panic!("The edges of a CSR graph cannot be changed after it is built");
+    }
+
+    fn op_add_vertex(&self, v: NodeId) {
+        panic!("A CSR graph cannot be changed after it is built");
+    }
+
+    fn op_add_edge(&self, v: NodeId, e: NodeId) {
+        panic!("A CSR graph cannot be changed after it is built");
+    }
+
+    fn op_delete_edge(&self, v: NodeId, e: NodeId) {
+        panic!("A CSR graph cannot be changed after it is built");
+    }
+
+    fn op_delete_vertex(&self, v: NodeId) {
+        panic!("A CSR graph cannot be changed after it is built");
+    }
+
+    fn op_find_vertex(&self, v: NodeId) {
+        self.find_vertex(v);
+    }
+}
@@ -1,18 +1,29 @@
 #![allow(dead_code)]
-use crate::graphmodels::epoch::lftt::{Desc, NodeDesc, OpStatus, OpType, Operator, ReturnCode};
-use crate::graphmodels::epoch::mdlist::{MDList, MDNode};
+use crate::graphmodels::epoch::filter::VertexFilter;
+use crate::graphmodels::epoch::index::VertexIndex;
+use crate::graphmodels::epoch::lftt::{
+    Desc, NodeDesc, OpStatus, OpType, Operator, ReturnCode, TxnOutcome, TxnStats,
+};
+use crate::graphmodels::epoch::mdlist::{MDList, MDListConfig, MDNode};
+use crate::graphmodels::epoch::sync::AtomicCell;
+use crate::graphmodels::epoch::wal::WriteAheadLog;
+use crate::memory::{self, MemoryUsage};
 use epoch::{Atomic, Guard, Owned, Shared};
 
-// use bloom::{BloomFilter, ASMS};
-use lock_free_bloomfilter::bloomfilter::BloomFilter;
 use std::cell::RefCell;
+use std::collections::HashSet;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+use std::sync::atomic::AtomicIsize;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
 use std::sync::{Arc, RwLock};
-use utils::atomic::AtomicCell;
 
-const KEY_RANGE: usize = 1024;
-const BASIS: usize = 16;
+#[cfg(not(crossbeam_loom))]
 thread_local!(static HELPSTACK: RefCell<Vec<*const u8>> = RefCell::new(Vec::new()));
+// Threads of the model checker are not OS threads
+#[cfg(crossbeam_loom)]
+loom::thread_local!(static HELPSTACK: RefCell<Vec<*const u8>> = RefCell::new(Vec::new()));
 
 #[inline]
 fn set_mark(p: usize) -> usize {
@@ -51,6 +62,10 @@ pub struct Node<'a, T, E> {
     value: AtomicCell<Option<T>>,
     node_desc: Atomic<NodeDesc<'a, T, E>>,
     next: Atomic<Self>,
+    // Updated after the transactions that change them commit,
+    // so they can briefly be negative
+    out_degree: AtomicIsize,
+    in_degree: AtomicIsize,
     pub out_edges: Option<MDList<'a, E, T>>,
     pub in_edges: Option<MDList<'a, E, T>>,
 }
@@ -61,6 +76,30 @@ impl<'a, T: Copy, E> Node<'a, T, E> {
     }
 }
 
+impl<'a, T, E> Node<'a, T, E> {
+    /// Returns the number of out- or in-edges of the vertex.
+    ///
+    /// The count is updated once a transaction that inserts or deletes edges
+    /// has committed, so it can lag behind concurrent transactions.
+    pub fn degree(&self, direction_in: bool) -> usize {
+        let degree = if direction_in {
+            &self.in_degree
+        } else {
+            &self.out_degree
+        };
+
+        std::cmp::max(degree.load(SeqCst), 0) as usize
+    }
+
+    fn add_degree(&self, direction_in: bool, delta: isize) {
+        if direction_in {
+            self.in_degree.fetch_add(delta, SeqCst);
+        } else {
+            self.out_degree.fetch_add(delta, SeqCst);
+        }
+    }
+}
+
 impl<'a, T, E> Node<'a, T, E> {
     #[inline]
     fn new(
@@ -76,6 +115,8 @@ impl<'a, T, E> Node<'a, T, E> {
             value: AtomicCell::new(value),
             next,
             node_desc,
+            out_degree: AtomicIsize::new(0),
+            in_degree: AtomicIsize::new(0),
             out_edges,
             in_edges,
         }
@@ -153,11 +194,59 @@ impl<'a: 't + 'g, 't, 'g, T: 'a + Clone, E: 'a + Clone> Iterator
     }
 }
 
+/// Iterates the vertices with keys in `[lo, hi)` in ascending order,
+/// skipping vertices that are not logically in the list.
+pub struct RangeRefEntry<'a: 't + 'g, 't, 'g, T: 'a, E: 'a> {
+    _parent: &'t AdjacencyList<'a, T, E>,
+    guard: &'g Guard,
+    current: Shared<'g, Node<'a, T, E>>,
+    hi: usize,
+}
+
+impl<'a: 't + 'g, 't, 'g, T: 'a + Clone, E: 'a + Clone> Iterator
+    for RangeRefEntry<'a, 't, 'g, T, E>
+{
+    type Item = RefEntry<'a, 'g, T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            while let Some(current_ref) = self.current.as_ref() {
+                // The tail has key `usize::max_value()`, so we always stop there
+                if current_ref.key >= self.hi {
+                    self.current = Shared::null();
+                    return None;
+                }
+
+                let node = self.current;
+                let next = current_ref.next.load(SeqCst, self.guard);
+                self.current = next.with_tag(clr_mark(next.tag()));
+
+                if AdjacencyList::is_logically_present(current_ref, self.guard) {
+                    return Some(RefEntry { node });
+                }
+            }
+
+            None
+        }
+    }
+}
+
 pub struct AdjacencyList<'a, T, E> {
     head: Atomic<Node<'a, T, E>>,
     cursor: Atomic<Node<'a, T, E>>,
     tail: Atomic<Node<'a, T, E>>,
-    // bloom_filter: BloomFilter,
+    index: VertexIndex<'a, T, E>,
+    md_config: MDListConfig,
+    stats: TxnStats,
+    filter: Option<VertexFilter>,
+    wal: Option<WriteAheadLog<'a, T, E>>,
+    /// The committed `InsertEdge` descriptor shared by every edge connected
+    /// outside of a transaction
+    connected: Atomic<NodeDesc<'a, T, E>>,
+    /// Number of out-edges, updated together with the degrees of the vertices
+    num_out_edges: AtomicIsize,
+    /// Whether the vertices have in-edges, operations that need them fail without
+    keeps_in_edges: bool,
 }
 
 /// Uh... FIXME:(rasmus)
@@ -180,7 +269,13 @@ unsafe impl<#[may_dangle] 'a, #[may_dangle] T, #[may_dangle] E> Drop for Adjacen
 
 impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E> {
     // Public operations
+    /// Creates a list whose `MDList`s are sized for `size_hint` vertices
     pub fn new(size_hint: i64) -> Self {
+        let md_config = MDListConfig::for_key_range(std::cmp::max(size_hint, 0) as usize);
+        Self::with_config(size_hint, md_config)
+    }
+
+    pub fn with_config(size_hint: i64, md_config: MDListConfig) -> Self {
         let guard = &epoch::pin();
         let head = Node::new(0, None, Atomic::null(), Atomic::null(), None, None);
         let tail = Atomic::new(Node::new(
@@ -193,13 +288,421 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         ));
         head.next.store(tail.load(SeqCst, guard), SeqCst);
 
+        let connected = unsafe {
+            let desc = Desc::alloc(vec![Operator {
+                optype: OpType::InsertEdge(0, 0, None, false),
+                derived: false,
+            }]);
+            (*desc).status.store(OpStatus::Committed);
+            (*desc).pending[0].store(false);
+            Atomic::new(NodeDesc::new(desc, 0))
+        };
+
         let head = Atomic::new(head);
         AdjacencyList {
             cursor: head.clone(),
             head,
             tail,
-            // bloom_filter: BloomFilter::create(size_hint, 0.0001),
+            index: VertexIndex::with_capacity(std::cmp::max(size_hint, 0) as usize),
+            md_config,
+            stats: TxnStats::default(),
+            filter: None,
+            wal: None,
+            connected,
+            num_out_edges: AtomicIsize::new(0),
+            keeps_in_edges: true,
+        }
+    }
+
+    /// Creates the vertices without in-edges, for a directed graph whose in-edges are never read.
+    /// Operations that need them fail, as do deletions of vertices,
+    /// since the arcs pointing to a vertex cannot be found.
+    ///
+    /// Has to be called before any vertex is inserted.
+    pub fn disable_in_edges(&mut self) {
+        self.keeps_in_edges = false;
+    }
+
+    pub fn keeps_in_edges(&self) -> bool {
+        self.keeps_in_edges
+    }
+
+    /// Whether `optype` cannot be executed, because the vertices have no in-edges
+    fn lacks_in_edges(&self, optype: &OpType<'a, T, E>) -> bool {
+        !self.keeps_in_edges && (optype.needs_in_edges() || matches!(optype, OpType::Delete(_)))
+    }
+
+    /// Creates the out- and in-edges of a new vertex, with their heads owned by `n_desc`.
+    /// The in-edges are `None` if the list does not keep them.
+    unsafe fn new_edge_lists(
+        &self,
+        n_desc: &Atomic<NodeDesc<'a, T, E>>,
+        guard: &Guard,
+    ) -> (MDList<'a, E, T>, Option<MDList<'a, E, T>>) {
+        let new_list = || {
+            let mdlist = MDList::new(self.md_config);
+            mdlist.head().load(SeqCst, guard).deref_mut().node_desc = n_desc.clone();
+            mdlist
+        };
+
+        let out_edges = new_list();
+        let in_edges = if self.keeps_in_edges {
+            Some(new_list())
+        } else {
+            None
+        };
+
+        (out_edges, in_edges)
+    }
+
+    /// Enables the bloom filter, so that `Find`, `Delete` and `InsertEdge`
+    /// on vertices that were never inserted fail without traversing the list.
+    ///
+    /// The filter is filled with the vertices already in the list.
+    pub fn enable_bloom_filter(&mut self, false_positive_rate: f64) {
+        let guard = &epoch::pin();
+        let keys = unsafe { self.possible_keys(guard) };
+
+        let filter = VertexFilter::new(keys.len(), false_positive_rate);
+        for key in keys {
+            filter.insert(key, guard);
+        }
+
+        self.filter = Some(filter);
+    }
+
+    pub fn bloom_filter(&self) -> Option<&VertexFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Logs every transaction that commits from now on to `wal`,
+    /// after writing a snapshot of the committed state
+    pub(crate) fn enable_wal(&mut self, wal: WriteAheadLog<'a, T, E>) -> std::io::Result<()> {
+        let guard = &epoch::pin();
+        wal.write_snapshot(&mut wal.lock(), self, guard)?;
+        self.wal = Some(wal);
+        Ok(())
+    }
+
+    /// Writes a snapshot of the committed state, and truncates the log
+    pub(crate) fn snapshot(&self) -> std::io::Result<()> {
+        if let Some(wal) = self.wal.as_ref() {
+            let guard = &epoch::pin();
+            wal.write_snapshot(&mut wal.lock(), self, guard)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a snapshot if enough transactions were logged since the last one.
+    ///
+    /// Called once a transaction has finished rather than while committing it,
+    /// so that a thread that helps a transaction to commit never writes a snapshot.
+    pub(crate) fn snapshot_if_due(&self) -> std::io::Result<()> {
+        if let Some(wal) = self.wal.as_ref() {
+            let mut state = wal.lock();
+            if wal.snapshot_due(&state) {
+                let guard = &epoch::pin();
+                wal.write_snapshot(&mut state, self, guard)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn md_config(&self) -> &MDListConfig {
+        &self.md_config
+    }
+
+    /// Returns the number of out-edges of all vertices.
+    ///
+    /// Like the degrees, it is updated once a transaction has committed.
+    pub fn num_out_edges(&self) -> usize {
+        std::cmp::max(self.num_out_edges.load(SeqCst), 0) as usize
+    }
+
+    fn add_degree(&self, parent: &Node<'a, T, E>, direction_in: bool, delta: isize) {
+        parent.add_degree(direction_in, delta);
+        if !direction_in {
+            self.num_out_edges.fetch_add(delta, SeqCst);
+        }
+    }
+
+    /// Returns `false` only if `key` has definitely never been inserted,
+    /// always `true` if the bloom filter is disabled
+    #[inline]
+    fn might_contain(&self, key: usize, guard: &Guard) -> bool {
+        self.filter
+            .as_ref()
+            .map_or(true, |filter| filter.might_contain(key, guard))
+    }
+
+    #[inline]
+    fn filter_insert(&self, key: usize, guard: &Guard) {
+        if let Some(filter) = self.filter.as_ref() {
+            filter.insert(key, guard);
+        }
+    }
+
+    /// Applies the edges inserted and deleted by a committed transaction
+    /// to the degrees of the vertices it updated.
+    ///
+    /// The vertices are not looked up by key, as a vertex may have been
+    /// deleted and inserted again since the transaction committed.
+    unsafe fn record_degrees(&self, desc: *const Desc<'a, T, E>) {
+        for (op, parents) in (*desc).ops.iter().zip((*desc).parents.iter()) {
+            let (directions, delta): (&[bool], isize) = match op.optype {
+                OpType::InsertEdge(.., direction_in) => (&[direction_in], 1),
+                OpType::DeleteEdge(.., direction_in) => (&[direction_in], -1),
+                OpType::InsertArc(..) => (&[false, true], 1),
+                OpType::DeleteArc(..) => (&[false, true], -1),
+                _ => continue,
+            };
+
+            for (parent, direction_in) in parents.iter().zip(directions) {
+                if let Some(parent) = parent.load().as_ref() {
+                    self.add_degree(parent, *direction_in, delta);
+                }
+            }
+        }
+    }
+
+    /// Counts the vertices deleted by a committed transaction,
+    /// and rebuilds the bloom filter once enough deletions have accumulated
+    unsafe fn record_deletes(&self, deleted: &[Shared<Node<'a, T, E>>], guard: &Guard) {
+        if let Some(filter) = self.filter.as_ref() {
+            let mut rebuild = false;
+            for _ in deleted.iter().filter(|node| !node.is_null()) {
+                rebuild |= filter.record_delete();
+            }
+
+            if rebuild {
+                filter.rebuild(|| self.possible_keys(guard).into_iter(), guard);
+            }
+        }
+    }
+
+    /// Collects the keys of every node that is in the list,
+    /// or will be if a pending transaction commits
+    unsafe fn possible_keys(&self, guard: &Guard) -> Vec<usize> {
+        let mut keys = Vec::new();
+        let head = self.head.load(SeqCst, guard);
+        let mut current = head.deref().next.load(SeqCst, guard);
+
+        while let Some(current_ref) = current.with_tag(clr_mark(current.tag())).as_ref() {
+            if current_ref.key == usize::max_value() {
+                break;
+            }
+
+            let node_desc = current_ref.node_desc.load(SeqCst, guard);
+            let possible = match node_desc.with_tag(clr_mark(node_desc.tag())).as_ref() {
+                Some(node_desc_ref) => match (*node_desc_ref.desc).status.load() {
+                    OpStatus::Active => true,
+                    _ => Self::is_key_exist(node_desc_ref, guard),
+                },
+                None => true,
+            };
+
+            if possible {
+                keys.push(current_ref.key);
+            }
+
+            current = current_ref.next.load(SeqCst, guard);
+        }
+
+        keys
+    }
+
+    /// Links `keys` into the list without executing any transactions.
+    /// Every vertex is given the same, already committed, `Insert` descriptor.
+    ///
+    /// Returns the inserted nodes in the same order as `keys`.
+    ///
+    /// # Safety
+    ///
+    /// The list must be empty and not yet shared with other threads.
+    /// `keys` must be sorted, unique and less than `usize::max_value()`.
+    pub unsafe fn bulk_insert_vertices(
+        &self,
+        keys: &[usize],
+        guard: &'g Guard,
+    ) -> Vec<Shared<'g, Node<'a, T, E>>> {
+        let desc = Desc::alloc(vec![Operator {
+            optype: OpType::Insert(0, None),
+            derived: false,
+        }]);
+        (*desc).status.store(OpStatus::Committed);
+        (*desc).pending[0].store(false);
+        let n_desc = Atomic::new(NodeDesc::new(desc, 0));
+
+        let head = self.head.load(SeqCst, guard);
+        let mut next = head.deref().next.load(SeqCst, guard);
+        let mut inserted = Vec::with_capacity(keys.len());
+
+        for key in keys.iter().rev() {
+            let (out_edges, in_edges) = self.new_edge_lists(&n_desc, guard);
+            let node = Owned::new(Node::new(
+                *key,
+                None,
+                Atomic::null(),
+                n_desc.clone(),
+                Some(out_edges),
+                in_edges,
+            ));
+            node.next.store(next, Relaxed);
+
+            next = node.into_shared(guard);
+            self.index.insert(*key, next, guard);
+            self.filter_insert(*key, guard);
+            inserted.push(next);
+        }
+
+        head.deref().next.store(next, SeqCst);
+        if let Some(last) = inserted.first() {
+            self.cursor.store(*last, SeqCst);
+        }
+
+        inserted.reverse();
+        inserted
+    }
+
+    /// Looks up a vertex that is logically in the list.
+    ///
+    /// The vertex index is consulted first, and the list is
+    /// only traversed if the indexed node has been unlinked,
+    /// or if the vertex has not been indexed yet.
+    pub fn get_vertex(&self, key: usize, guard: &'g Guard) -> Option<Shared<'g, Node<'a, T, E>>> {
+        if !self.might_contain(key, guard) {
+            return None;
+        }
+
+        unsafe {
+            if let Some(node) = self.index.get(key, guard) {
+                let node_ref = node.deref();
+                if !is_marked(node_ref.next.load(SeqCst, guard).tag()) {
+                    return if Self::is_logically_present(node_ref, guard) {
+                        Some(node)
+                    } else {
+                        None
+                    };
+                }
+            }
+        }
+
+        self.range(key..=key, guard).next().map(|entry| entry.node)
+    }
+
+    /// Checks if `key` is logically in the list.
+    ///
+    /// Vertices are indexed before the transaction that inserts them commits,
+    /// so unlike `get_vertex`, a key that is not indexed is missing without traversing the list.
+    pub fn contains_vertex(&self, key: usize, guard: &'g Guard) -> bool {
+        unsafe {
+            match self.index.get(key, guard) {
+                Some(node) if !is_marked(node.deref().next.load(SeqCst, guard).tag()) => {
+                    Self::is_logically_present(node.deref(), guard)
+                }
+                Some(_) => self.get_vertex(key, guard).is_some(),
+                None => false,
+            }
+        }
+    }
+
+    /// Contention counters for transactions executed on this list
+    pub fn stats(&self) -> &TxnStats {
+        &self.stats
+    }
+
+    /// Estimates the bytes held by the nodes that are physically in the list,
+    /// including logically deleted ones, and the descriptors installed on them.
+    /// The buffer of the write-ahead log is not included.
+    pub fn memory_usage(&self, guard: &Guard) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        // Descriptors are shared between the nodes touched by the same transaction
+        let mut seen = HashSet::new();
+
+        usage.vertices += self.index.heap_size(guard);
+        if let Some(filter) = self.filter.as_ref() {
+            usage.vertices += filter.heap_size();
+        }
+
+        unsafe {
+            let mut node = self.head.load(SeqCst, guard);
+            while let Some(node_ref) = node.as_ref() {
+                let next = node_ref.next.load(SeqCst, guard).with_tag(0);
+
+                let size = mem::size_of::<Node<'a, T, E>>();
+                if node_ref.out_edges.is_none() {
+                    // The head and tail sentinels
+                    usage.overhead += size + memory::ALLOCATION_HEADER;
+                    node = next;
+                    continue;
+                }
+
+                usage.vertices += size;
+                usage.overhead += memory::ALLOCATION_HEADER;
+                usage += Self::descriptor_usage(node_ref.node_desc.load(SeqCst, guard), &mut seen);
+
+                for mdlist in node_ref.out_edges.iter().chain(node_ref.in_edges.iter()) {
+                    let head = mdlist.head().load(SeqCst, guard).as_raw();
+                    mdlist.visit_nodes(guard, |md_node| {
+                        let size = mem::size_of::<MDNode<'a, E, T>>();
+                        if std::ptr::eq(md_node, head) {
+                            usage.overhead += size;
+                        } else {
+                            usage.edges += size;
+                        }
+                        usage.overhead += memory::ALLOCATION_HEADER;
+
+                        let pending = md_node.pending.load(SeqCst, guard);
+                        if !pending.is_null() {
+                            usage.descriptors += mem::size_of_val(pending.deref());
+                            usage.overhead += memory::ALLOCATION_HEADER;
+                        }
+
+                        let node_desc = md_node.node_desc.load(SeqCst, guard);
+                        usage += Self::descriptor_usage(node_desc, &mut seen);
+                    });
+                }
+
+                node = next;
+            }
+        }
+
+        usage
+    }
+
+    /// Counts a node descriptor and the transaction it belongs to, unless they were already `seen`
+    unsafe fn descriptor_usage(
+        node_desc: Shared<NodeDesc<'a, T, E>>,
+        seen: &mut HashSet<usize>,
+    ) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        let node_desc = node_desc.with_tag(0);
+        let node_desc_ref = match node_desc.as_ref() {
+            Some(node_desc_ref) => node_desc_ref,
+            None => return usage,
+        };
+
+        if seen.insert(node_desc.as_raw() as usize) {
+            usage.descriptors += mem::size_of::<NodeDesc<'a, T, E>>();
+            usage.overhead += memory::ALLOCATION_HEADER;
         }
+
+        let desc = node_desc_ref.desc;
+        if !desc.is_null() && seen.insert(desc as usize) {
+            let (ops, unused_ops) =
+                memory::vec::<Operator<'a, T, E>>((*desc).ops.len(), (*desc).ops.capacity());
+            let (pending, unused_pending) =
+                memory::vec::<AtomicCell<bool>>((*desc).pending.len(), (*desc).pending.capacity());
+            let (parents, unused_parents) = memory::vec::<[AtomicCell<*const Node<'a, T, E>>; 2]>(
+                (*desc).parents.len(),
+                (*desc).parents.capacity(),
+            );
+            usage.descriptors += mem::size_of::<Desc<'a, T, E>>() + ops + pending + parents;
+            usage.overhead +=
+                memory::ALLOCATION_HEADER + unused_ops + unused_pending + unused_parents;
+        }
+
+        usage
     }
 
     pub fn iter<'t>(&'t self, guard: &'g Guard) -> IterRefEntry<'a, 't, 'g, T, E>
@@ -213,6 +716,60 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         }
     }
 
+    /// Returns the vertices with keys within `range`, in ascending order.
+    ///
+    /// The scan starts at the first key in the range, rather than at the head,
+    /// and the whole traversal is protected by `guard`.
+    pub fn range<'t, R: RangeBounds<usize>>(
+        &'t self,
+        range: R,
+        guard: &'g Guard,
+    ) -> RangeRefEntry<'a, 't, 'g, T, E>
+    where
+        'a: 't + 'g,
+    {
+        let lo = match range.start_bound() {
+            Bound::Included(lo) => *lo,
+            Bound::Excluded(lo) => lo.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+
+        let hi = match range.end_bound() {
+            Bound::Included(hi) => hi.saturating_add(1),
+            Bound::Excluded(hi) => *hi,
+            Bound::Unbounded => usize::max_value(),
+        };
+
+        let pred = &mut Shared::null();
+        let current = &mut self.head.load(SeqCst, guard);
+        unsafe {
+            self.locate_pred(pred, current, lo, guard);
+        }
+
+        RangeRefEntry {
+            _parent: self,
+            guard,
+            current: *current,
+            hi,
+        }
+    }
+
+    /// Returns the first vertex with a key greater than `key`
+    pub fn successor(&self, key: usize, guard: &'g Guard) -> Option<RefEntry<'a, 'g, T, E>> {
+        if key == usize::max_value() {
+            return None;
+        }
+
+        self.range((Bound::Excluded(key), Bound::Unbounded), guard).next()
+    }
+
+    /// Returns the last vertex with a key less than `key`
+    ///
+    /// The list is singly linked, so this scans every key below `key`.
+    pub fn predecessor(&self, key: usize, guard: &'g Guard) -> Option<RefEntry<'a, 'g, T, E>> {
+        self.range(..key, guard).last()
+    }
+
     pub fn execute_ops<'t>(
         &'t self,
         desc: *const Desc<'a, T, E>,
@@ -259,20 +816,28 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
     {
         let guard = &*(guard as *const _);
         *inserted = Shared::null();
-        let cursor = self.cursor.load_consume(guard);
 
         let current = &mut self.head.load_consume(guard);
 
         let n_desc = Atomic::new(NodeDesc::new(desc, opid));
         loop {
-            // if self.bloom_filter.might_contain(vertex) {
-            //     self.locate_pred(pred, current, vertex, guard);
-            // } else {
-            // If the node is definitely not in the list, we skip the location on pred,
-            // and just append the vertex to the end of the list.
-            *pred = cursor;
-            *current = cursor.as_ref().unwrap().next.load_consume(guard);
-            // }
+            // Vertices are mostly inserted in ascending order, so if the key fits
+            // directly after the last inserted node, we skip the location of pred.
+            let cursor = self.cursor.load_consume(guard);
+            let cursor_ref = cursor.as_ref().unwrap();
+            let cursor_next = cursor_ref.next.load_consume(guard);
+            if cursor_ref.key < vertex
+                && !is_marked(cursor_next.tag())
+                && cursor_next
+                    .as_ref()
+                    .map_or(false, |next| next.key >= vertex)
+            {
+                *pred = cursor;
+                *current = cursor_next;
+            } else {
+                *current = self.head.load_consume(guard);
+                self.locate_pred(pred, current, vertex, guard);
+            }
 
             // Check if node is physically in the list
             if Self::is_node_exist(*current, vertex) {
@@ -298,6 +863,8 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     // We are the only one accessing n_desc...
                     n_desc.load(Relaxed, epoch::unprotected()).as_ref().unwrap(),
                 ) {
+                    // The thread that inserted the node may not have reached the filter yet
+                    self.filter_insert(vertex, guard);
                     return ReturnCode::Skip;
                 }
 
@@ -318,15 +885,17 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         .as_ref()
                         .unwrap()
                         .node_desc
-                        .compare_and_set(
+                        .compare_exchange(
                             g_current_desc,
                             n_desc.load(Relaxed, epoch::unprotected()),
                             SeqCst,
+                            SeqCst,
                             guard,
                         )
                         .is_ok()
                     {
                         *inserted = *current;
+                        self.filter_insert(vertex, guard);
                         return ReturnCode::Inserted(self.cursor.clone());
                         // return ReturnCode::Inserted(RefEntry { node: *inserted });
                     }
@@ -339,31 +908,32 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                 let mut new_node = None;
                 if new_node.is_none() {
-                    let in_edges = MDList::new(KEY_RANGE);
-                    let out_edges = MDList::new(KEY_RANGE);
-
-                    in_edges.head().load(SeqCst, guard).deref_mut().node_desc = n_desc.clone();
-                    out_edges.head().load(SeqCst, guard).deref_mut().node_desc = n_desc.clone();
-
+                    let (out_edges, in_edges) = self.new_edge_lists(&n_desc, guard);
                     new_node.replace(Node::new(
                         vertex,
                         value.clone(),
                         Atomic::null(),
                         n_desc.clone(),
-                        Some(in_edges),
                         Some(out_edges),
+                        in_edges,
                     ));
                 }
 
                 new_node.as_ref().unwrap().next.store(*current, Relaxed);
 
                 let next = &pred.as_ref().unwrap().next;
-                if let Ok(p) =
-                    next.compare_and_set(*current, Owned::new(new_node.unwrap()), SeqCst, guard)
-                {
+                if let Ok(p) = next.compare_exchange(
+                    *current,
+                    Owned::new(new_node.unwrap()),
+                    SeqCst,
+                    SeqCst,
+                    guard,
+                ) {
                     *inserted = p;
-                    self.cursor.store(*inserted, Relaxed);
-                    // self.bloom_filter.set(vertex);
+                    self.index.insert(vertex, p, guard);
+                    // Publishes the node to threads that skip to the cursor
+                    self.cursor.store(*inserted, SeqCst);
+                    self.filter_insert(vertex, guard);
                     return ReturnCode::Inserted(self.cursor.clone());
                     // return ReturnCode::Inserted(RefEntry { node: *inserted });
                 }
@@ -377,13 +947,15 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         }
     }
 
-    /// Connects two nodes
+    /// Connects two nodes without executing a transaction.
+    /// The edge is given the list's committed `InsertEdge` descriptor.
     ///
     /// # Safety
     ///
     /// Should not be called directly?
     #[inline]
     pub unsafe fn connect<'t>(
+        &self,
         vertex_node: &Node<'a, T, E>,
         edge: usize,
         edge_node: E,
@@ -391,7 +963,9 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
     ) -> ReturnCode<Atomic<Node<'a, T, E>>> {
         let dim = &mut 0;
         let pred_dim = &mut 0;
-        let guard = &*(&epoch::pin() as *const _);
+        // The guard has to outlive the loop, as the `MDList` defers the destruction of descriptors
+        let pinned = epoch::pin();
+        let guard = &*(&pinned as *const _);
         let inserted: &mut Shared<'a, MDNode<'a, E, T>> = &mut Shared::null();
         let md_pred: &mut Shared<'a, MDNode<'a, E, T>> = &mut Shared::null();
 
@@ -405,27 +979,31 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         };
         let md_current = &mut mdlist.head().load(SeqCst, guard);
 
-        let new_md_node = MDNode::new(edge, Some(edge_node));
+        let mut new_md_node = MDNode::new(edge, Some(edge_node), mdlist.config());
+        new_md_node.node_desc = self.connected.clone();
         let new_node = Atomic::new(new_md_node);
 
-        let coord = MDList::<E, T>::key_to_coord(edge);
-        MDList::locate_pred(&coord, md_pred, md_current, dim, pred_dim, guard);
-        let md_pred_ref = md_pred.as_ref().expect("MDPred was NULL");
-        let pred_child = md_pred_ref.children[*pred_dim].load(SeqCst, guard);
-
-        // Check if the node is physically NOT within the list, or that it is there, but marked for deletion
-        // If it is marked for deletion, the mdlist will physically remove it during the call to mdlist->Insert
-        if !Self::is_mdnode_exist(*md_current, edge) || is_delinv(pred_child.tag()) {
-            //Check if our transaction has been aborted by another thread
-            let result = mdlist.insert(&new_node, md_pred, md_current, dim, pred_dim, guard);
-
-            if result {
-                *inserted = new_node.load(SeqCst, guard);
+        let coord = mdlist.key_to_coord(edge);
+        loop {
+            mdlist.locate_pred(&coord, md_pred, md_current, dim, pred_dim, guard);
+            let md_pred_ref = md_pred.as_ref().expect("MDPred was NULL");
+            let pred_child = md_pred_ref.children[*pred_dim].load(SeqCst, guard);
+
+            // Check if the node is physically NOT within the list, or that it is there, but marked for deletion
+            // If it is marked for deletion, the mdlist will physically remove it during the call to mdlist->Insert
+            if !Self::is_mdnode_exist(*md_current, edge) || is_delinv(pred_child.tag()) {
+                // A failed insert leaves the position to retry from in `md_pred` and `md_current`
+                if mdlist.insert(&new_node, md_pred, md_current, dim, pred_dim, guard) {
+                    *inserted = new_node.load(SeqCst, guard);
+                    self.add_degree(vertex_node, direction_in, 1);
+                    return ReturnCode::Success;
+                }
+            } else {
+                // The edge already exists, so the new node was never linked
+                drop(new_node.into_owned());
                 return ReturnCode::Success;
             }
         }
-
-        ReturnCode::Success
     }
 
     #[inline]
@@ -443,12 +1021,16 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         dim: &mut usize,
         pred_dim: &mut usize,
         guard: &Guard,
-    ) -> ReturnCode<Atomic<Node<T, E>>> {
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>> {
         let guard = &*(guard as *const _);
         *inserted = Shared::null();
         *md_pred = Shared::null();
 
-        let n_desc = Atomic::new(NodeDesc::new(desc, opid));
+        // The value is carried by the descriptor as well, for when an edge
+        // that was deleted is inserted again, reusing its node
+        let mut new_desc = NodeDesc::new(desc, opid);
+        new_desc.value = value.clone();
+        let n_desc = Atomic::new(new_desc);
         let g_n_desc = &mut n_desc.load(SeqCst, guard);
 
         // Try to find the vertex to which the current key is adjacenct,
@@ -462,13 +1044,13 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                 };
                 let md_current = &mut mdlist.head().load(SeqCst, guard);
 
-                let mut new_md_node = MDNode::new(edge, value.clone());
+                let mut new_md_node = MDNode::new(edge, value.clone(), mdlist.config());
                 new_md_node.node_desc = n_desc.clone();
                 let new_node = Atomic::new(new_md_node);
 
                 loop {
-                    let coord = MDList::<E, T>::key_to_coord(edge);
-                    MDList::locate_pred(&coord, md_pred, md_current, dim, pred_dim, guard);
+                    let coord = mdlist.key_to_coord(edge);
+                    mdlist.locate_pred(&coord, md_pred, md_current, dim, pred_dim, guard);
                     let md_pred_ref = md_pred.as_ref().unwrap();
                     let pred_child = md_pred_ref.children[*pred_dim].load(SeqCst, guard);
 
@@ -486,24 +1068,35 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                         self.finish_pending_txn(pred_current_desc.with_tag(clr_mark(pred_current_desc.tag())), desc, guard);
 
-                        let same_op = if let (Some(a), Some(b)) =
+                        let (same_op, same_txn) = if let (Some(a), Some(b)) =
                             (pred_current_desc.as_ref(), g_n_desc.as_ref())
                         {
-                            Self::is_same_operation(a, b)
+                            (
+                                Self::is_same_operation(a, b),
+                                Self::is_same_transaction(a, b),
+                            )
                         } else {
-                            false
+                            (false, false)
                         };
 
+                        // If the pred is owned by an earlier operation of this transaction,
+                        // e.g. a DeleteEdge, overwriting its descriptor would undo that operation
+                        let owned = same_op || same_txn;
+
                         let mut pred_desc = Atomic::null();
-                        if !same_op {
+                        if !owned {
+                            // Only the head of an `MDList` that is not part of a vertex
+                            // has no descriptor
                             let exists =
                                 if let Some(pred_current_desc_ref) = pred_current_desc.as_ref() {
                                     Self::is_key_exist(pred_current_desc_ref, guard)
                                 } else {
-                                    false
+                                    true
                                 };
 
                             let mut new_pred_desc = NodeDesc::new(desc, opid);
+                            new_pred_desc.value =
+                                Self::resolve_edge_value(md_pred_ref, pred_current_desc).cloned();
                             if exists {
                                 new_pred_desc.override_as_find = true;
                             } else {
@@ -521,17 +1114,24 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         //      DeleteVertex will find an adoption descriptor in md_pred's predecessor. This descriptor will move all children of md_pred to that node.
                         //      If InsertEdge sucessfully added it's new node to md_pred, the DeleteVertex will find it after the adoption process.
                         //      If InsertEdge is too slow to add it's new node, its CAS will fail during the insert process, and it will re-traverse
-                        if same_op
+                        if owned
                             || md_pred_ref
                                 .node_desc
-                                .compare_and_set(
+                                .compare_exchange(
                                     pred_current_desc,
                                     pred_desc.load(SeqCst, guard),
                                     SeqCst,
+                                    SeqCst,
                                     guard,
                                 )
                                 .is_ok()
                         {
+                            // A DeleteVertex that traversed the edges before the pred was updated
+                            // has missed the new node, so the vertex must still exist
+                            if !self.is_vertex_retained(current_ref, desc, guard) {
+                                return ReturnCode::Fail("Vertex was deleted".into());
+                            }
+
                             // Do insert
                             let result =
                                 mdlist.insert(&new_node, md_pred, md_current, dim, pred_dim, guard);
@@ -541,6 +1141,12 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                                 return ReturnCode::Success;
                             }
                         }
+
+                        // Another thread changed the position, so we search again from the head
+                        *md_current = mdlist.head().load(SeqCst, guard);
+                        *dim = 0;
+                        *pred_dim = 0;
+                        continue;
                     } else {
                         let current_desc =
                             md_current.as_ref().unwrap().node_desc.load(SeqCst, guard);
@@ -553,10 +1159,11 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                             let pred_child = &md_pred_ref.children[*pred_dim];
                             if !is_delinv(pred_child.load(SeqCst, epoch::unprotected()).tag())
                                 && pred_child
-                                    .compare_and_set(
+                                    .compare_exchange(
                                         *md_current,
                                         md_current.with_tag(set_delinv(md_current.tag())),
                                         SeqCst,
+                                        SeqCst,
                                         guard,
                                     )
                                     .is_err()
@@ -594,14 +1201,21 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                                 .as_ref()
                                 .unwrap()
                                 .node_desc
-                                .compare_and_set(
+                                .compare_exchange(
                                     current_desc,
                                     n_desc.load(SeqCst, guard),
                                     SeqCst,
+                                    SeqCst,
                                     guard,
                                 )
                                 .is_ok()
                             {
+                                // As for a new node, a DeleteVertex or FindEdges that
+                                // passed the node before it was reinserted has missed it
+                                if !self.is_vertex_retained(current_ref, desc, guard) {
+                                    return ReturnCode::Fail("Vertex was deleted".into());
+                                }
+
                                 return ReturnCode::Success;
                             };
                         }
@@ -624,7 +1238,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         deleted: &mut Shared<'t, Node<'a, T, E>>,
         pred: &mut Shared<'t, Node<'a, T, E>>,
         guard: &Guard,
-    ) -> ReturnCode<Atomic<Node<T, E>>>
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>>
     where
         'a: 't,
     {
@@ -632,6 +1246,10 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         let guard = &*(guard as *const _);
 
         *deleted = Shared::null();
+        if !self.might_contain(vertex, guard) {
+            return ReturnCode::Fail("Requested vertex was not found".into());
+        }
+
         let current = &mut self.head.load(SeqCst, guard);
 
         let node_desc = Atomic::new(NodeDesc::new(desc, opid));
@@ -656,21 +1274,14 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     // Check if DeleteVertex operation is ongoing
                     let pending_status = &(*desc).pending[opid];
                     if !pending_status.load() {
-                        self.finish_delete_vertex(
-                            current
-                                .as_ref()
-                                .unwrap()
-                                .out_edges
-                                .as_ref()
-                                .unwrap()
-                                .head()
-                                .load(SeqCst, guard),
-                            0,
+                        if !self.finish_delete_edges(
+                            current.as_ref().unwrap(),
                             desc,
                             &node_desc,
-                            16, // FIXME:(rasmus) Fix magic number, should be DIMENSION
                             guard,
-                        );
+                        ) {
+                            return ReturnCode::Fail("Vertex still has edges".into());
+                        }
 
                         // Only allow the thread that marks the operation
                         // complete to perform physical updates
@@ -690,27 +1301,21 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         _ => return ReturnCode::Fail("Transaction is Inactive".into()),
                     }
 
-                    if let Ok(_p) = current.as_ref().unwrap().node_desc.compare_and_set(
+                    if let Ok(_p) = current.as_ref().unwrap().node_desc.compare_exchange(
                         g_current_desc,
                         node_desc.load(SeqCst, guard),
                         SeqCst,
+                        SeqCst,
                         guard,
                     ) {
-                        self.finish_delete_vertex(
-                            current
-                                .as_ref()
-                                .unwrap()
-                                .out_edges
-                                .as_ref()
-                                .unwrap()
-                                .head()
-                                .load(SeqCst, guard),
-                            0,
+                        if !self.finish_delete_edges(
+                            current.as_ref().unwrap(),
                             desc,
                             &node_desc,
-                            16, // FIXME:(rasmus) Fix magic number, should be DIMENSION
                             guard,
-                        );
+                        ) {
+                            return ReturnCode::Fail("Vertex still has edges".into());
+                        }
 
                         // Only allow the thread that marks the operation
                         // complete to perform physical updates
@@ -744,7 +1349,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         dim: &mut usize,
         pred_dim: &mut usize,
         guard: &Guard,
-    ) -> ReturnCode<Atomic<Node<T, E>>> {
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>> {
         // Lifetime hack to bind guard to lifetime of self
         let guard = &*(guard as *const _);
         *deleted = Shared::null();
@@ -771,9 +1376,9 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     .expect("NO MD LIST")
             };
             let md_current = &mut md_list.head().load(SeqCst, guard);
-            let coord = &MDList::<T, E>::key_to_coord(edge);
+            let coord = &md_list.key_to_coord(edge);
             loop {
-                MDList::locate_pred(coord, md_pred, md_current, dim, pred_dim, guard);
+                md_list.locate_pred(coord, md_pred, md_current, dim, pred_dim, guard);
 
                 if Self::is_mdnode_exist(*md_current, edge) {
                     let current_desc = &md_current.as_ref().expect("NO CURRENT DESC").node_desc; // Safe
@@ -785,12 +1390,8 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                     self.finish_pending_txn(g_current_desc, desc, guard);
 
-                    if let (Some(g_c), Some(g_n)) = (g_current_desc.as_ref(), g_n_desc.as_ref())
-                    {
-                        if Self::is_same_operation(
-                            g_c,
-                            g_n,
-                        ) {
+                    if let (Some(g_c), Some(g_n)) = (g_current_desc.as_ref(), g_n_desc.as_ref()) {
+                        if Self::is_same_operation(g_c, g_n) {
                             return ReturnCode::Skip;
                         }
                     } else {
@@ -802,11 +1403,23 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                             OpStatus::Active => {}
                             _ => return ReturnCode::Fail("Transaction is inactive".into()),
                         }
+                        // The value is restored if the transaction aborts
+                        let mut new_desc = NodeDesc::new(desc, opid);
+                        new_desc.value =
+                            Self::resolve_edge_value(md_current.deref(), g_current_desc).cloned();
+                        let new_desc = Atomic::new(new_desc);
+
                         if md_current
                             .as_ref()
                             .unwrap()
                             .node_desc
-                            .compare_and_set(g_current_desc, *g_n_desc, SeqCst, guard)
+                            .compare_exchange(
+                                g_current_desc,
+                                new_desc.load(SeqCst, guard),
+                                SeqCst,
+                                SeqCst,
+                                guard,
+                            )
                             .is_ok()
                         {
                             *deleted = *md_current;
@@ -827,20 +1440,170 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         }
     }
 
-    #[inline]
-    unsafe fn find<'t>(
+    /// Owns an existing edge, so that its value is changed when the transaction commits.
+    /// Until then, the edge keeps the value it had.
+    unsafe fn update_edge<'t>(
         &'t self,
-        key: usize,
+        vertex: usize,
+        edge: usize,
+        direction_in: bool,
         desc: *const Desc<'a, T, E>,
         opid: usize,
         guard: &Guard,
-    ) -> ReturnCode<Atomic<Node<'a, T, E>>>
-    where
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>> {
+        // Lifetime hack to bind guard to lifetime of self
+        let guard = &*(guard as *const _);
+        let current = &mut self.head.load(SeqCst, guard);
+
+        let n_desc = Atomic::new(NodeDesc::new(desc, opid));
+        let g_n_desc = &mut n_desc.load(SeqCst, guard);
+
+        if !self.find_vertex(current, g_n_desc, desc, vertex, guard) {
+            return ReturnCode::Fail("Requested vertex was not found".into());
+        }
+
+        let current_ref = current.as_ref().expect("NO CURRENT");
+        let md_list = if direction_in {
+            &current_ref.in_edges
+        } else {
+            &current_ref.out_edges
+        }
+        .as_ref()
+        .expect("NO MD LIST");
+
+        let md_pred = &mut Shared::null();
+        let md_current = &mut md_list.head().load(SeqCst, guard);
+        let (mut dim, mut pred_dim) = (0, 0);
+        md_list.locate_pred(
+            &md_list.key_to_coord(edge),
+            md_pred,
+            md_current,
+            &mut dim,
+            &mut pred_dim,
+            guard,
+        );
+
+        if !Self::is_mdnode_exist(*md_current, edge) {
+            return ReturnCode::Fail("MDNode does not exists".into());
+        }
+
+        let md_current_ref = md_current.as_ref().unwrap();
+        loop {
+            let g_current_desc = md_current_ref.node_desc.load(SeqCst, guard);
+            if is_marked(g_current_desc.tag()) {
+                return ReturnCode::Fail("Node was already marked".into());
+            }
+
+            self.finish_pending_txn(g_current_desc, desc, guard);
+
+            match g_current_desc.as_ref() {
+                Some(g_c) if Self::is_same_operation(g_c, g_n_desc.as_ref().unwrap()) => {
+                    return ReturnCode::Skip
+                }
+                Some(g_c) if Self::is_key_exist(g_c, guard) => {}
+                _ => return ReturnCode::Fail("Key does not exists".into()),
+            }
+
+            match (*desc).status.load() {
+                OpStatus::Active => {}
+                _ => return ReturnCode::Fail("Transaction is inactive".into()),
+            }
+
+            let mut new_desc = NodeDesc::new(desc, opid);
+            new_desc.value = Self::resolve_edge_value(md_current_ref, g_current_desc).cloned();
+            let new_desc = Atomic::new(new_desc);
+
+            if md_current_ref
+                .node_desc
+                .compare_exchange(
+                    g_current_desc,
+                    new_desc.load(SeqCst, guard),
+                    SeqCst,
+                    SeqCst,
+                    guard,
+                )
+                .is_ok()
+            {
+                return ReturnCode::Success;
+            }
+        }
+    }
+
+    /// Finds a vertex, and checks that every edge in one of its `MDList`s is owned
+    /// by an earlier operation of the transaction.
+    ///
+    /// The transaction is conflicted if another edge is present,
+    /// i.e. one that was added after the graph read the edges of the vertex.
+    unsafe fn find_edges<'t>(
+        &'t self,
+        vertex: usize,
+        direction_in: bool,
+        desc: *const Desc<'a, T, E>,
+        opid: usize,
+        guard: &Guard,
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>>
+    where
+        'a: 't,
+    {
+        // Lifetime hack to bind guard to lifetime of self
+        let guard = &*(guard as *const _);
+
+        // Owning the vertex makes an edge that is added after it has been passed
+        // help this transaction first, see `is_vertex_retained`
+        if let ReturnCode::Fail(reason) = self.find(vertex, desc, opid, guard) {
+            return ReturnCode::Fail(reason);
+        }
+
+        let current = &mut self.head.load(SeqCst, guard);
+        let node_desc = Atomic::new(NodeDesc::new(desc, opid));
+        let g_node_desc = &mut node_desc.load(SeqCst, guard);
+        if !self.find_vertex(current, g_node_desc, desc, vertex, guard) {
+            return ReturnCode::Fail("Requested vertex was not found".into());
+        }
+
+        let current_ref = current.as_ref().expect("NO CURRENT");
+        let md_list = if direction_in {
+            &current_ref.in_edges
+        } else {
+            &current_ref.out_edges
+        }
+        .as_ref()
+        .expect("NO MD LIST");
+
+        if self.finish_delete_vertex(
+            md_list.head().load(SeqCst, guard),
+            true,
+            0,
+            desc,
+            &node_desc,
+            self.md_config.dimension,
+            true,
+            guard,
+        ) {
+            ReturnCode::Success
+        } else {
+            ReturnCode::Fail("Vertex has edges that were not read".into())
+        }
+    }
+
+    #[inline]
+    unsafe fn find<'t>(
+        &'t self,
+        key: usize,
+        desc: *const Desc<'a, T, E>,
+        opid: usize,
+        guard: &Guard,
+    ) -> ReturnCode<Atomic<Node<'a, T, E>>>
+    where
         'a: 't,
     {
         // Hack to bind lifetime of guard to self.
         let guard = &*(guard as *const _);
 
+        if !self.might_contain(key, guard) {
+            return ReturnCode::Fail("Reqested node does not exist".into());
+        }
+
         let pred = &mut Shared::null();
         let current = &mut self.head.load(SeqCst, guard);
 
@@ -884,7 +1647,13 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                     if current_ref
                         .node_desc
-                        .compare_and_set(g_current_desc, n_desc.load(SeqCst, guard), SeqCst, guard)
+                        .compare_exchange(
+                            g_current_desc,
+                            n_desc.load(SeqCst, guard),
+                            SeqCst,
+                            SeqCst,
+                            guard,
+                        )
                         .is_ok()
                     {
                         return ReturnCode::Success;
@@ -920,9 +1689,14 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         HELPSTACK.with(|hs| {
             for d in hs.borrow().iter() {
                 if std::ptr::eq(*d as *const _, desc) {
-                    (*desc)
+                    if (*desc)
                         .status
-                        .compare_and_swap(OpStatus::Active, OpStatus::Aborted);
+                        .compare_exchange(OpStatus::Active, OpStatus::Aborted)
+                        .is_ok()
+                    {
+                        (*desc).conflict.store(true);
+                        self.stats.record_abort();
+                    }
                     return;
                 }
             }
@@ -962,6 +1736,10 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                 let op = &(*desc).ops[opid];
 
                 match &op.optype {
+                    optype if self.lacks_in_edges(optype) => {
+                        ret = ReturnCode::Fail("The vertices have no in-edges".into());
+                    }
+
                     OpType::Insert(key, value) => {
                         let mut inserted = Shared::null();
                         let mut pred = Shared::null();
@@ -987,7 +1765,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         let mut dim = 0;
                         let mut pred_dim = 0;
 
-                        self.insert_edge(
+                        ret = self.insert_edge(
                             *vertex,
                             *edge,
                             value,
@@ -1002,6 +1780,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                             guard,
                         );
 
+                        Self::record_parent(desc, opid, 0, parent, &ret);
                         md_ins_nodes.push(inserted);
                         md_ins_pred_nodes.push(md_pred);
                         md_ins_parent_nodes.push(parent);
@@ -1009,15 +1788,17 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         md_ins_pred_dims.push(pred_dim);
                     }
 
-                    OpType::Connect(vertex, edge_id, edge) => {
-                        panic!("INSTRUCTION NOT ALLOWED IN TXN");
+                    OpType::Connect(..) => {
+                        // Connecting is only allowed outside of a transaction, as it is not logged
+                        ret = ReturnCode::Fail("Connect is not allowed in a transaction".into());
                     }
 
                     OpType::Delete(vertex) => {
                         let mut deleted = Shared::null();
                         let mut pred = Shared::null();
 
-                        self.delete_vertex(*vertex, desc, opid, &mut deleted, &mut pred, guard);
+                        ret =
+                            self.delete_vertex(*vertex, desc, opid, &mut deleted, &mut pred, guard);
 
                         del_nodes.push(deleted);
                         del_pred_nodes.push(pred);
@@ -1031,7 +1812,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         let mut dim = 0;
                         let mut pred_dim = 0;
 
-                        self.delete_edge(
+                        ret = self.delete_edge(
                             *vertex,
                             *edge,
                             *direction_in,
@@ -1045,6 +1826,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                             guard,
                         );
 
+                        Self::record_parent(desc, opid, 0, parent, &ret);
                         md_del_nodes.push(deleted);
                         md_del_pred_nodes.push(md_pred);
                         md_del_parent_nodes.push(parent);
@@ -1052,14 +1834,114 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                         md_del_pred_dims.push(pred_dim);
                     }
 
+                    OpType::InsertArc(vertex, edge, out_value, in_value) => {
+                        // Both sides are owned by the same operation, so they are
+                        // committed or aborted together. A failure on either side,
+                        // e.g. a missing vertex or an existing edge, aborts the transaction.
+                        let sides = [
+                            (*vertex, *edge, out_value, false),
+                            (*edge, *vertex, in_value, true),
+                        ];
+
+                        for (side, (v, e, value, direction_in)) in sides.iter().enumerate() {
+                            let mut inserted = Shared::null();
+                            let mut md_pred = Shared::null();
+                            let mut parent = Shared::null();
+
+                            let mut dim = 0;
+                            let mut pred_dim = 0;
+
+                            ret = self.insert_edge(
+                                *v,
+                                *e,
+                                value,
+                                *direction_in,
+                                desc,
+                                opid,
+                                &mut inserted,
+                                &mut md_pred,
+                                &mut parent,
+                                &mut dim,
+                                &mut pred_dim,
+                                guard,
+                            );
+
+                            Self::record_parent(desc, opid, side, parent, &ret);
+                            md_ins_nodes.push(inserted);
+                            md_ins_pred_nodes.push(md_pred);
+                            md_ins_parent_nodes.push(parent);
+                            md_ins_dims.push(dim);
+                            md_ins_pred_dims.push(pred_dim);
+
+                            if let ReturnCode::Fail(_) = ret {
+                                break;
+                            }
+                        }
+                    }
+
+                    OpType::DeleteArc(vertex, edge) => {
+                        let sides = [(*vertex, *edge, false), (*edge, *vertex, true)];
+
+                        for (side, (v, e, direction_in)) in sides.iter().enumerate() {
+                            let mut deleted = Shared::null();
+                            let mut md_pred = Shared::null();
+                            let mut parent = Shared::null();
+
+                            let mut dim = 0;
+                            let mut pred_dim = 0;
+
+                            ret = self.delete_edge(
+                                *v,
+                                *e,
+                                *direction_in,
+                                desc,
+                                opid,
+                                &mut deleted,
+                                &mut md_pred,
+                                &mut parent,
+                                &mut dim,
+                                &mut pred_dim,
+                                guard,
+                            );
+
+                            Self::record_parent(desc, opid, side, parent, &ret);
+                            md_del_nodes.push(deleted);
+                            md_del_pred_nodes.push(md_pred);
+                            md_del_parent_nodes.push(parent);
+                            md_del_dims.push(dim);
+                            md_del_pred_dims.push(pred_dim);
+
+                            if let ReturnCode::Fail(_) = ret {
+                                break;
+                            }
+                        }
+                    }
+
                     OpType::Find(key) => {
                         ret = self.find(*key, desc, opid, guard);
                     }
+
+                    OpType::UpdateEdge(vertex, edge, _, direction_in) => {
+                        ret = self.update_edge(*vertex, *edge, *direction_in, desc, opid, guard);
+                    }
+
+                    OpType::FindEdges(vertex, direction_in) => {
+                        ret = self.find_edges(*vertex, *direction_in, desc, opid, guard);
+                    }
                 }
 
                 opid += 1;
 
-                sender.as_ref().map(|tx| tx.send(ret.clone()));
+                if op.derived {
+                    if let ReturnCode::Fail(_) = ret {
+                        // Unless the transaction was aborted for another reason
+                        if let OpStatus::Active = (*desc).status.load() {
+                            (*desc).conflict.store(true);
+                        }
+                    }
+                } else {
+                    sender.as_ref().map(|tx| tx.send(ret.clone()));
+                }
             }
 
             hs.borrow_mut().pop();
@@ -1070,6 +1952,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     .compare_exchange(OpStatus::Active, OpStatus::Aborted)
                     .is_ok()
                 {
+                    self.stats.record_abort();
                     // FIXME:(rasmus) call mark for deletion here
                     // Self::mark_for_deletion(
                     //     &ins_nodes,
@@ -1083,11 +1966,10 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     //     guard,
                     // );
                 }
-            } else if (*desc)
-                .status
-                .compare_exchange(OpStatus::Active, OpStatus::Committed)
-                .is_ok()
-            {
+            } else if self.commit(desc) {
+                self.stats.record_commit();
+                self.record_degrees(desc);
+                self.record_deletes(&del_nodes, guard);
                 // Self::mark_for_deletion(
                 //     &del_nodes,
                 //     &del_pred_nodes,
@@ -1103,11 +1985,85 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         });
     }
 
+    /// Remembers the vertex whose edges a side of an operation changed, unless
+    /// the operation failed or another thread executing it got there first
+    #[inline]
+    unsafe fn record_parent(
+        desc: *const Desc<'a, T, E>,
+        opid: usize,
+        side: usize,
+        parent: Shared<Node<'a, T, E>>,
+        ret: &ReturnCode<Atomic<Node<'a, T, E>>>,
+    ) {
+        if let ReturnCode::Fail(_) = ret {
+            return;
+        }
+
+        let _ = (*desc).parents[opid][side].compare_exchange(ptr::null(), parent.as_raw());
+    }
+
+    /// Marks the transaction as committed, after appending it to the log if enabled.
+    ///
+    /// While logging, commits are serialized, so that the log is in commit order.
+    /// A transaction that cannot be logged is aborted.
+    unsafe fn commit(&self, desc: *const Desc<'a, T, E>) -> bool {
+        let wal = match self.wal.as_ref() {
+            Some(wal) => wal,
+            None => {
+                return (*desc)
+                    .status
+                    .compare_exchange(OpStatus::Active, OpStatus::Committed)
+                    .is_ok()
+            }
+        };
+
+        let mut state = wal.lock();
+        match (*desc).status.load() {
+            OpStatus::Active => {}
+            _ => return false,
+        }
+
+        let start = match wal.append(&mut state, &(*desc).ops) {
+            Ok(start) => start,
+            Err(err) => {
+                (*desc).log_error.store(Some(err.kind()));
+                if (*desc)
+                    .status
+                    .compare_exchange(OpStatus::Active, OpStatus::Aborted)
+                    .is_ok()
+                {
+                    self.stats.record_abort();
+                }
+                return false;
+            }
+        };
+
+        if (*desc)
+            .status
+            .compare_exchange(OpStatus::Active, OpStatus::Committed)
+            .is_err()
+        {
+            // Aborted by a helping thread while the record was written.
+            // If it cannot be removed, the log refuses any further records.
+            if let Some(start) = start {
+                let _ = wal.discard(&mut state, start);
+            }
+            return false;
+        }
+
+        true
+    }
+
     #[inline]
     fn is_same_operation(desc: &NodeDesc<'a, T, E>, other: &NodeDesc<'a, T, E>) -> bool {
         std::ptr::eq(desc.desc, other.desc) && desc.opid == other.opid
     }
 
+    #[inline]
+    fn is_same_transaction(desc: &NodeDesc<'a, T, E>, other: &NodeDesc<'a, T, E>) -> bool {
+        std::ptr::eq(desc.desc, other.desc)
+    }
+
     #[inline]
     unsafe fn finish_pending_txn<'t>(
         &'t self,
@@ -1124,6 +2080,10 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                 return;
             }
 
+            if let OpStatus::Active = (*g_node_inner_desc).status.load() {
+                self.stats.record_help();
+            }
+
             let optype = &(*g_node_inner_desc).ops[node_desc_ref.opid].optype;
             if let OpType::Delete(_) = optype {
                 if (*g_node_inner_desc).pending[node_desc_ref.opid].load() {
@@ -1136,15 +2096,52 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         }
     }
 
+    /// Marks both edge lists of a deleted vertex, so that no edge can be added
+    /// to them by a transaction that found the vertex before it was deleted.
+    ///
+    /// The edges themselves are deleted by earlier operations of the transaction.
+    /// Returns `false` if an edge is still present, i.e. one that was added after
+    /// the graph read the edges to delete. The transaction is then conflicted,
+    /// unless the edge was added by the transaction itself.
+    unsafe fn finish_delete_edges(
+        &self,
+        node: &Node<'a, T, E>,
+        desc: *const Desc<'a, T, E>,
+        node_desc: &Atomic<NodeDesc<'a, T, E>>,
+        guard: &Guard,
+    ) -> bool {
+        for mdlist in node.out_edges.iter().chain(node.in_edges.iter()) {
+            let marked = self.finish_delete_vertex(
+                mdlist.head().load(SeqCst, guard),
+                true,
+                0,
+                desc,
+                node_desc,
+                self.md_config.dimension,
+                false,
+                guard,
+            );
+
+            if !marked {
+                return false;
+            }
+        }
+
+        true
+    }
+
     unsafe fn finish_delete_vertex<'t>(
         &'t self,
         n: Shared<'t, MDNode<'a, E, T>>,
+        is_head: bool,
         dim: usize,
         desc: *const Desc<'a, T, E>,
         node_desc: &Atomic<NodeDesc<'a, T, E>>,
         dimension: usize,
+        retain_own: bool,
         guard: &Guard,
-    ) where
+    ) -> bool
+    where
         'a: 't,
     {
         // Hack to bind lifetime of guard to self.
@@ -1155,26 +2152,62 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
             let current_desc = &n_ref.node_desc;
             let g_current_desc = current_desc.load(SeqCst, guard);
 
-            if g_current_desc.is_null() || (is_marked(g_current_desc.tag())) {
+            if is_marked(g_current_desc.tag()) {
                 break;
             }
 
-            self.finish_pending_txn(g_current_desc, desc, guard);
+            if !g_current_desc.is_null() {
+                self.finish_pending_txn(g_current_desc, desc, guard);
+            }
 
             match (*desc).status.load() {
                 OpStatus::Active => {}
-                _ => return,
+                _ => return false,
+            }
+
+            // Only the head of an `MDList` that is not part of a vertex has no descriptor.
+            // Edges deleted by this transaction keep their descriptor,
+            // as do edges that are already deleted, otherwise they would be
+            // restored if this transaction aborts.
+            let (is_same, is_own, exists) = match g_current_desc.as_ref() {
+                Some(g_c) if std::ptr::eq(g_c.desc, desc) => (
+                    Self::is_same_operation(g_c, node_desc.load(SeqCst, guard).as_ref().unwrap()),
+                    true,
+                    Self::is_key_exist_once_committed(g_c),
+                ),
+                Some(g_c) => (false, false, Self::is_key_exist(g_c, guard)),
+                None => (false, false, true),
+            };
+
+            // A descriptor installed on an edge as the pred of an insertion
+            // does not operate on the edge, even if it is this transaction's
+            let is_pred = matches!(
+                g_current_desc.as_ref(),
+                Some(g_c) if g_c.override_as_find || g_c.override_as_delete
+            );
+
+            if exists && !is_same && !is_head && !(retain_own && is_own && !is_pred) {
+                if !is_own || is_pred {
+                    (*desc).conflict.store(true);
+                }
+                return false;
             }
 
             // Move on to the next children if we either succeed a CAS to update
             // the descriptor or we see that a different thread has already done so
             let n_node_desc = &n_ref.node_desc;
-            if Self::is_same_operation(
-                g_current_desc.as_ref().unwrap(),
-                node_desc.load(SeqCst, guard).as_ref().unwrap(),
-            ) || n_node_desc
-                .compare_and_set(g_current_desc, node_desc.load(SeqCst, guard), SeqCst, guard)
-                .is_ok()
+            if is_same
+                || is_own
+                || !exists
+                || n_node_desc
+                    .compare_exchange(
+                        g_current_desc,
+                        node_desc.load(SeqCst, guard),
+                        SeqCst,
+                        SeqCst,
+                        guard,
+                    )
+                    .is_ok()
             {
                 let pending = n_ref.pending.load(SeqCst, guard);
 
@@ -1189,14 +2222,20 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                 for i in (dim..dimension).rev() {
                     let child = n_ref.children[i].load(SeqCst, guard).with_tag(0);
 
-                    if !child.is_null() {
-                        self.finish_delete_vertex(child, dim, desc, node_desc, dimension, guard);
+                    if !child.is_null()
+                        && !self.finish_delete_vertex(
+                            child, false, dim, desc, node_desc, dimension, retain_own, guard,
+                        )
+                    {
+                        return false;
                     }
                 }
 
                 break;
             }
         }
+
+        true
     }
 
     #[inline]
@@ -1223,24 +2262,110 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
     /// Checks if a node is logically within the list
     #[inline]
     unsafe fn is_key_exist(node_desc: &NodeDesc<'a, T, E>, guard: &Guard) -> bool {
+        // Descriptors installed on an edge's pred do not operate on its key,
+        // so the pred keeps the state it had, whatever the outcome of the transaction
+        if node_desc.override_as_find {
+            return true;
+        }
+        if node_desc.override_as_delete {
+            return false;
+        }
+
         let is_node_active = Self::is_node_active(node_desc, guard);
         let opoptype = &(*node_desc.desc).ops[node_desc.opid].optype;
 
         match opoptype {
-            OpType::Find(..) => return true,
-            OpType::Insert(..) | OpType::InsertEdge(..) | OpType::Connect(..) => {
-                if is_node_active {
-                    return true;
+            OpType::Find(..) | OpType::UpdateEdge(..) | OpType::FindEdges(..) => true,
+            OpType::Insert(..)
+            | OpType::InsertEdge(..)
+            | OpType::InsertArc(..)
+            | OpType::Connect(..) => is_node_active,
+            OpType::Delete(..) | OpType::DeleteEdge(..) | OpType::DeleteArc(..) => !is_node_active,
+        }
+    }
+
+    /// Checks if a node owned by the running transaction
+    /// is logically within the list once the transaction commits
+    #[inline]
+    unsafe fn is_key_exist_once_committed(node_desc: &NodeDesc<'a, T, E>) -> bool {
+        if node_desc.override_as_find {
+            return true;
+        }
+        if node_desc.override_as_delete {
+            return false;
+        }
+
+        match &(*node_desc.desc).ops[node_desc.opid].optype {
+            OpType::Delete(..) | OpType::DeleteEdge(..) | OpType::DeleteArc(..) => false,
+            _ => true,
+        }
+    }
+
+    /// Checks if a node is logically within the list,
+    /// without helping any pending transactions
+    #[inline]
+    unsafe fn is_logically_present(node: &Node<'a, T, E>, guard: &Guard) -> bool {
+        let node_desc = node.node_desc.load(SeqCst, guard);
+        if is_marked(node_desc.tag()) || is_marked(node.next.load(SeqCst, guard).tag()) {
+            return false;
+        }
+
+        match node_desc.as_ref() {
+            Some(node_desc_ref) => Self::is_key_exist(node_desc_ref, guard),
+            None => false,
+        }
+    }
+
+    /// Checks if an edge is logically within its `MDList`,
+    /// without helping any pending transactions
+    #[inline]
+    pub(crate) unsafe fn is_edge_present(node: &MDNode<'a, E, T>, guard: &Guard) -> bool {
+        let node_desc = node.node_desc.load(SeqCst, guard);
+        if is_marked(node_desc.tag()) {
+            return false;
+        }
+
+        match node_desc.as_ref() {
+            Some(node_desc_ref) => Self::is_key_exist(node_desc_ref, guard),
+            None => false,
+        }
+    }
+
+    /// Returns the value of an edge, without helping any pending transactions
+    #[inline]
+    pub(crate) unsafe fn edge_value<'n>(
+        node: &'n MDNode<'a, E, T>,
+        guard: &'n Guard,
+    ) -> Option<&'n E> {
+        Self::resolve_edge_value(node, node.node_desc.load(SeqCst, guard))
+    }
+
+    /// Returns the value of an edge with the given descriptor.
+    ///
+    /// A committed `UpdateEdge` holds the new value, until the descriptor is
+    /// replaced by one that carries the value over. Otherwise, the value is the one
+    /// carried by the descriptor, or the one the node was inserted with.
+    unsafe fn resolve_edge_value<'n>(
+        node: &'n MDNode<'a, E, T>,
+        node_desc: Shared<'n, NodeDesc<'a, T, E>>,
+    ) -> Option<&'n E> {
+        if let Some(node_desc_ref) = node_desc.as_ref() {
+            let is_pred = node_desc_ref.override_as_find || node_desc_ref.override_as_delete;
+            let committed = matches!((*node_desc_ref.desc).status.load(), OpStatus::Committed);
+            if let OpType::UpdateEdge(_, _, value, _) =
+                &(*node_desc_ref.desc).ops[node_desc_ref.opid].optype
+            {
+                if committed && !is_pred {
+                    return Some(value);
                 }
             }
-            OpType::Delete(..) | OpType::DeleteEdge(..) => {
-                if !is_node_active {
-                    return true;
-                }
+
+            if let Some(value) = node_desc_ref.value.as_ref() {
+                return Some(value);
             }
         }
 
-        node_desc.override_as_find || (!is_node_active && node_desc.override_as_delete)
+        node.value()
     }
 
     #[inline]
@@ -1254,9 +2379,11 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         // Hack to bind lifetime of guard to self.
         let guard = &*(guard as *const _);
         let pred_next = &mut Shared::null();
+        let head = self.head.load(SeqCst, guard);
 
         while let Some(curr_ref) = current.as_ref() {
-            if curr_ref.key >= key {
+            // The head has key 0 as well, but it is never the node of a vertex
+            if curr_ref.key >= key && *current != head {
                 break;
             }
             *pred = *current;
@@ -1272,7 +2399,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
             if current != pred_next {
                 //Failed to remove deleted nodes, start over from pred
                 if pred_n
-                    .compare_and_set(*pred_next, *current, SeqCst, guard)
+                    .compare_exchange(*pred_next, *current, SeqCst, SeqCst, guard)
                     .is_err()
                 {
                     *current = self.head.load(SeqCst, guard);
@@ -1293,10 +2420,18 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         // Hack to bind lifetime of guard to self.
         let guard = &*(guard as *const _);
 
-        *curr = self.head.load(SeqCst, guard);
-        let pred = &mut Shared::null();
+        if !self.might_contain(key, guard) {
+            return false;
+        }
 
-        self.locate_pred(pred, curr, key, guard);
+        match self.index.get(key, guard) {
+            Some(node) if !is_marked(node.deref().next.load(SeqCst, guard).tag()) => *curr = node,
+            _ => {
+                *curr = self.head.load(SeqCst, guard);
+                let pred = &mut Shared::null();
+                self.locate_pred(pred, curr, key, guard);
+            }
+        }
 
         if Self::is_node_exist(*curr, key) {
             let current_desc = &curr.as_ref().unwrap().node_desc;
@@ -1315,6 +2450,29 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         }
     }
 
+    /// Checks that a vertex found earlier in the transaction has not been deleted since.
+    /// A descriptor of the same transaction was already checked when the vertex was found.
+    unsafe fn is_vertex_retained(
+        &self,
+        node: &Node<'a, T, E>,
+        desc: *const Desc<'a, T, E>,
+        guard: &Guard,
+    ) -> bool {
+        let node_desc = node.node_desc.load(SeqCst, guard);
+        if is_marked(node_desc.tag()) {
+            return false;
+        }
+
+        match node_desc.as_ref() {
+            Some(node_desc_ref) if std::ptr::eq(node_desc_ref.desc, desc) => true,
+            Some(node_desc_ref) => {
+                self.finish_pending_txn(node_desc, desc, guard);
+                Self::is_key_exist(node_desc_ref, guard)
+            }
+            None => true,
+        }
+    }
+
     unsafe fn mark_for_deletion<'t>(
         nodes: &[Shared<'t, Node<'a, T, E>>],
         preds: &[Shared<'t, Node<'a, T, E>>],
@@ -1323,6 +2481,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
         // parents: &[Shared<'a, Node<'a, T, E>>],
         dims: &[usize],
         pred_dims: &[usize],
+        dimension: usize,
         desc: *const Desc<'a, T, E>,
         guard: &Guard,
     ) where
@@ -1336,10 +2495,11 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                 if std::ptr::eq(g_node_desc.as_ref().expect("No g_node desc").desc, desc)
                     && node_desc
-                        .compare_and_set(
+                        .compare_exchange(
                             g_node_desc,
                             g_node_desc.with_tag(set_mark(g_node_desc.tag())),
                             SeqCst,
+                            SeqCst,
                             guard,
                         )
                         .is_ok()
@@ -1351,9 +2511,11 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
                     let fetched = n_next.fetch_or(0x1, SeqCst, guard);
                     let succ = fetched.with_tag(clr_mark(fetched.tag()));
 
-                    pred_next.compare_and_set(n, succ, SeqCst, guard).is_ok();
+                    pred_next
+                        .compare_exchange(n, succ, SeqCst, SeqCst, guard)
+                        .is_ok();
 
-                    // assert!(pred_next.compare_and_set(n, succ, SeqCst, guard).is_ok());
+                    // assert!(pred_next.compare_exchange(n, succ, SeqCst, SeqCst, guard).is_ok());
                 }
             }
         }
@@ -1370,15 +2532,23 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a + Clone, E: 'a + Clone> AdjacencyList<'a, T, E>
 
                 if std::ptr::eq(g_node_desc.as_ref().unwrap().desc, desc)
                     && node_desc
-                        .compare_and_set(
+                        .compare_exchange(
                             g_node_desc,
                             g_node_desc.with_tag(set_mark(g_node_desc.tag())),
                             SeqCst,
+                            SeqCst,
                             guard,
                         )
                         .is_ok()
                 {
-                    MDList::delete(&mut pred_node, &mut node, &mut pred_dim, &mut dim, guard);
+                    MDList::delete(
+                        &mut pred_node,
+                        &mut node,
+                        &mut pred_dim,
+                        &mut dim,
+                        dimension,
+                        guard,
+                    );
                 }
             }
         }
@@ -1392,7 +2562,10 @@ impl<'a, T: Clone, E: Clone> AdjacencyList<'a, T, E> {
     {
         let ops = ops
             .drain(..)
-            .map(|op| Operator::<'a, T, E> { optype: op })
+            .map(|op| Operator::<'a, T, E> {
+                optype: op,
+                derived: false,
+            })
             .collect();
 
         Transaction::new(self, ops)
@@ -1418,9 +2591,222 @@ impl<'a: 't, 't, N, E> Transaction<'a, 't, N, E> {
 impl<'a: 't, 't, N: Clone, E: Clone> Transaction<'a, 't, N, E> {
     #[must_use]
     pub fn execute(self) -> std::sync::mpsc::Receiver<ReturnCode<Atomic<Node<'a, N, E>>>> {
+        self.execute_with_outcome().1
+    }
+
+    /// Executes the transaction and reports how it finished,
+    /// so that the caller can decide whether to retry it
+    #[must_use]
+    pub fn execute_with_outcome(
+        self,
+    ) -> (
+        TxnOutcome,
+        std::sync::mpsc::Receiver<ReturnCode<Atomic<Node<'a, N, E>>>>,
+    ) {
         let (tx, rx) = std::sync::mpsc::channel();
         let guard = &epoch::pin();
         self.adjlist.execute_ops(self.ops, tx, guard);
-        rx
+        let outcome = unsafe { (*self.ops).outcome() };
+        (outcome, rx)
+    }
+}
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+    use crate::graphmodels::epoch::lftt::{RetryPolicy, TxnStatsSnapshot};
+    use crate::graphmodels::epoch::Graph;
+    use std::time::Duration;
+
+    type List = AdjacencyList<'static, usize, usize>;
+
+    /// The descriptor of a transaction with a single operation, that is `status`
+    fn node_desc(
+        optype: OpType<'static, usize, usize>,
+        status: OpStatus,
+    ) -> NodeDesc<'static, usize, usize> {
+        let desc = Desc::alloc(vec![Operator {
+            optype,
+            derived: false,
+        }]);
+        unsafe { (*desc).status.store(status) };
+        NodeDesc::new(desc, 0)
+    }
+
+    fn exists(node_desc: &NodeDesc<'static, usize, usize>) -> bool {
+        unsafe { List::is_key_exist(node_desc, epoch::unprotected()) }
+    }
+
+    #[test]
+    fn key_exists_by_outcome_of_its_operation() {
+        use OpStatus::*;
+
+        let cases = vec![
+            (OpType::Find(1), Active, true),
+            (OpType::Find(1), Aborted, true),
+            (OpType::Insert(1, None), Active, false),
+            (OpType::Insert(1, None), Committed, true),
+            (OpType::Insert(1, None), Aborted, false),
+            (OpType::InsertEdge(1, 2, None, false), Committed, true),
+            (OpType::InsertEdge(1, 2, None, false), Aborted, false),
+            (OpType::Delete(1), Active, true),
+            (OpType::Delete(1), Committed, false),
+            (OpType::Delete(1), Aborted, true),
+            (OpType::DeleteEdge(1, 2, false), Committed, false),
+            (OpType::DeleteEdge(1, 2, false), Aborted, true),
+        ];
+
+        for (optype, status, expected) in cases {
+            assert_eq!(exists(&node_desc(optype, status)), expected, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn pred_keeps_its_state_whatever_the_outcome() {
+        for status in &[OpStatus::Active, OpStatus::Committed, OpStatus::Aborted] {
+            // A pred that was in the list is not removed by an aborted insert
+            let mut found = node_desc(OpType::InsertEdge(1, 3, None, false), *status);
+            found.override_as_find = true;
+            assert!(exists(&found), "{:?}", status);
+
+            // and a pred that was deleted is not restored by a committed one
+            let mut deleted = node_desc(OpType::InsertEdge(1, 3, None, false), *status);
+            deleted.override_as_delete = true;
+            assert!(!exists(&deleted), "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn pred_connected_outside_of_a_transaction_is_kept() {
+        // With two dimensions of basis 2, the edge `2` is the pred of the edge `3`
+        let list = List::with_config(4, MDListConfig::new(2, 2));
+        let _ = list.txn(vec![OpType::Insert(1, None)]).execute();
+
+        let guard = &epoch::pin();
+        let vertex = list.get_vertex(1, guard).unwrap();
+        unsafe { list.connect(vertex.deref(), 2, 2, false) };
+
+        let inserted = list
+            .txn(vec![OpType::InsertEdge(1, 3, Some(3), false)])
+            .execute_with_outcome()
+            .0;
+        assert_eq!(inserted, TxnOutcome::Committed);
+
+        let out_edges = unsafe { vertex.deref() }.out_edges.as_ref().unwrap();
+        let present: Vec<usize> = out_edges
+            .iter(guard)
+            .filter(|entry| unsafe { List::is_edge_present(entry.node, guard) })
+            .map(|entry| entry.node.key)
+            .collect();
+        assert_eq!(present, vec![2, 3]);
+    }
+
+    /// Installs a transaction on vertex `v` that is still active, as if its thread
+    /// stalled right after reaching `v`. Once helped, it finds vertex `next`.
+    fn stall_on(graph: &Graph<'static, usize>, v: usize, next: usize) {
+        let desc = Desc::alloc(vec![Operator {
+            optype: OpType::Find(next),
+            derived: false,
+        }]);
+        unsafe {
+            let node = graph.inner.get_vertex(v, epoch::unprotected()).unwrap();
+            let node_desc = Owned::new(NodeDesc::new(desc, 0));
+            node.deref().node_desc.store(node_desc, SeqCst);
+        }
+    }
+
+    #[test]
+    fn conflicts_are_counted_and_retried() {
+        for max_attempts in 1..=2 {
+            let mut graph: Graph<usize> = Graph::bulk_load(3, &Vec::new(), true);
+            graph.set_retry_policy(RetryPolicy::new(max_attempts));
+            stall_on(&graph, 1, 2);
+
+            // Helping the stalled transaction leads back to this one,
+            // which is aborted to break the cycle
+            let ops = vec![OpType::Find(2), OpType::Find(1)];
+            let outcome = graph.execute_ops_with_outcome(ops).0;
+
+            let retried = max_attempts > 1;
+            let expected = TxnStatsSnapshot {
+                commits: if retried { 2 } else { 1 },
+                aborts: 1,
+                helps: 2,
+                retries: if retried { 1 } else { 0 },
+            };
+            assert_eq!(graph.txn_stats(), expected);
+            let expected = if retried {
+                TxnOutcome::Committed
+            } else {
+                TxnOutcome::Conflicted
+            };
+            assert_eq!(outcome, expected);
+        }
+    }
+
+    #[test]
+    fn failed_operations_are_not_retried() {
+        let mut graph: Graph<usize> = Graph::bulk_load(3, &Vec::new(), true);
+        graph.set_retry_policy(RetryPolicy::new(3));
+
+        let outcome = graph.execute_ops_with_outcome(vec![OpType::Find(7)]).0;
+        assert_eq!(outcome, TxnOutcome::Aborted);
+
+        let expected = TxnStatsSnapshot {
+            aborts: 1,
+            ..TxnStatsSnapshot::default()
+        };
+        assert_eq!(graph.txn_stats(), expected);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy =
+            RetryPolicy::new(8).with_backoff(Duration::from_micros(10), Duration::from_micros(50));
+        let backoffs: Vec<u128> = (1..=5).map(|a| policy.backoff(a).as_micros()).collect();
+        assert_eq!(backoffs, vec![10, 20, 40, 50, 50]);
+
+        assert_eq!(RetryPolicy::new(0).max_attempts, 1);
+    }
+
+    #[test]
+    fn lookup_of_unindexed_vertex_scans_the_list() {
+        let list = List::new(4);
+        for key in 1..4 {
+            let _ = list.txn(vec![OpType::Insert(key, None)]).execute();
+        }
+
+        let guard = &epoch::pin();
+        let node = list.get_vertex(2, guard).unwrap();
+        list.index.insert(2, Shared::null(), guard);
+
+        assert_eq!(list.get_vertex(2, guard), Some(node));
+        // Vertices are indexed before they are inserted, so only lookups fall back
+        assert!(!list.contains_vertex(2, guard));
+    }
+
+    #[test]
+    fn lookup_of_unlinked_vertex_scans_the_list() {
+        let list = List::new(4);
+        for key in 1..4 {
+            let _ = list.txn(vec![OpType::Insert(key, None)]).execute();
+        }
+
+        // Marks the node of 2 for deletion, so that it is unlinked
+        // when 2 is inserted again, and a new node takes its place
+        let guard = &epoch::pin();
+        let unlinked = list.get_vertex(2, guard).unwrap();
+        let unlinked_ref = unsafe { unlinked.deref() };
+        unlinked_ref.node_desc.fetch_or(0x1, SeqCst, guard);
+        let _ = list.txn(vec![OpType::Insert(2, Some(2))]).execute();
+
+        let node = list.get_vertex(2, guard).unwrap();
+        assert_ne!(node, unlinked);
+        assert_eq!(unsafe { node.deref() }.value(), Some(2));
+        assert!(is_marked(unlinked_ref.next.load(SeqCst, guard).tag()));
+
+        list.index.insert(2, unlinked, guard);
+        assert_eq!(list.get_vertex(2, guard), Some(node));
+        assert!(list.contains_vertex(2, guard));
     }
 }
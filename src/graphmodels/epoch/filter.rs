@@ -0,0 +1,165 @@
+use crate::memory;
+use epoch::{Atomic, Guard, Owned, Shared};
+use lock_free_bloomfilter::bloomfilter::BloomFilter;
+use std::mem;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+
+const MIN_CAPACITY: usize = 64;
+
+/// The filter is rebuilt once the deleted keys
+/// make up this fraction of the keys added to it
+const REBUILD_RATIO: usize = 4;
+
+/// A bloom filter over the vertex keys, used to reject lookups
+/// for vertices that have never been inserted without traversing the list.
+///
+/// Bloom filters cannot forget keys, so deleted vertices keep producing
+/// positives until the filter is rebuilt. Deletions are counted, and once
+/// they reach a quarter of the keys in the filter, or the filter outgrows
+/// its capacity, a new filter is filled from the list and swapped in.
+///
+/// While a rebuild is running, keys are added to both filters,
+/// so the filter never reports a vertex that is in the list as missing.
+pub struct VertexFilter {
+    current: Atomic<BloomFilter>,
+    pending: Atomic<BloomFilter>,
+    false_positive_rate: f64,
+    capacity: AtomicUsize,
+    inserted: AtomicUsize,
+    deleted: AtomicUsize,
+    rebuilding: AtomicBool,
+    rebuilds: AtomicUsize,
+}
+
+unsafe impl Send for VertexFilter {}
+unsafe impl Sync for VertexFilter {}
+
+impl Drop for VertexFilter {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = epoch::unprotected();
+            for filter in &[&self.current, &self.pending] {
+                let filter = filter.load(SeqCst, guard);
+                if !filter.is_null() {
+                    drop(filter.into_owned());
+                }
+            }
+        }
+    }
+}
+
+impl VertexFilter {
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = std::cmp::max(capacity, MIN_CAPACITY);
+
+        Self {
+            current: Atomic::new(BloomFilter::create(capacity as i64, false_positive_rate)),
+            pending: Atomic::null(),
+            false_positive_rate,
+            capacity: AtomicUsize::new(capacity),
+            inserted: AtomicUsize::new(0),
+            deleted: AtomicUsize::new(0),
+            rebuilding: AtomicBool::new(false),
+            rebuilds: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds `key`, must be called after the vertex is linked into the list
+    pub fn insert(&self, key: usize, guard: &Guard) {
+        loop {
+            let current = self.current.load(SeqCst, guard);
+            unsafe { current.deref() }.set(key);
+
+            if let Some(pending) = unsafe { self.pending.load(SeqCst, guard).as_ref() } {
+                pending.set(key);
+            }
+
+            // A rebuild swapped the filter while we were setting the old one
+            if self.current.load(SeqCst, guard) == current {
+                break;
+            }
+        }
+
+        self.inserted.fetch_add(1, SeqCst);
+    }
+
+    /// Returns `false` only if `key` is definitely not a vertex
+    pub fn might_contain(&self, key: usize, guard: &Guard) -> bool {
+        unsafe { self.current.load(SeqCst, guard).deref() }.might_contain(key)
+    }
+
+    /// Counts a committed vertex deletion.
+    ///
+    /// Returns `true` if the filter should be rebuilt.
+    pub fn record_delete(&self) -> bool {
+        let deleted = self.deleted.fetch_add(1, SeqCst) + 1;
+        let inserted = self.inserted.load(SeqCst);
+
+        deleted * REBUILD_RATIO >= std::cmp::max(inserted, MIN_CAPACITY)
+            || inserted > self.capacity.load(SeqCst)
+    }
+
+    /// Estimates the bytes of the current filter from its capacity and false positive rate,
+    /// as an optimal filter needs `-n ln(p) / ln(2)^2` bits for `n` keys
+    pub fn heap_size(&self) -> usize {
+        let capacity = self.capacity.load(SeqCst) as f64;
+        let bits = -capacity * self.false_positive_rate.ln() / std::f64::consts::LN_2.powi(2);
+        mem::size_of::<BloomFilter>() + bits.ceil() as usize / 8 + 2 * memory::ALLOCATION_HEADER
+    }
+
+    /// Number of times the filter has been rebuilt
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds.load(SeqCst)
+    }
+
+    /// Replaces the filter with one containing the keys yielded by `keys`.
+    ///
+    /// `keys` is called after the new filter is published to concurrent inserts,
+    /// and must yield every key that might be in the list at that point.
+    /// Only one rebuild runs at a time, other callers return immediately.
+    pub fn rebuild<I>(&self, keys: impl FnOnce() -> I, guard: &Guard)
+    where
+        I: Iterator<Item = usize>,
+    {
+        if self
+            .rebuilding
+            .compare_exchange(false, true, SeqCst, SeqCst)
+            .is_err()
+        {
+            return;
+        }
+
+        let inserted = self.inserted.load(SeqCst);
+        let deleted = self.deleted.load(SeqCst);
+        let capacity = std::cmp::max(
+            self.capacity.load(SeqCst),
+            2 * inserted.saturating_sub(deleted),
+        );
+
+        let next = Owned::new(BloomFilter::create(
+            capacity as i64,
+            self.false_positive_rate,
+        ))
+        .into_shared(guard);
+        self.pending.store(next, SeqCst);
+
+        let mut count = 0;
+        for key in keys() {
+            unsafe { next.deref() }.set(key);
+            count += 1;
+        }
+
+        let old = self.current.swap(next, SeqCst, guard);
+        self.pending.store(Shared::null(), SeqCst);
+        unsafe {
+            guard.defer_destroy(old);
+        }
+
+        self.capacity.store(capacity, SeqCst);
+        self.inserted.store(count, SeqCst);
+        self.deleted.store(0, SeqCst);
+        self.rebuilds.fetch_add(1, SeqCst);
+        self.rebuilding.store(false, SeqCst);
+    }
+}
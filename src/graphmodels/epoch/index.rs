@@ -0,0 +1,123 @@
+use crate::graphmodels::epoch::adjlist::Node;
+use crate::memory;
+use epoch::{Atomic, Guard, Owned, Shared};
+use std::mem;
+use std::sync::atomic::Ordering::SeqCst;
+
+const MIN_BUCKETS: usize = 64;
+
+struct IndexEntry<'a, T, E> {
+    key: usize,
+    node: Atomic<Node<'a, T, E>>,
+    next: Atomic<Self>,
+}
+
+/// A lock-free hash index from vertex keys to nodes in the adjacency list.
+///
+/// Entries are only ever added or updated, never removed, so lookups
+/// can return a node that has since been deleted. The index is a hint,
+/// and the node descriptor decides if the vertex is logically in the list.
+///
+/// The number of buckets is fixed at creation,
+/// and keys are mapped to buckets by their low bits,
+/// as vertex ids are mostly dense.
+pub struct VertexIndex<'a, T, E> {
+    buckets: Box<[Atomic<IndexEntry<'a, T, E>>]>,
+    mask: usize,
+}
+
+unsafe impl<'a, T: Send + Sync, E: Send + Sync> Send for VertexIndex<'a, T, E> {}
+unsafe impl<'a, T: Send + Sync, E: Send + Sync> Sync for VertexIndex<'a, T, E> {}
+
+unsafe impl<#[may_dangle] 'a, #[may_dangle] T, #[may_dangle] E> Drop for VertexIndex<'a, T, E> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = epoch::unprotected();
+            for bucket in self.buckets.iter() {
+                let mut entry = bucket.load(SeqCst, guard);
+                while let Some(entry_ref) = entry.as_ref() {
+                    let next = entry_ref.next.load(SeqCst, guard);
+                    drop(entry.into_owned());
+                    entry = next;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T, E> VertexIndex<'a, T, E> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let num_buckets = std::cmp::max(capacity, MIN_BUCKETS).next_power_of_two();
+
+        Self {
+            buckets: (0..num_buckets).map(|_| Atomic::null()).collect(),
+            mask: num_buckets - 1,
+        }
+    }
+
+    /// Bytes held by the buckets and the entries of the index
+    pub fn heap_size(&self, guard: &Guard) -> usize {
+        let mut size = mem::size_of_val(&*self.buckets) + memory::ALLOCATION_HEADER;
+        for bucket in self.buckets.iter() {
+            let mut entry = bucket.load(SeqCst, guard);
+            while let Some(entry_ref) = unsafe { entry.as_ref() } {
+                size += mem::size_of::<IndexEntry<'a, T, E>>() + memory::ALLOCATION_HEADER;
+                entry = entry_ref.next.load(SeqCst, guard);
+            }
+        }
+        size
+    }
+
+    #[inline]
+    fn bucket(&self, key: usize) -> &Atomic<IndexEntry<'a, T, E>> {
+        &self.buckets[key & self.mask]
+    }
+
+    /// Points `key` at `node`, replacing any previous node
+    pub fn insert<'g>(&self, key: usize, node: Shared<'g, Node<'a, T, E>>, guard: &'g Guard) {
+        let bucket = self.bucket(key);
+        let mut new_entry = None;
+
+        loop {
+            let head = bucket.load(SeqCst, guard);
+
+            let mut current = head;
+            while let Some(entry) = unsafe { current.as_ref() } {
+                if entry.key == key {
+                    entry.node.store(node, SeqCst);
+                    return;
+                }
+                current = entry.next.load(SeqCst, guard);
+            }
+
+            let entry = new_entry.take().unwrap_or_else(|| {
+                Owned::new(IndexEntry {
+                    key,
+                    node: Atomic::null(),
+                    next: Atomic::null(),
+                })
+            });
+            entry.node.store(node, SeqCst);
+            entry.next.store(head, SeqCst);
+
+            match bucket.compare_exchange(head, entry, SeqCst, SeqCst, guard) {
+                Ok(_) => return,
+                Err(err) => new_entry = Some(err.new),
+            }
+        }
+    }
+
+    /// Returns the last node inserted for `key`, if any
+    pub fn get<'g>(&self, key: usize, guard: &'g Guard) -> Option<Shared<'g, Node<'a, T, E>>> {
+        let mut current = self.bucket(key).load(SeqCst, guard);
+        while let Some(entry) = unsafe { current.as_ref() } {
+            if entry.key == key {
+                let node = entry.node.load(SeqCst, guard);
+                return if node.is_null() { None } else { Some(node) };
+            }
+            current = entry.next.load(SeqCst, guard);
+        }
+
+        None
+    }
+}
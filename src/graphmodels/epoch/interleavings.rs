@@ -0,0 +1,156 @@
+//! Explores every interleaving (up to a preemption bound) of small scenarios
+//! on the lock-free adjacency list, using the `loom` model checker. Run with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg crossbeam_loom" cargo test --release --features loom interleavings
+//! ```
+//!
+//! Vertices are only deleted logically, `mark_for_deletion` is never reached,
+//! so the scenarios cover the descriptor based paths of `help_ops`.
+
+use super::*;
+use loom::sync::Arc;
+use loom::thread;
+use std::sync::atomic::AtomicBool;
+
+type TestGraph = Graph<'static, usize>;
+
+fn edge(node_id: NodeId) -> Option<EdgeInfo> {
+    Some(EdgeInfo {
+        node_id,
+        weight: None,
+    })
+}
+
+fn check(scenario: impl Fn() + Sync + Send + 'static) {
+    let mut builder = loom::model::Builder::new();
+    // Most bugs need few preemptions, and the state space
+    // of the list grows quickly with every one that is allowed
+    if builder.preemption_bound.is_none() {
+        builder.preemption_bound = Some(2);
+    }
+    // Setting up a graph and collecting its garbage takes many atomic operations
+    builder.max_branches = builder.max_branches.max(10_000);
+    builder.check(scenario);
+}
+
+/// A directed graph where every `MDList` has two dimensions of basis 2,
+/// so that an edge `2` is inserted as the parent of an edge `3`, adopting it
+fn graph() -> TestGraph {
+    Graph::with_md_config(4, true, MDListConfig::new(2, 2))
+}
+
+fn txn(graph: &TestGraph, ops: Vec<OpType<'static, usize, E>>) -> TxnOutcome {
+    graph.inner.txn(ops).execute_with_outcome().0
+}
+
+fn vertices(graph: &TestGraph) -> Vec<NodeId> {
+    let guard = &epoch::pin();
+    graph.read(guard).vertices().map(|v| v.get().key).collect()
+}
+
+fn out_edges(graph: &TestGraph, v: NodeId) -> Option<Vec<NodeId>> {
+    let guard = &epoch::pin();
+    let view = graph.read(guard);
+    let edges = view.out_edges(v)?.map(|e| e.node_id).collect();
+    Some(edges)
+}
+
+#[test]
+fn concurrent_inserts_of_the_same_key() {
+    check(|| {
+        let graph = Arc::new(graph());
+
+        let other = {
+            let graph = Arc::clone(&graph);
+            thread::spawn(move || txn(&graph, vec![OpType::Insert(1, None)]))
+        };
+        let outcome = txn(&graph, vec![OpType::Insert(1, None)]);
+        let other = other.join().unwrap();
+
+        assert!(outcome == TxnOutcome::Committed || other == TxnOutcome::Committed);
+        assert_eq!(vertices(&graph), vec![1]);
+    });
+}
+
+#[test]
+fn insert_edge_racing_delete_vertex() {
+    check(|| {
+        let graph = Arc::new(graph());
+        for v in 1..=3 {
+            assert_eq!(
+                txn(&graph, vec![OpType::Insert(v, None)]),
+                TxnOutcome::Committed
+            );
+        }
+        let arc = OpType::InsertArc(1, 3, edge(3), edge(1));
+        assert_eq!(txn(&graph, vec![arc]), TxnOutcome::Committed);
+
+        // The edge to 2 adopts the edge to 3, while the deletion marks the list of 1
+        let inserter = {
+            let graph = Arc::clone(&graph);
+            thread::spawn(move || txn(&graph, vec![OpType::InsertArc(1, 2, edge(2), edge(1))]))
+        };
+        let deleted = txn(&graph, vec![OpType::Delete(1)]);
+        let inserted = inserter.join().unwrap();
+
+        if deleted == TxnOutcome::Committed {
+            assert_eq!(vertices(&graph), vec![2, 3]);
+        } else {
+            let expected = if inserted == TxnOutcome::Committed {
+                vec![2, 3]
+            } else {
+                vec![3]
+            };
+            assert_eq!(out_edges(&graph, 1), Some(expected));
+        }
+
+        // A vertex that is inserted again starts without edges
+        assert_eq!(
+            txn(&graph, vec![OpType::Insert(1, None)]),
+            TxnOutcome::Committed
+        );
+        if deleted == TxnOutcome::Committed {
+            assert_eq!(out_edges(&graph, 1), Some(vec![]));
+        }
+    });
+}
+
+#[test]
+fn helping_a_stalled_transaction() {
+    // Set once an execution has one transaction finish the other
+    static HELPED: AtomicBool = AtomicBool::new(false);
+
+    check(|| {
+        let graph = Arc::new(graph());
+
+        let stalled = {
+            let graph = Arc::clone(&graph);
+            thread::spawn(move || {
+                txn(
+                    &graph,
+                    vec![OpType::Insert(1, None), OpType::Insert(2, None)],
+                )
+            })
+        };
+        let deleted = txn(&graph, vec![OpType::Delete(1)]);
+        let inserted = stalled.join().unwrap();
+
+        // Deleting a vertex that is being inserted has to finish the insertion first,
+        // so the transaction is never aborted, and takes effect as a whole.
+        // A deletion that is ordered before the insertion commits without effect.
+        assert_eq!(inserted, TxnOutcome::Committed);
+        let vertices = vertices(&graph);
+        if deleted == TxnOutcome::Committed {
+            assert!(vertices == vec![2] || vertices == vec![1, 2]);
+        } else {
+            assert_eq!(vertices, vec![1, 2]);
+        }
+
+        if graph.txn_stats().helps > 0 {
+            HELPED.store(true, SeqCst);
+        }
+    });
+
+    assert!(HELPED.load(SeqCst));
+}
@@ -1,17 +1,135 @@
-use crate::graphmodels::epoch::adjlist::RefEntry;
-use crossbeam_utils::atomic::AtomicCell;
+use crate::graphmodels::epoch::adjlist::{Node, RefEntry};
+use crate::graphmodels::epoch::sync::AtomicCell;
 
 use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::io;
 use std::mem;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum OpStatus {
     Active,
     Committed,
     Aborted,
 }
 
+/// How a transaction finished, as seen by the thread that started it
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TxnOutcome {
+    Committed,
+    /// One of the operations failed, e.g. the key did not exist
+    Aborted,
+    /// Aborted by a helping thread because of a conflicting descriptor
+    Conflicted,
+    /// Aborted because it could not be appended to the write-ahead log
+    LogFailed(io::ErrorKind),
+}
+
+/// Decides if and when a conflicted transaction is executed again.
+///
+/// Only transactions that were aborted because of a conflicting descriptor
+/// are retried, transactions that fail because of their operations are not.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Executes each transaction once, without retrying
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_micros(0),
+            max_backoff: Duration::from_micros(0),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts: std::cmp::max(max_attempts, 1),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Exponential backoff to wait before the given (1-based) attempt is retried
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let shift = std::cmp::min(attempt.saturating_sub(1), 31) as u32;
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << shift)
+            .unwrap_or(self.max_backoff);
+
+        std::cmp::min(backoff, self.max_backoff)
+    }
+}
+
+/// Contention counters for all transactions executed on an adjacency list
+#[derive(Default)]
+pub struct TxnStats {
+    commits: AtomicUsize,
+    aborts: AtomicUsize,
+    helps: AtomicUsize,
+    retries: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TxnStatsSnapshot {
+    pub commits: usize,
+    pub aborts: usize,
+    pub helps: usize,
+    pub retries: usize,
+}
+
+impl TxnStats {
+    #[inline]
+    pub fn record_commit(&self) {
+        self.commits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_abort(&self) {
+        self.aborts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_help(&self) {
+        self.helps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TxnStatsSnapshot {
+        TxnStatsSnapshot {
+            commits: self.commits.load(Ordering::Relaxed),
+            aborts: self.aborts.load(Ordering::Relaxed),
+            helps: self.helps.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.commits.store(0, Ordering::Relaxed);
+        self.aborts.store(0, Ordering::Relaxed);
+        self.helps.store(0, Ordering::Relaxed);
+        self.retries.store(0, Ordering::Relaxed);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ReturnCode<R> {
     Success,
@@ -30,17 +148,82 @@ pub enum OpType<'a, T, E> {
     Delete(usize),
     InsertEdge(usize, usize, Option<E>, bool),
     DeleteEdge(usize, usize, bool),
+    /// Inserts the directed edge `(v, e)` into the out-edges of `v`
+    /// and the in-edges of `e`, with the given values for each side
+    InsertArc(usize, usize, Option<E>, Option<E>),
+    /// Deletes the directed edge `(v, e)` from the out-edges of `v`
+    /// and the in-edges of `e`
+    DeleteArc(usize, usize),
+    /// Sets the value of the existing edge `(v, e)` once the transaction commits
+    UpdateEdge(usize, usize, E, bool),
+    /// Finds `v`, and fails if one of its out- or in-edges is present
+    /// without being owned by an earlier operation of the transaction
+    FindEdges(usize, bool),
+}
+
+impl<'a, T, E> OpType<'a, T, E> {
+    /// Whether the operation reads or updates the in-edges of a vertex
+    pub fn needs_in_edges(&self) -> bool {
+        match self {
+            OpType::InsertEdge(.., direction_in)
+            | OpType::DeleteEdge(.., direction_in)
+            | OpType::UpdateEdge(.., direction_in)
+            | OpType::FindEdges(_, direction_in) => *direction_in,
+            OpType::InsertArc(..) | OpType::DeleteArc(..) => true,
+            _ => false,
+        }
+    }
 }
 
 pub struct Operator<'a, T, E> {
     pub optype: OpType<'a, T, E>,
+    /// Added by the graph rather than requested by the caller, e.g. the deletion
+    /// of an edge of a deleted vertex. Its result is not reported, and if it fails,
+    /// what it was derived from has changed, so the transaction is conflicted.
+    pub derived: bool,
+}
+
+/// Bytes and allocations of every descriptor allocated so far.
+/// Descriptors are never freed, so this is what the graphs leave behind once dropped.
+#[cfg(feature = "testing")]
+static DESCRIPTOR_BYTES: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "testing")]
+static DESCRIPTOR_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "testing")]
+#[inline]
+fn record_descriptor(bytes: usize, allocations: usize) {
+    DESCRIPTOR_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    DESCRIPTOR_ALLOCATIONS.fetch_add(allocations, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "testing"))]
+#[inline]
+fn record_descriptor(_bytes: usize, _allocations: usize) {}
+
+/// Returns the bytes and the number of allocations of the descriptors allocated so far,
+/// by every graph. The values of edges that are carried by descriptors are not included.
+#[cfg(feature = "testing")]
+pub fn allocated_descriptors() -> (usize, usize) {
+    (
+        DESCRIPTOR_BYTES.load(Ordering::Relaxed),
+        DESCRIPTOR_ALLOCATIONS.load(Ordering::Relaxed),
+    )
 }
 
 pub struct Desc<'a, T, E> {
     pub status: AtomicCell<OpStatus>,
+    /// Set when the transaction is aborted by a helping thread,
+    /// rather than by one of its own operations failing
+    pub conflict: AtomicCell<bool>,
+    /// Set when the transaction could not be appended to the write-ahead log
+    pub log_error: AtomicCell<Option<io::ErrorKind>>,
     pub size: usize,
     pub ops: Vec<Operator<'a, T, E>>,
     pub pending: Vec<AtomicCell<bool>>,
+    /// The vertices whose edges each operation inserted or deleted,
+    /// one per side of an arc, set by the first thread that executed it
+    pub parents: Vec<[AtomicCell<*const Node<'a, T, E>>; 2]>,
 }
 
 impl<'a, T: 'a, E: 'a> Desc<'a, T, E> {
@@ -64,10 +247,22 @@ impl<'a, T: 'a, E: 'a> Desc<'a, T, E> {
             }
 
             ptr::write(&mut (*ptr).status, AtomicCell::new(OpStatus::Active));
+            ptr::write(&mut (*ptr).conflict, AtomicCell::new(false));
+            ptr::write(&mut (*ptr).log_error, AtomicCell::new(None));
 
             let size = ops.len();
             ptr::write(&mut (*ptr).size, size);
 
+            let ops_bytes = ops.capacity() * mem::size_of::<Operator<'a, T, E>>();
+            let pending_bytes = size * mem::size_of::<AtomicCell<bool>>();
+            let parents_bytes = size * mem::size_of::<[AtomicCell<*const Node<'a, T, E>>; 2]>();
+            record_descriptor(
+                layout.size() + ops_bytes + pending_bytes + parents_bytes,
+                1 + (ops_bytes > 0) as usize
+                    + (pending_bytes > 0) as usize
+                    + (parents_bytes > 0) as usize,
+            );
+
             ptr::write(&mut (*ptr).ops, ops);
 
             ptr::write(
@@ -75,6 +270,13 @@ impl<'a, T: 'a, E: 'a> Desc<'a, T, E> {
                 (0..size).map(|_| AtomicCell::new(true)).collect(),
             );
 
+            ptr::write(
+                &mut (*ptr).parents,
+                (0..size)
+                    .map(|_| [AtomicCell::new(ptr::null()), AtomicCell::new(ptr::null())])
+                    .collect(),
+            );
+
             ptr
         }
     }
@@ -99,13 +301,25 @@ impl<'a, T: 'a, E: 'a> Desc<'a, T, E> {
         Layout::from_size_align_unchecked(size_self, align_self)
     }
 
+    pub fn outcome(&self) -> TxnOutcome {
+        match (self.status.load(), self.log_error.load()) {
+            (OpStatus::Committed, _) => TxnOutcome::Committed,
+            (_, Some(kind)) => TxnOutcome::LogFailed(kind),
+            _ if self.conflict.load() => TxnOutcome::Conflicted,
+            _ => TxnOutcome::Aborted,
+        }
+    }
+
     #[must_use]
     pub fn empty() -> Self {
         Self {
             status: AtomicCell::new(OpStatus::Committed),
+            conflict: AtomicCell::new(false),
+            log_error: AtomicCell::new(None),
             size: 0,
             ops: Vec::new(),
             pending: Vec::new(),
+            parents: Vec::new(),
         }
     }
 }
@@ -115,6 +329,9 @@ pub struct NodeDesc<'a, T, E> {
     pub opid: usize,
     pub override_as_find: bool,
     pub override_as_delete: bool,
+    /// The value of an edge that was set by an `UpdateEdge` or a reinsertion,
+    /// carried over from the descriptor this one replaced
+    pub value: Option<E>,
 }
 
 impl<'a, T, E> Drop for NodeDesc<'a, T, E> {
@@ -124,13 +341,17 @@ impl<'a, T, E> Drop for NodeDesc<'a, T, E> {
 }
 
 impl<'a, T, E> NodeDesc<'a, T, E> {
+    /// Every node descriptor is moved to the heap once it is created, and never freed
     #[inline]
     pub fn new(desc: *const Desc<'a, T, E>, opid: usize) -> Self {
+        record_descriptor(mem::size_of::<Self>(), 1);
+
         Self {
             desc,
             opid,
             override_as_find: false,
             override_as_delete: false,
+            value: None,
         }
     }
 }
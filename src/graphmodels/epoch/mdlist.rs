@@ -3,7 +3,7 @@
 use crate::graphmodels::epoch::lftt::NodeDesc;
 use epoch::{Atomic, Guard, Shared};
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
 
@@ -51,25 +51,102 @@ pub enum LocatePredStatus {
     LogicallyDeleted,
 }
 
-const DIMENSION: usize = 16;
-const MASK: [usize; DIMENSION] = [
-    0x3 << 30,
-    0x3 << 28,
-    0x3 << 26,
-    0x3 << 24,
-    0x3 << 22,
-    0x3 << 20,
-    0x3 << 18,
-    0x3 << 16,
-    0x3 << 14,
-    0x3 << 12,
-    0x3 << 10,
-    0x3 << 8,
-    0x3 << 6,
-    0x3 << 4,
-    0x3 << 2,
-    0x3,
-];
+/// The largest supported dimension, nodes always have room for this many children
+pub const MAX_DIMENSION: usize = 16;
+
+/// Describes how keys are mapped to coordinates in an `MDList`.
+///
+/// A key is mapped to its `dimension` digits in base `basis`,
+/// most significant digit first. For the mapping to be balanced,
+/// `basis.pow(dimension)` should be close to the range of keys.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MDListConfig {
+    pub dimension: usize,
+    pub basis: usize,
+    /// `log2(basis)`, if `basis` is a power of two
+    shift: Option<u32>,
+}
+
+impl Default for MDListConfig {
+    /// 16 dimensions with basis 4, covering 32-bit keys
+    fn default() -> Self {
+        Self::new(MAX_DIMENSION, 4)
+    }
+}
+
+impl MDListConfig {
+    pub fn new(dimension: usize, basis: usize) -> Self {
+        assert!(
+            dimension >= 1 && dimension <= MAX_DIMENSION,
+            "`dimension` must be within 1..={}",
+            MAX_DIMENSION
+        );
+        assert!(basis >= 2, "`basis` must be at least 2");
+
+        Self {
+            dimension,
+            basis,
+            shift: if basis.is_power_of_two() {
+                Some(basis.trailing_zeros())
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Picks the smallest dimension that covers `key_range` with basis 4,
+    /// so that lookups stay logarithmic in the number of keys
+    pub fn for_key_range(key_range: usize) -> Self {
+        let mut dimension = 1;
+        while dimension < MAX_DIMENSION && 4usize.saturating_pow(dimension as u32) < key_range {
+            dimension += 1;
+        }
+
+        Self::with_dimension(key_range, dimension)
+    }
+
+    /// Picks the smallest basis that covers `key_range` in `dimension` dimensions
+    pub fn with_dimension(key_range: usize, dimension: usize) -> Self {
+        let dimension = std::cmp::min(std::cmp::max(dimension, 1), MAX_DIMENSION);
+        let covers = |basis: usize| basis.saturating_pow(dimension as u32) >= key_range;
+
+        // Start from the floating point root, and correct for rounding errors
+        let mut basis = std::cmp::max((key_range as f64).powf(1.0 / dimension as f64) as usize, 2);
+        while !covers(basis) {
+            basis += 1;
+        }
+        while basis > 2 && covers(basis - 1) {
+            basis -= 1;
+        }
+
+        Self::new(dimension, basis)
+    }
+
+    /// Maps `key` to its coordinates, unused dimensions are left as 0.
+    /// Keys larger than the key range are placed by their first coordinate.
+    #[inline]
+    pub fn key_to_coord(&self, mut key: usize) -> [usize; MAX_DIMENSION] {
+        let mut coord = [0; MAX_DIMENSION];
+        match self.shift {
+            Some(shift) => {
+                let mask = self.basis - 1;
+                for i in (1..self.dimension).rev() {
+                    coord[i] = key & mask;
+                    key >>= shift;
+                }
+            }
+            None => {
+                for i in (1..self.dimension).rev() {
+                    coord[i] = key % self.basis;
+                    key /= self.basis;
+                }
+            }
+        }
+        coord[0] = key;
+
+        coord
+    }
+}
 
 /// An entry in the adjacency list.
 /// It is guaranteed to live as long as the Guard
@@ -81,17 +158,22 @@ pub struct Entry<'a: 't + 'g, 't, 'g, T: 'a, P> {
 }
 
 impl<'a: 't + 'g, 't, 'g, T: 'a, P> Entry<'a, 't, 'g, T, P> {
-    pub fn value(&self) -> Option<&T> {
+    pub fn value(&self) -> Option<&'g T> {
         self.node.val.as_ref()
     }
 }
 
+/// Iterates the entries of an `MDList` in ascending key order.
+///
+/// Every descendant of a node has a larger key than the node itself,
+/// and a child in a higher dimension only holds keys that are smaller
+/// than those under a child in a lower dimension.
+/// A depth-first walk that yields a node before its children,
+/// and visits the children from the highest dimension down, is therefore sorted.
 pub struct Iter<'a: 't + 'g, 't, 'g, T: 'a, P: 'a> {
     parent: &'t MDList<'a, T, P>,
     guard: &'g Guard,
     stack: Vec<&'t Atomic<MDNode<'a, T, P>>>,
-    current: Option<&'t MDNode<'a, T, P>>,
-    returned_prematurely: bool,
 }
 
 impl<'a: 't + 'g, 't, 'g, T: 'a, P: 'a> Iterator for Iter<'a, 't, 'g, T, P> {
@@ -101,42 +183,41 @@ impl<'a: 't + 'g, 't, 'g, T: 'a, P: 'a> Iterator for Iter<'a, 't, 'g, T, P> {
         unsafe {
             let guard = &*(self.guard as *const _);
 
-            if self.returned_prematurely {
-                self.returned_prematurely = false;
-                for d in 0..DIMENSION {
-                    let child = &self.current.unwrap().children[d];
-                    if !child.load(SeqCst, guard).is_null() {
-                        self.stack.push(&child);
-                    }
+            let dimension = self.parent.config.dimension;
+            while let Some(shared) = self.stack.pop().map(|n| n.load(SeqCst, guard)) {
+                let node = match shared.as_ref() {
+                    Some(node) => node,
+                    None => continue,
+                };
+
+                // Finish any pending adoption, so that the children
+                // are only reachable through one parent
+                let pending = node.pending.load(SeqCst, guard);
+                if !pending.is_null() {
+                    MDList::finish_inserting(node, pending, guard);
                 }
-            }
 
-            // let mut entries = VecDeque::new();
-            while let Some(node) = self.stack.pop().map(|n| n.load(SeqCst, guard)) {
-                if node.is_null() || is_delinv(node.tag()) != 0 {
-                    continue;
+                // A deleted node still routes to its children,
+                // so we descend into it even though it is not returned.
+                // Children are pushed from the lowest dimension,
+                // which makes the highest dimension pop first.
+                for d in 0..dimension {
+                    let child = &node.children[d];
+                    let loaded_child = child.load(SeqCst, guard);
+                    if !loaded_child.is_null() && is_adpinv(loaded_child.tag()) == 0 {
+                        self.stack.push(child);
+                    }
                 }
 
-                let node = node.as_ref().unwrap();
-                self.current = Some(node);
-
                 // The root node might not be logically added,
                 // so if it has no value, we skip it
-                if node.val.is_some() {
-                    self.returned_prematurely = true;
+                if is_delinv(shared.tag()) == 0 && node.val.is_some() {
                     return Some(Entry {
                         node,
                         _parent: self.parent,
                         _guard: self.guard,
                     });
                 }
-
-                for d in 0..DIMENSION {
-                    let child = &node.children[d];
-                    if !child.load(SeqCst, guard).is_null() {
-                        self.stack.push(&child);
-                    }
-                }
             }
 
             None
@@ -267,46 +348,110 @@ pub struct MDDesc<'a, T, P> {
 #[repr(C)]
 pub struct MDNode<'a, T, P> {
     pub key: usize,
-    coord: [usize; DIMENSION],
+    coord: [usize; MAX_DIMENSION],
     val: Option<T>,
     pub pending: Atomic<MDDesc<'a, T, P>>,
     pub node_desc: Atomic<NodeDesc<'a, P, T>>,
-    pub children: [Atomic<Self>; DIMENSION],
+    pub children: [Atomic<Self>; MAX_DIMENSION],
+}
+
+/// Maps `key` to the coordinates of its node.
+///
+/// Keys are shifted by one, as the head of every list is placed at the origin.
+#[inline]
+fn node_coord(config: &MDListConfig, key: usize) -> [usize; MAX_DIMENSION] {
+    config.key_to_coord(key.saturating_add(1))
 }
 
 impl<'a, T, P> MDNode<'a, T, P> {
-    pub fn new(key: usize, val: Option<T>) -> Self {
+    pub fn new(key: usize, val: Option<T>, config: &MDListConfig) -> Self {
         Self {
             key,
             val,
-            coord: MDList::<T, P>::key_to_coord(key),
+            coord: node_coord(config, key),
             pending: Atomic::null(),
             node_desc: Atomic::null(),
             children: Default::default(),
         }
     }
+
+    /// The value the node was inserted with
+    pub fn value(&self) -> Option<&T> {
+        self.val.as_ref()
+    }
 }
 
 #[repr(C)]
 pub struct MDList<'a, T, P> {
-    basis: usize,
+    config: MDListConfig,
     len: AtomicUsize,
     head: Atomic<MDNode<'a, T, P>>,
 }
 
+unsafe impl<#[may_dangle] 'a, #[may_dangle] T, #[may_dangle] P> Drop for MDList<'a, T, P> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = epoch::unprotected();
+            // Children that are being adopted are linked from both of their parents,
+            // so every node is collected once before any of them is freed
+            let mut nodes = HashSet::new();
+            let mut pending = HashSet::new();
+            let mut stack = vec![self.head.load(SeqCst, guard)];
+            while let Some(node) = stack.pop() {
+                let node = node.with_tag(0);
+                let node_ref = match node.as_ref() {
+                    Some(node_ref) => node_ref,
+                    None => continue,
+                };
+                if !nodes.insert(node.as_raw()) {
+                    continue;
+                }
+
+                let desc = node_ref.pending.load(SeqCst, guard);
+                if !desc.is_null() {
+                    pending.insert(desc.as_raw());
+                }
+                for child in &node_ref.children[..self.config.dimension] {
+                    stack.push(child.load(SeqCst, guard));
+                }
+            }
+
+            for desc in pending {
+                drop(Shared::from(desc).into_owned());
+            }
+            for node in nodes {
+                drop(Shared::from(node).into_owned());
+            }
+        }
+    }
+}
+
 impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
-    pub fn new(basis: usize) -> Self {
+    pub fn new(config: MDListConfig) -> Self {
         Self {
-            head: Atomic::new(MDNode::new(0, None)),
+            head: Atomic::new(MDNode {
+                coord: [0; MAX_DIMENSION],
+                ..MDNode::new(0, None, &config)
+            }),
             len: AtomicUsize::new(0),
-            basis,
+            config,
         }
     }
 
+    pub fn config(&self) -> &MDListConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn key_to_coord(&self, key: usize) -> [usize; MAX_DIMENSION] {
+        node_coord(&self.config, key)
+    }
+
     pub fn len(&self) -> usize {
         self.len.load(SeqCst)
     }
 
+    /// Iterates the entries in ascending key order
     pub fn iter<'t>(&'t self, guard: &'g Guard) -> Iter<'a, 't, 'g, T, P> {
         let mut stack = Vec::new();
         stack.push(&self.head);
@@ -314,8 +459,37 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
             parent: self,
             stack,
             guard,
-            current: None,
-            returned_prematurely: false,
+        }
+    }
+
+    /// Visits every node that is physically in the list, starting with the head.
+    /// Unlike `iter`, nodes that are logically deleted are visited as well.
+    pub fn visit_nodes(&self, guard: &Guard, mut visit: impl FnMut(&MDNode<'a, T, P>)) {
+        unsafe {
+            // Hack to bind lifetime of guard to self.
+            let guard = &*(guard as *const _);
+            let mut stack = vec![self.head.load(SeqCst, guard)];
+            while let Some(node) = stack.pop() {
+                let node = match node.as_ref() {
+                    Some(node) => node,
+                    None => continue,
+                };
+
+                // Adopted children are still linked from their old parent
+                let pending = node.pending.load(SeqCst, guard);
+                if !pending.is_null() {
+                    MDList::finish_inserting(node, pending, guard);
+                }
+
+                for child in &node.children[..self.config.dimension] {
+                    let child = child.load(SeqCst, guard);
+                    if is_adpinv(child.tag()) == 0 {
+                        stack.push(child.with_tag(0));
+                    }
+                }
+
+                visit(node);
+            }
         }
     }
 
@@ -324,22 +498,22 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
     }
 
     pub unsafe fn get<'t>(
-        &'a self,
+        &'t self,
         key: usize,
         guard: &'g Guard,
     ) -> Result<Entry<'a, 't, 'g, T, P>, impl std::fmt::Debug> {
         // Rebind lifetime to self
         let guard = &*(guard as *const _);
 
-        let coord = Self::key_to_coord(key);
+        let coord = self.key_to_coord(key);
         let pred = &mut Shared::null();
         let curr = &mut self.head.load(SeqCst, guard);
         let mut dim = 0;
         let mut pred_dim = 0;
         if let LocatePredStatus::Found =
-            Self::locate_pred(&coord, pred, curr, &mut dim, &mut pred_dim, guard)
+            self.locate_pred(&coord, pred, curr, &mut dim, &mut pred_dim, guard)
         {
-            if dim == DIMENSION {
+            if dim == self.config.dimension {
                 if let Some(curr_ref) = curr.as_ref() {
                     Ok(Entry {
                         node: curr_ref,
@@ -357,39 +531,9 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         }
     }
 
+    /// Collects the entries in ascending key order
     pub fn entries<'t>(&'a self, guard: &'g Guard) -> Vec<Entry<'a, 't, 'g, T, P>> {
-        unsafe {
-            let mut stack = Vec::new();
-            stack.push(&self.head);
-
-            let mut entries = Vec::new();
-            while let Some(node) = stack.pop().map(|n| n.load(SeqCst, guard)) {
-                if node.is_null() || is_delinv(node.tag()) != 0 {
-                    continue;
-                }
-
-                let node = node.as_ref().unwrap();
-
-                // The root node might not be logically added,
-                // so if it has no value, we skip it
-                if node.val.is_some() {
-                    entries.push(Entry {
-                        node,
-                        _parent: self,
-                        _guard: guard,
-                    })
-                }
-
-                for d in 0..DIMENSION {
-                    let child = &node.children[d];
-                    if !child.load(SeqCst, guard).is_null() {
-                        stack.push(&child);
-                    }
-                }
-            }
-
-            entries
-        }
+        self.iter(guard).collect()
     }
 
     pub unsafe fn insert(
@@ -402,10 +546,11 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         guard: &Guard,
     ) -> bool {
         let guard = &*(guard as *const _);
+        let dimension = self.config.dimension;
 
         let pred_ref = pred.as_ref().unwrap(); // Safe unwrap
-        let pred_child = &pred_ref.children[*pred_dim].load(SeqCst, guard);
-        if *dim == DIMENSION && is_delinv(pred_child.tag()) == 0 {
+        let mut pred_child = pred_ref.children[*pred_dim].load(SeqCst, guard);
+        if *dim == dimension && is_delinv(pred_child.tag()) == 0 {
             return false;
         }
 
@@ -415,46 +560,60 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         } else {
             expected = curr.with_tag(set_delinv(curr.tag()));
 
-            if *dim == DIMENSION - 1 {
-                *dim = DIMENSION;
+            if *dim == dimension - 1 {
+                *dim = dimension;
             }
         }
 
-        if *pred_child == expected {
+        if pred_child == expected {
             let desc = Self::fill_new_node(
                 &new_node.load(SeqCst, guard).as_ref().unwrap(),
                 pred,
                 expected,
                 dim,
                 pred_dim,
+                dimension,
                 guard,
             );
 
-            if let Ok(pred_child) = pred_ref.children[*pred_dim].compare_and_set(
+            match pred_ref.children[*pred_dim].compare_exchange(
                 expected,
                 new_node.load(SeqCst, guard),
                 SeqCst,
+                SeqCst,
                 guard,
             ) {
-                if pred_child == expected && !desc.load(SeqCst, guard).is_null() {
-                    if let Some(curr_ref) = curr.as_ref() {
+                Ok(_) => {
+                    if !desc.load(SeqCst, guard).is_null() {
+                        // `curr` must finish its own adoption before we adopt its children,
+                        // otherwise the children it has yet to adopt are lost
+                        if let Some(curr_ref) = curr.as_ref() {
+                            let curr_pending = curr_ref.pending.load(SeqCst, guard);
+                            if !curr_pending.is_null() {
+                                Self::finish_inserting(curr_ref, curr_pending, guard);
+                            }
+                        }
+
                         Self::finish_inserting(
-                            curr_ref,
-                            curr_ref.pending.load(SeqCst, guard),
+                            &new_node.load(SeqCst, guard).as_ref().unwrap(),
+                            desc.load(SeqCst, guard),
                             guard,
                         );
                     }
 
-                    Self::finish_inserting(
-                        &new_node.load(SeqCst, guard).as_ref().unwrap(),
-                        desc.load(SeqCst, guard),
-                        guard,
-                    );
+                    self.len.fetch_add(1, SeqCst);
+                    return true;
+                }
+                Err(err) => {
+                    // The descriptor was never published, a retry fills in a new one
+                    let new_node_ref = new_node.load(SeqCst, guard).deref();
+                    new_node_ref.pending.store(Shared::null(), SeqCst);
+                    if !desc.load(SeqCst, guard).is_null() {
+                        drop(desc.into_owned());
+                    }
+                    pred_child = err.current;
                 }
             }
-
-            self.len.fetch_add(1, SeqCst);
-            return true;
         }
 
         //If the code reaches here it means the CAS failed
@@ -486,14 +645,21 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         curr: &mut Shared<'t, MDNode<'a, T, P>>,
         pred_dim: &mut usize,
         dim: &mut usize,
+        dimension: usize,
         guard: &Guard,
     ) -> bool {
-        if *dim == DIMENSION {
+        if *dim == dimension {
             let pred_child = &pred.as_ref().unwrap().children[*pred_dim];
 
             if pred_child.load(SeqCst, guard) == *curr
                 && pred_child
-                    .compare_and_set(*curr, curr.with_tag(set_delinv(curr.tag())), SeqCst, guard)
+                    .compare_exchange(
+                        *curr,
+                        curr.with_tag(set_delinv(curr.tag())),
+                        SeqCst,
+                        SeqCst,
+                        guard,
+                    )
                     .is_ok()
             {
                 return true;
@@ -508,51 +674,20 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         // Rebind lifetime to self
         let guard = &*(guard as *const _);
 
-        let coord = Self::key_to_coord(key);
+        let coord = self.key_to_coord(key);
         let pred = &mut Shared::null();
         let curr = &mut self.head.load(SeqCst, guard);
         let mut dim = 0;
         let mut pred_dim = 0;
 
-        Self::locate_pred(&coord, pred, curr, &mut dim, &mut pred_dim, guard);
+        self.locate_pred(&coord, pred, curr, &mut dim, &mut pred_dim, guard);
 
-        dim == DIMENSION
-    }
-
-    /// Computes the 16th root of a given key
-    #[inline]
-    pub fn key_to_coord(key: usize) -> [usize; DIMENSION] {
-        // let mut coords = [0; DIMENSION];
-        // for i in 0..DIMENSION {
-        //     coords[i] = (key & MASK[i]) >> (30 - (i << 1));
-        // }
-        // coords
-
-        // The above code is 83 assebly instructions, this is 63.
-        // We mainly abvoid movups instructions
-        // Does it improve performance signficantly? ¯\_(ツ)_/¯, probably not
-        [
-            (key & MASK[0]) >> 30,
-            (key & MASK[1]) >> (30 - (1 << 1)),
-            (key & MASK[2]) >> (30 - (2 << 1)),
-            (key & MASK[3]) >> (30 - (3 << 1)),
-            (key & MASK[4]) >> (30 - (4 << 1)),
-            (key & MASK[5]) >> (30 - (5 << 1)),
-            (key & MASK[6]) >> (30 - (6 << 1)),
-            (key & MASK[7]) >> (30 - (7 << 1)),
-            (key & MASK[8]) >> (30 - (8 << 1)),
-            (key & MASK[9]) >> (30 - (9 << 1)),
-            (key & MASK[10]) >> (30 - (10 << 1)),
-            (key & MASK[11]) >> (30 - (11 << 1)),
-            (key & MASK[12]) >> (30 - (12 << 1)),
-            (key & MASK[13]) >> (30 - (13 << 1)),
-            (key & MASK[14]) >> (30 - (14 << 1)),
-            (key & MASK[15]),
-        ]
+        dim == self.config.dimension
     }
 
     #[inline]
     pub unsafe fn next_node(
+        &self,
         curr: &mut Shared<'a, MDNode<'a, T, P>>,
         dim: usize,
         stack: &mut Vec<Shared<'a, MDNode<'a, T, P>>>,
@@ -568,7 +703,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
 
         let node_ref = curr.as_ref().unwrap();
 
-        for d in dim..DIMENSION {
+        for d in dim..self.config.dimension {
             let child = &node_ref.children[d];
             let loaded_child = child.load(SeqCst, guard);
 
@@ -587,7 +722,8 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
 
     #[inline]
     pub unsafe fn locate_pred<'t>(
-        coord: &[usize; DIMENSION],
+        &self,
+        coord: &[usize; MAX_DIMENSION],
         pred: &mut Shared<'t, MDNode<'a, T, P>>,
         curr: &mut Shared<'t, MDNode<'a, T, P>>,
         dim: &mut usize,
@@ -601,7 +737,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         let mut status = LocatePredStatus::Found;
         // Locate the proper position to insert
         // tranverses list from low dim to high dim
-        while *dim < DIMENSION {
+        while *dim < self.config.dimension {
             // Locate predecessor and successor
             while let Some(curr_ref) = curr.as_ref() {
                 if coord[*dim] > curr_ref.coord[*dim] {
@@ -647,6 +783,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         curr: Shared<'a, MDNode<'a, T, P>>,
         dim: &mut usize,
         pred_dim: &mut usize,
+        dimension: usize,
         guard: &Guard,
     ) -> Atomic<MDDesc<'a, T, P>> {
         let mut desc = Atomic::null();
@@ -670,7 +807,7 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
         }
 
         // FIXME:(rasmus) A memset is missing here...
-        if *dim < DIMENSION {
+        if *dim < dimension {
             new_node.children[*dim].store(curr, SeqCst);
         }
 
@@ -700,20 +837,55 @@ impl<'a: 'd + 'g, 'd, 'g, T: 'a, P: 'a> MDList<'a, T, P> {
                 // FIXME:(rasmus) Unnecessary load? CAS only succeeds if it is null...
                 if n.children[i].load(SeqCst, guard).is_null()
                     && n.children[i]
-                        .compare_and_set(Shared::null(), g_child, SeqCst, guard)
+                        .compare_exchange(Shared::null(), g_child, SeqCst, SeqCst, guard)
                         .is_err()
                 {}
             }
         }
 
         if n.pending.load(SeqCst, guard) == desc {
-            if let Ok(_p) = n
-                .pending
-                .compare_and_set(desc, Shared::null(), SeqCst, guard)
+            if n.pending
+                .compare_exchange(desc, Shared::null(), SeqCst, SeqCst, guard)
+                .is_ok()
             {
-                // FIXME:(rasmus) Do proper clean-up here
-                // guard.defer_destroy(p);
+                // Helpers that loaded the descriptor before it was cleared may still read it
+                guard.defer_destroy(desc);
             }
         }
     }
 }
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_covers_the_key_range() {
+        let config = |c: MDListConfig| (c.dimension, c.basis);
+
+        assert_eq!(config(MDListConfig::for_key_range(0)), (1, 2));
+        assert_eq!(config(MDListConfig::for_key_range(1000)), (5, 4));
+        assert_eq!(config(MDListConfig::for_key_range(50_000_000)), (13, 4));
+        assert_eq!(
+            config(MDListConfig::for_key_range(usize::max_value())),
+            (16, 16)
+        );
+        assert_eq!(config(MDListConfig::with_dimension(1000, 3)), (3, 10));
+        assert_eq!(config(MDListConfig::with_dimension(1001, 3)), (3, 11));
+        assert_eq!(config(MDListConfig::with_dimension(1000, 0)), (1, 1000));
+    }
+
+    #[test]
+    fn coordinates_are_ordered_like_keys() {
+        assert_eq!(MDListConfig::new(3, 10).key_to_coord(123)[..3], [1, 2, 3]);
+        assert_eq!(MDListConfig::new(2, 4).key_to_coord(7)[..2], [1, 3]);
+        // Keys beyond the key range are placed by their first coordinate
+        assert_eq!(MDListConfig::new(2, 4).key_to_coord(37)[..2], [9, 1]);
+
+        for (dimension, basis) in &[(1, 2), (2, 4), (3, 10), (4, 3), (16, 4)] {
+            let config = MDListConfig::new(*dimension, *basis);
+            let coords: Vec<_> = (0..1000).map(|key| config.key_to_coord(key)).collect();
+            assert!(coords.windows(2).all(|w| w[0] < w[1]), "{:?}", config);
+        }
+    }
+}
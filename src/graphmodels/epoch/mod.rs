@@ -1,20 +1,44 @@
 mod adjlist;
+mod filter;
+mod index;
 mod lftt;
 mod mdlist;
-
-use crate::graphmodels::epoch::adjlist::AdjacencyList;
-pub use crate::graphmodels::epoch::adjlist::{IterRefEntry, Node, RefEntry};
-
-pub use crate::graphmodels::epoch::lftt::{OpType, ReturnCode};
+mod sync;
+mod view;
+mod wal;
+
+#[cfg(all(test, crossbeam_loom))]
+mod interleavings;
+
+use crate::graphmodels::epoch::adjlist::{AdjacencyList, Transaction};
+pub use crate::graphmodels::epoch::adjlist::{IterRefEntry, Node, RangeRefEntry, RefEntry};
+
+pub use crate::graphmodels::epoch::mdlist::MDListConfig;
+use crate::graphmodels::epoch::view::Pinned;
+pub use crate::graphmodels::epoch::view::{EdgeIter, ReadView};
+pub use crate::graphmodels::epoch::wal::WalConfig;
+use crate::graphmodels::epoch::wal::WriteAheadLog;
+
+#[cfg(feature = "testing")]
+pub use crate::graphmodels::epoch::lftt::allocated_descriptors;
+use crate::graphmodels::epoch::lftt::Operator;
+pub use crate::graphmodels::epoch::lftt::{
+    OpType, RetryPolicy, ReturnCode, TxnOutcome, TxnStatsSnapshot,
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
 
 use epoch::{Atomic, Guard, Shared};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::ops::RangeBounds;
+use std::path::Path;
 use std::sync::atomic::AtomicUsize;
-use std::sync::{Arc, RwLock};
 
 use crate::graph::{CSRGraph, Range};
+use crate::memory::MemoryUsage;
 use crate::types::*;
 
 #[derive(Clone, Copy)]
@@ -78,30 +102,175 @@ impl AsNode for EdgeInfo {
 
 pub struct Graph<'a, T: Copy + Clone + Into<usize>> {
     inner: AdjacencyList<'a, T, EdgeInfo>,
-    cache: Arc<RwLock<BTreeMap<NodeId, &'a Node<'a, T, E>>>>,
+    retry_policy: RetryPolicy,
     directed: bool,
     num_nodes: usize,
-    num_edges: usize,
 }
 
 type E = EdgeInfo;
 
 impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
+    /// Creates a graph whose `MDList` dimension is derived from `size_hint`
     pub fn new(size_hint: i64, directed: bool) -> Self {
+        let md_config = MDListConfig::for_key_range(std::cmp::max(size_hint, 0) as usize);
+        Self::with_md_config(size_hint, directed, md_config)
+    }
+
+    /// Creates a graph where every `MDList` uses `md_config`
+    pub fn with_md_config(size_hint: i64, directed: bool, md_config: MDListConfig) -> Self {
         Self {
-            inner: AdjacencyList::new(size_hint),
-            cache: Arc::new(RwLock::new(BTreeMap::new())),
+            inner: AdjacencyList::with_config(size_hint, md_config),
+            retry_policy: RetryPolicy::default(),
             directed,
             num_nodes: 0,
-            num_edges: 0,
         }
     }
 
+    /// Sets how transactions that abort because of a conflicting descriptor are retried
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Commits, aborts, helping events and retries since the graph was created
+    pub fn txn_stats(&self) -> TxnStatsSnapshot {
+        self.inner.stats().snapshot()
+    }
+
+    pub fn reset_txn_stats(&self) {
+        self.inner.stats().reset();
+    }
+
+    /// Lets lookups of vertices that were never inserted fail without traversing the list,
+    /// at the cost of updating a bloom filter on every insert
+    pub fn enable_bloom_filter(&mut self, false_positive_rate: f64) {
+        self.inner.enable_bloom_filter(false_positive_rate);
+    }
+
+    /// Number of times the bloom filter has been rebuilt to forget deleted vertices,
+    /// `None` if it is disabled
+    pub fn bloom_filter_rebuilds(&self) -> Option<usize> {
+        self.inner.bloom_filter().map(|filter| filter.rebuilds())
+    }
+
+    /// Executes `ops` as a single transaction.
+    ///
+    /// If the transaction is aborted by a conflicting transaction, it is
+    /// executed again according to the graph's `RetryPolicy`.
+    /// The receiver holds the results of the last attempt.
+    ///
+    /// A vertex is deleted together with its edges, and the edges of other vertices
+    /// that point to it, in the same transaction.
     pub fn execute_ops<'t>(
         &'t self,
         ops: Vec<OpType<'a, T, E>>,
     ) -> std::sync::mpsc::Receiver<ReturnCode<Atomic<Node<'a, T, E>>>> {
-        self.inner.txn(ops).execute()
+        self.execute_ops_with_outcome(ops).1
+    }
+
+    /// Like `execute_ops`, but also reports how the last attempt finished
+    pub fn execute_ops_with_outcome<'t>(
+        &'t self,
+        ops: Vec<OpType<'a, T, E>>,
+    ) -> (
+        TxnOutcome,
+        std::sync::mpsc::Receiver<ReturnCode<Atomic<Node<'a, T, E>>>>,
+    ) {
+        self.with_retries(|| {
+            Transaction::new(&self.inner, self.with_incident_edges(&ops)).execute_with_outcome()
+        })
+    }
+
+    /// Runs `attempt_txn` again according to the graph's `RetryPolicy`,
+    /// as long as it reports a conflicted transaction
+    fn with_retries<R>(&self, mut attempt_txn: impl FnMut() -> (TxnOutcome, R)) -> (TxnOutcome, R) {
+        let mut attempt = 1;
+        loop {
+            let (outcome, res) = attempt_txn();
+
+            if outcome != TxnOutcome::Conflicted || attempt >= self.retry_policy.max_attempts {
+                // A snapshot that fails leaves the log as it is, and is tried again
+                // after the next transaction
+                let _ = self.inner.snapshot_if_due();
+                return (outcome, res);
+            }
+
+            self.inner.stats().record_retry();
+            let backoff = self.retry_policy.backoff(attempt);
+            if backoff > std::time::Duration::from_micros(0) {
+                sync::backoff(backoff);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Precedes every `Delete(v)` in `ops` with the deletion of the edges of `v`,
+    /// and of the edges of other vertices that point to `v`, as they are read now.
+    ///
+    /// An edge added to `v` after it was read is found by the `Delete`,
+    /// which aborts the transaction as conflicted, so that the edges are read again.
+    fn with_incident_edges(&self, ops: &[OpType<'a, T, E>]) -> Vec<Operator<'a, T, E>> {
+        let requested = |optype: &OpType<'a, T, E>| Operator {
+            optype: optype.clone(),
+            derived: false,
+        };
+
+        if !ops.iter().any(|op| matches!(op, OpType::Delete(_))) {
+            return ops.iter().map(requested).collect();
+        }
+
+        let guard = &epoch::pin();
+        let view = self.read(guard);
+
+        // Edges deleted by earlier operations cannot be deleted again
+        let mut deleted = HashSet::new();
+        let mut expanded = Vec::with_capacity(ops.len());
+        for op in ops {
+            match op {
+                OpType::DeleteEdge(v, e, direction_in) => {
+                    deleted.insert((*v, *e, *direction_in));
+                }
+                OpType::DeleteArc(v, e) => {
+                    deleted.insert((*v, *e, false));
+                    deleted.insert((*e, *v, true));
+                }
+                OpType::Delete(v) => {
+                    let mut delete = |v: NodeId, e: NodeId, direction_in: bool| {
+                        if deleted.insert((v, e, direction_in)) {
+                            expanded.push(Operator {
+                                optype: OpType::DeleteEdge(v, e, direction_in),
+                                derived: true,
+                            });
+                        }
+                    };
+
+                    for e in view.out_edges(*v).into_iter().flatten() {
+                        delete(*v, e.node_id, false);
+                        // In an undirected graph, both sides of an edge are stored as out-edges
+                        if self.directed && view.has_in_edge(e.node_id, *v) {
+                            delete(e.node_id, *v, true);
+                        } else if !self.directed && view.has_out_edge(e.node_id, *v) {
+                            delete(e.node_id, *v, false);
+                        }
+                    }
+
+                    for e in view.in_edges(*v).into_iter().flatten() {
+                        delete(*v, e.node_id, true);
+                        if view.has_out_edge(e.node_id, *v) {
+                            delete(e.node_id, *v, false);
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            expanded.push(requested(op));
+        }
+
+        expanded
     }
 
     pub fn add_vertex<'t>(
@@ -131,9 +300,10 @@ impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
         insert_edge_txn.execute().recv().expect("Txn failed");
     }
 
-    pub fn connect<'t>(parent: &Node<T, E>, child: E, direction_in: bool) {
+    pub fn connect<'t>(&self, parent: &Node<'a, T, E>, child: E, direction_in: bool) {
         unsafe {
-            AdjacencyList::connect(parent, child.node_id, child, direction_in);
+            self.inner
+                .connect(parent, child.node_id, child, direction_in);
         }
     }
 
@@ -156,10 +326,50 @@ impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
     //     }
     // }
 
-    pub fn iter_vertices<'t, 'g>(&'a self, guard: &'g Guard) -> IterRefEntry<'a, 't, 'g, T, E> {
+    pub fn iter_vertices<'t, 'g>(&'t self, guard: &'g Guard) -> IterRefEntry<'a, 't, 'g, T, E>
+    where
+        'a: 't + 'g,
+    {
         self.inner.iter(guard)
     }
 
+    /// Opens a read-only view of the graph that is valid while `guard` is pinned
+    pub fn read<'g>(&'g self, guard: &'g Guard) -> ReadView<'a, 'g, T>
+    where
+        'a: 'g,
+    {
+        ReadView::new(self, guard)
+    }
+
+    /// Iterates the vertices with keys within `range` in ascending order,
+    /// e.g. to shard work over key ranges
+    pub fn vertex_range<'t, 'g, R: RangeBounds<usize>>(
+        &'t self,
+        range: R,
+        guard: &'g Guard,
+    ) -> RangeRefEntry<'a, 't, 'g, T, E>
+    where
+        'a: 't + 'g,
+    {
+        self.inner.range(range, guard)
+    }
+
+    /// Returns the vertex with the smallest key greater than `key`
+    pub fn successor<'g>(&self, key: usize, guard: &'g Guard) -> Option<RefEntry<'a, 'g, T, E>>
+    where
+        'a: 'g,
+    {
+        self.inner.successor(key, guard)
+    }
+
+    /// Returns the vertex with the largest key less than `key`
+    pub fn predecessor<'g>(&self, key: usize, guard: &'g Guard) -> Option<RefEntry<'a, 'g, T, E>>
+    where
+        'a: 'g,
+    {
+        self.inner.predecessor(key, guard)
+    }
+
     pub fn find_vertex<'t>(&'t self, key: usize) -> Option<Atomic<Node<'a, T, E>>> {
         let op = OpType::Find(key);
         let find_txn = self.inner.txn(vec![op]);
@@ -174,7 +384,7 @@ impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
 
     pub fn delete_vertex<'t>(&'t self, key: usize) -> Option<Atomic<Node<'a, T, E>>> {
         let op = OpType::Delete(key);
-        let insertion_txn = self.inner.txn(vec![op]).execute();
+        let insertion_txn = self.execute_ops(vec![op]);
 
         if let Ok(ReturnCode::Deleted(entry)) = insertion_txn.recv() {
             Some(entry)
@@ -183,6 +393,29 @@ impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
         }
     }
 
+    /// Inserts the directed edge `(v, e)` into the out-edges of `v`
+    /// and the in-edges of `e` in a single transaction
+    pub fn add_arc(&self, v: usize, e: usize, weight: Option<Weight>) -> Result<(), ()> {
+        let out_edge = EdgeInfo { node_id: e, weight };
+        let in_edge = EdgeInfo { node_id: v, weight };
+
+        let op = OpType::InsertArc(v, e, Some(out_edge), Some(in_edge));
+        match self.execute_ops(vec![op]).recv() {
+            Ok(ReturnCode::Fail(_)) | Err(_) => Err(()),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Deletes the directed edge `(v, e)` from the out-edges of `v`
+    /// and the in-edges of `e` in a single transaction
+    pub fn delete_arc(&self, v: usize, e: usize) -> Result<(), ()> {
+        let op = OpType::DeleteArc(v, e);
+        match self.execute_ops(vec![op]).recv() {
+            Ok(ReturnCode::Fail(_)) | Err(_) => Err(()),
+            Ok(_) => Ok(()),
+        }
+    }
+
     pub fn delete_edge<'t>(
         &'t self,
         parent: usize,
@@ -200,100 +433,305 @@ impl<'a, T: 'a + Copy + Clone + Into<usize>> Graph<'a, T> {
     }
 }
 
-impl<'a> CSRGraph<CustomNode, EdgeInfo> for Graph<'_, usize> {
-    fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(num_nodes as i64, true);
-        let guard = unsafe { &*(&epoch::pin() as *const _) };
-        for v in 1..num_nodes {
-            let inserted = graph.add_vertex(v, None);
-            graph
-                .cache
-                .write()
-                .expect("Could not write")
-                .insert(v, unsafe {
-                    inserted.unwrap().1.load(SeqCst, guard).deref()
+impl<'a> Graph<'a, usize> {
+    /// Replaces the out- or in-edges of `v` with `edges` in one transaction.
+    ///
+    /// Only the difference is applied: targets that are no longer present are deleted,
+    /// new targets are inserted, and edges to targets in both sets take the new weight.
+    /// In a directed graph, the opposite side of every edge is updated as well.
+    ///
+    /// The transaction fails if the edges of `v` changed after they were read,
+    /// in which case they are read again according to the graph's `RetryPolicy`.
+    pub fn replace_edges(&self, v: NodeId, mut edges: Vec<E>, direction_in: bool) {
+        edges.sort_by_key(|e| e.node_id);
+        edges.dedup_by_key(|e| e.node_id);
+
+        self.with_retries(|| match self.replace_ops(v, &edges, direction_in) {
+            Some(ops) if !ops.is_empty() => (
+                Transaction::new(&self.inner, ops).execute_with_outcome().0,
+                (),
+            ),
+            Some(_) => (TxnOutcome::Committed, ()),
+            None => (TxnOutcome::Aborted, ()),
+        });
+    }
+
+    /// Reads the edges of `v`, and returns the operations that replace them with
+    /// the sorted `edges`, or `None` if `v` is not in the graph.
+    ///
+    /// Every operation depends on the edges that were read, so they are all derived.
+    fn replace_ops(
+        &self,
+        v: NodeId,
+        edges: &[E],
+        direction_in: bool,
+    ) -> Option<Vec<Operator<'a, usize, E>>> {
+        let current: Vec<NodeId> = {
+            let guard = &epoch::pin();
+            let view = self.read(guard);
+            let found = if direction_in {
+                view.in_edges(v)
+            } else {
+                view.out_edges(v)
+            };
+
+            found?.map(|e| e.node_id).collect()
+        };
+
+        if current.is_empty() && edges.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let mut ops = Vec::new();
+        for e in &current {
+            if edges.binary_search_by_key(e, |edge| edge.node_id).is_err() {
+                ops.push(match (self.directed, direction_in) {
+                    (true, false) => OpType::DeleteArc(v, *e),
+                    (true, true) => OpType::DeleteArc(*e, v),
+                    (false, _) => OpType::DeleteEdge(v, *e, direction_in),
                 });
+            }
         }
 
-        graph.num_nodes = num_nodes;
+        for edge in edges.iter().copied() {
+            let e = edge.node_id;
+            let reverse = EdgeInfo {
+                node_id: v,
+                weight: edge.weight,
+            };
 
-        for (v, e, w) in edge_list {
-            if *v == 0 || *e == 0 {
-                // Our datastructure cannot handle id 0
-                continue;
+            if current.binary_search(&e).is_err() {
+                ops.push(match (self.directed, direction_in) {
+                    (true, false) => OpType::InsertArc(v, e, Some(edge), Some(reverse)),
+                    (true, true) => OpType::InsertArc(e, v, Some(reverse), Some(edge)),
+                    (false, _) => OpType::InsertEdge(v, e, Some(edge), direction_in),
+                });
+            } else {
+                ops.push(OpType::UpdateEdge(v, e, edge, direction_in));
+                if self.directed {
+                    ops.push(OpType::UpdateEdge(e, v, reverse, !direction_in));
+                }
             }
+        }
 
-            let edge_info_ev = EdgeInfo {
-                node_id: *v,
-                weight: w.as_ref().map(|x| *x),
-            };
+        // Fails if an edge was added after the edges were read,
+        // as it is not owned by any of the operations above
+        ops.push(OpType::FindEdges(v, direction_in));
+
+        Some(
+            ops.into_iter()
+                .map(|optype| Operator {
+                    optype,
+                    derived: true,
+                })
+                .collect(),
+        )
+    }
 
-            let edge_info_ve = EdgeInfo {
-                node_id: *e,
-                weight: w.as_ref().map(|x| *x),
-            };
+    /// Makes committed transactions durable in `dir`.
+    ///
+    /// A snapshot of the graph is written first, after which every committed transaction
+    /// is appended to a log, until the next snapshot replaces it.
+    /// A transaction that cannot be appended is aborted with `TxnOutcome::LogFailed`.
+    /// The graph is restored from `dir` with `Graph::recover`.
+    pub fn enable_wal<P: AsRef<Path>>(&mut self, dir: P, config: WalConfig) -> io::Result<()> {
+        self.enable_wal_at(dir.as_ref(), config, 0)
+    }
 
-            graph.num_edges += 1;
+    fn enable_wal_at(&mut self, dir: &Path, config: WalConfig, seq: u64) -> io::Result<()> {
+        let header = wal::header(self.directed, self.num_nodes, self.inner.md_config());
+        let wal = WriteAheadLog::create(
+            dir,
+            config,
+            header,
+            seq,
+            wal::encode_ops,
+            wal::encode_vertices,
+        )?;
+
+        self.inner.enable_wal(wal)
+    }
 
-            if let (Some(en), Some(vn)) = (
-                graph.cache.read().unwrap().get(e),
-                graph.cache.read().unwrap().get(v),
-            ) {
-                Self::connect(en, edge_info_ev, true);
-                Self::connect(vn, edge_info_ve, false);
-            }
+    /// Writes a snapshot and truncates the log, does nothing if the log is disabled
+    pub fn snapshot(&self) -> io::Result<()> {
+        self.inner.snapshot()
+    }
+
+    /// Restores a graph from the snapshot and log in `dir`, and continues logging to it.
+    ///
+    /// The logged transactions are replayed in commit order,
+    /// a record torn by a crash and everything after it is discarded.
+    pub fn recover<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        Self::recover_with_config(dir, WalConfig::default())
+    }
+
+    pub fn recover_with_config<P: AsRef<Path>>(dir: P, config: WalConfig) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let recovered = wal::read_dir(dir)?;
+
+        let mut graph = Graph::with_md_config(
+            recovered.num_nodes as i64,
+            recovered.directed,
+            recovered.md_config,
+        );
+        graph.num_nodes = recovered.num_nodes;
+
+        let vertices = recovered
+            .vertices
+            .into_iter()
+            .map(|(key, value)| OpType::Insert(key, value));
+        let edges = recovered
+            .edges
+            .into_iter()
+            .map(|(v, e, value, direction_in)| OpType::InsertEdge(v, e, value, direction_in));
+
+        for op in vertices.chain(edges) {
+            graph.inner.txn(vec![op]).execute();
         }
 
-        graph
+        for ops in recovered.log {
+            graph.inner.txn(ops).execute();
+        }
+
+        graph.enable_wal_at(dir, config, recovered.seq)?;
+        Ok(graph)
     }
 
-    fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(num_nodes as i64, false);
-        let guard = unsafe { &*(&epoch::pin() as *const _) };
+    /// Builds a graph from `edge_list` without executing any transactions.
+    ///
+    /// The vertex list and the `MDList`s are constructed directly,
+    /// with the edges of each vertex inserted in parallel,
+    /// before the graph is published to other threads.
+    ///
+    /// The vertices are `0..num_nodes`. Edges with an endpoint outside of that range,
+    /// and self-edges, are silently dropped.
+    pub fn bulk_load(num_nodes: usize, edge_list: &EdgeList, directed: bool) -> Self {
+        let md_config = MDListConfig::for_key_range(num_nodes);
+        Self::bulk_load_with_config(num_nodes, edge_list, directed, md_config)
+    }
 
-        for v in 1..num_nodes {
-            let inserted = graph.add_vertex(v, None);
-            graph
-                .cache
-                .write()
-                .expect("Could not write")
-                .insert(v, unsafe {
-                    inserted.unwrap().1.load(SeqCst, guard).deref()
-                });
+    pub fn bulk_load_with_config(
+        num_nodes: usize,
+        edge_list: &EdgeList,
+        directed: bool,
+        md_config: MDListConfig,
+    ) -> Self {
+        Self::bulk_load_edges(num_nodes, edge_list, directed, true, md_config)
+    }
+
+    /// Loads a directed graph that only has the out-edges of its vertices.
+    ///
+    /// Transactions that need the in-edges fail: inserting or deleting arcs,
+    /// updating in-edges, and deleting vertices, whose incoming arcs cannot be found.
+    pub fn bulk_load_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        let md_config = MDListConfig::for_key_range(num_nodes);
+        Self::bulk_load_edges(num_nodes, edge_list, true, false, md_config)
+    }
+
+    fn bulk_load_edges(
+        num_nodes: usize,
+        edge_list: &EdgeList,
+        directed: bool,
+        keep_in_edges: bool,
+        md_config: MDListConfig,
+    ) -> Self {
+        let mut graph = Graph::with_md_config(num_nodes as i64, directed, md_config);
+        if !keep_in_edges {
+            graph.inner.disable_in_edges();
         }
+        let guard = &epoch::pin();
+
+        let keys: Vec<NodeId> = (0..num_nodes).collect();
+        let nodes: Vec<&'a Node<'a, usize, E>> = unsafe {
+            graph
+                .inner
+                .bulk_insert_vertices(&keys, guard)
+                .iter()
+                .map(|node| &*node.as_raw())
+                .collect()
+        };
 
         graph.num_nodes = num_nodes;
 
+        let inverted = directed && keep_in_edges;
+        let mut out_edges = Vec::with_capacity(edge_list.len());
+        let mut in_edges = Vec::with_capacity(if inverted { edge_list.len() } else { 0 });
         for (v, e, w) in edge_list {
-            if *v == 0 || *e == 0 {
-                // Our datastructure cannot handle id 0
+            if *v >= num_nodes || *e >= num_nodes {
+                continue;
+            }
+
+            // Disable self-edges
+            if v == e {
                 continue;
             }
 
             let edge_info_ev = EdgeInfo {
                 node_id: *v,
-                weight: w.as_ref().map(|x| *x),
+                weight: *w,
             };
 
             let edge_info_ve = EdgeInfo {
                 node_id: *e,
-                weight: w.as_ref().map(|x| *x),
+                weight: *w,
             };
 
-            graph.num_edges += 1;
-
-            if let (Some(en), Some(vn)) = (
-                graph.cache.read().unwrap().get(e),
-                graph.cache.read().unwrap().get(v),
-            ) {
-                Self::connect(en, edge_info_ev, false);
-                Self::connect(vn, edge_info_ve, false);
+            out_edges.push((*v, edge_info_ve));
+            if inverted {
+                in_edges.push((*e, edge_info_ev));
+            } else if !directed {
+                out_edges.push((*e, edge_info_ev));
             }
         }
 
+        out_edges.par_sort_unstable_by_key(|(v, _)| *v);
+        in_edges.par_sort_unstable_by_key(|(v, _)| *v);
+
+        graph.connect_sorted(&nodes, &out_edges, false);
+        graph.connect_sorted(&nodes, &in_edges, true);
+
         graph
     }
 
+    /// Connects every run of edges with the same source in parallel,
+    /// `edges` must be sorted by source
+    fn connect_sorted(
+        &self,
+        nodes: &[&Node<'a, usize, E>],
+        edges: &[(NodeId, E)],
+        direction_in: bool,
+    ) {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for i in 1..=edges.len() {
+            if i == edges.len() || edges[i].0 != edges[start].0 {
+                runs.push(&edges[start..i]);
+                start = i;
+            }
+        }
+
+        runs.par_iter().for_each(|run| {
+            let vertex = nodes[run[0].0];
+            for (_, edge_info) in run.iter() {
+                self.connect(vertex, *edge_info, direction_in);
+            }
+        });
+    }
+}
+
+impl<'a> CSRGraph<CustomNode, EdgeInfo> for Graph<'_, usize> {
+    fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Graph::bulk_load(num_nodes, edge_list, true)
+    }
+
+    fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Graph::bulk_load(num_nodes, edge_list, false)
+    }
+
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Graph::bulk_load_without_in_edges(num_nodes, edge_list)
+    }
+
     #[inline]
     fn directed(&self) -> bool {
         self.directed
@@ -305,148 +743,93 @@ impl<'a> CSRGraph<CustomNode, EdgeInfo> for Graph<'_, usize> {
     }
 
     fn num_edges(&self) -> usize {
-        // self.n_edges.get()
-        unimplemented!();
-    }
+        // Duplicate edges are only stored once in an `MDList`,
+        // so the stored edges are counted rather than the edge list
+        let out_edges = self.inner.num_out_edges();
 
-    #[inline]
-    fn num_edges_directed(&self) -> usize {
         if self.directed {
-            self.num_edges
+            out_edges
         } else {
-            self.num_edges * 2
+            out_edges / 2
         }
     }
 
-    fn out_degree(&self, v: NodeId) -> usize {
-        if v == 0 {
-            // Our datastructure cannot handle id 0
-            return 0;
-        }
+    fn num_edges_directed(&self) -> usize {
+        // Both sides of an undirected edge are stored as out-edges
+        self.inner.num_out_edges()
+    }
 
+    fn out_degree(&self, v: NodeId) -> usize {
         let guard = &epoch::pin();
-        if let Some(found) = self.cache.read().unwrap().get(&v) {
-            // self.find_vertex(v) {
-            found.out_edges.as_ref().unwrap().len()
-        // let mut count = 0;
-        // let mut edges = found.list.as_ref().unwrap().iter(guard);
-        // while let Some(edge) = edges.next() {
-        //     if let Some(present_edge) =  edge.value() {
-        //         match present_edge.direction {
-        //             Direction::Out => {
-        //                 count += 1;
-        //             },
-        //             _ => {}
-        //         }
-        //     }
-        // }
-        // count
-        } else {
-            panic!("Vertex not found");
-        }
+        self.read(guard).out_degree(v).expect("Vertex not found")
     }
 
     fn in_degree(&self, v: NodeId) -> usize {
-        if v == 0 {
-            // Our datastructure cannot handle id 0
-            return 0;
+        // An undirected graph only stores its edges as out-edges
+        if !self.directed {
+            return self.out_degree(v);
         }
+
         let guard = &epoch::pin();
-        if let Some(found) = self.cache.read().unwrap().get(&v) {
-            // self.find_vertex(v) {
-            // let mut count = 0;
-            // let guard = &epoch::pin();
-            found.in_edges.as_ref().unwrap().len()
-        // let mut edges = found.list.as_ref().unwrap().iter(guard);
-        // while let Some(edge) = edges.next() {
-        //     if let Some(present_edge) =  edge.value() {
-        //         match present_edge.direction {
-        //             Direction::In => {
-        //                 count += 1;
-        //             },
-        //             _ => {}
-        //         }
-        //     }
-        // }
-        // count
-        } else {
-            panic!("Vertex not found");
-        }
+        self.read(guard).in_degree(v).expect("Vertex not found")
     }
 
     fn out_neigh(&self, v: NodeId) -> Range<E> {
-        if v == 0 || v == 18446744073709551615 {
-            // Our datastructure cannot handle id 0
+        if v == usize::max_value() {
+            // The key of the tail
             return Box::new(Vec::new().into_iter());
         }
 
-        // println!("GETTING OUT NEIGH OF {}", v);
-
-        let guard = unsafe { &*(&epoch::pin() as *const _) };
-        if let Some(found) = self.cache.read().unwrap().get(&v) {
-            // self.find_vertex(v) {
-            let edges = found.out_edges.as_ref().unwrap();
-            let picked_edges = edges.iter(guard).map(|e| *e.value().unwrap());
-            // picked_edges
-            Box::new(picked_edges)
-        } else {
-            panic!("Vertex not found");
-        }
+        Box::new(Pinned::new(self, |view| {
+            view.out_edges(v).expect("Vertex not found").copied()
+        }))
     }
 
     fn in_neigh(&self, v: NodeId) -> Range<E> {
-        if v == 0 {
-            // Our datastructure cannot handle id 0
-            return Box::new(Vec::new().into_iter());
+        if !self.directed {
+            return self.out_neigh(v);
         }
 
-        let guard = unsafe { &*(&epoch::pin() as *const _) };
-        if let Some(found) = self.cache.read().unwrap().get(&v) {
-            // self.find_vertex(v) {
-            let edges = found.in_edges.as_ref().unwrap();
-            let picked_edges = edges.iter(guard).map(|e| *e.value().unwrap());
-            // picked_edges
-            Box::new(picked_edges)
-        } else {
-            panic!("Vertex not found");
+        if !self.inner.keeps_in_edges() {
+            return Box::new(Vec::new().into_iter());
         }
+
+        Box::new(Pinned::new(self, |view| {
+            view.in_edges(v).expect("Vertex not found").copied()
+        }))
     }
 
     fn print_stats(&self) {
+        let txn_stats = self.txn_stats();
         println!("---------- GRAPH ----------");
         println!("  Num Nodes          - {:?}", self.num_nodes());
         println!("  Num Edges          - {:?}", self.num_edges_directed());
+        println!("  Txn Commits        - {:?}", txn_stats.commits);
+        println!("  Txn Aborts         - {:?}", txn_stats.aborts);
+        println!("  Txn Helps          - {:?}", txn_stats.helps);
+        println!("  Txn Retries        - {:?}", txn_stats.retries);
+        if let Some(rebuilds) = self.bloom_filter_rebuilds() {
+            println!("  Filter Rebuilds    - {:?}", rebuilds);
+        }
         println!("---------------------------");
     }
 
+    fn memory_usage(&self) -> MemoryUsage {
+        self.inner.memory_usage(&epoch::pin())
+    }
+
     fn vertices(&self) -> Range<CustomNode> {
-        let guard = unsafe { &*(&epoch::pin() as *const _) };
-        let iter = self.inner.iter(guard).map(|v| CustomNode(v.get().key));
-        Box::new(iter)
+        Box::new(Pinned::new(self, |view| {
+            view.vertices().map(|v| CustomNode(v.key))
+        }))
     }
 
     fn replace_out_edges(&self, v: NodeId, edges: Vec<E>) {
-        // unimplemented!();
-
-        // if let Some(found) = self.get_vertex(v) {
-        //     let mut new_edges = HashSet::new();
-        //     for e in edges {
-        //         new_edges.insert(e);
-        //     }
-        //     self.vertices.borrow_mut().get_mut(found.index).unwrap().out_edges = new_edges;
-        // }
+        self.replace_edges(v, edges, false);
     }
 
     fn replace_in_edges(&self, v: NodeId, edges: Vec<E>) {
-        // unimplemented!();
-
-        // if let Some(found) = self.get_vertex(v) {
-        //     let mut new_edges = HashSet::new();
-        //     for e in edges {
-        //         new_edges.insert(e);
-        //     }
-        //     self.vertices.borrow_mut().get_mut(found.index).unwrap().in_edges = new_edges;
-        // }
+        self.replace_edges(v, edges, true);
     }
 
     fn old_bfs(&self, v: NodeId) {
@@ -459,29 +842,750 @@ impl<'a> CSRGraph<CustomNode, EdgeInfo> for Graph<'_, usize> {
     }
 
     fn op_add_edge(&self, v: NodeId, e: NodeId) {
-        let edge_info = EdgeInfo {
-            node_id: e,
-            weight: None,
+        if self.directed {
+            self.add_arc(v, e, None).ok();
+            return;
+        }
+
+        let edge = |node_id| {
+            Some(EdgeInfo {
+                node_id,
+                weight: None,
+            })
         };
 
-        let op = OpType::InsertEdge(v, e, Some(edge_info), false);
-        let insert_edge_txn = self.inner.txn(vec![op]);
-        insert_edge_txn.execute();
+        // Both sides of an undirected edge are inserted together,
+        // so that deleting either vertex finds the edge
+        let mut ops = vec![OpType::InsertEdge(v, e, edge(e), false)];
+        if v != e {
+            ops.push(OpType::InsertEdge(e, v, edge(v), false));
+        }
+        self.execute_ops(ops);
     }
 
     fn op_delete_edge(&self, v: NodeId, e: NodeId) {
-        self.delete_edge(v, e, false);
+        if self.directed {
+            self.delete_arc(v, e).ok();
+            return;
+        }
+
+        let mut ops = vec![OpType::DeleteEdge(v, e, false)];
+        if v != e {
+            ops.push(OpType::DeleteEdge(e, v, false));
+        }
+        self.execute_ops(ops);
     }
 
     fn op_delete_vertex(&self, v: NodeId) {
         let op = OpType::Delete(v);
-        let find_txn = self.inner.txn(vec![op]);
-        let res = find_txn.execute();
+        self.execute_ops(vec![op]);
     }
 
     fn op_find_vertex(&self, v: NodeId) {
         let op = OpType::Find(v);
-        let find_txn = self.inner.txn(vec![op]);
-        let res = find_txn.execute();
+        self.execute_ops(vec![op]);
+    }
+}
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+    use std::ops::Bound;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn delete_vertex_removes_edges() {
+        let edge_list = vec![(1, 2, None), (2, 3, None), (3, 1, None), (4, 2, None)];
+
+        for directed in &[true, false] {
+            let graph: Graph<usize> = Graph::bulk_load(5, &edge_list, *directed);
+            graph.op_delete_vertex(2);
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            for v in &[1, 3, 4] {
+                assert!(view.out_edges(*v).unwrap().all(|e| e.node_id != 2));
+                assert!(view.in_edges(*v).unwrap().all(|e| e.node_id != 2));
+            }
+
+            assert_eq!(view.out_degree(1), Some(if *directed { 0 } else { 1 }));
+            assert_eq!(view.out_degree(4), Some(0));
+        }
+    }
+
+    #[test]
+    fn deleted_vertices_are_never_observed_as_neighbours() {
+        const NUM_NODES: usize = 200;
+
+        let edge_list: EdgeList = (1..NUM_NODES)
+            .flat_map(|v| (1..8).map(move |i| (v, (v * i * 31) % (NUM_NODES - 1) + 1, None)))
+            .collect();
+        let graph: Graph<usize> = Graph::bulk_load(NUM_NODES, &edge_list, true);
+        let deleted: Vec<AtomicBool> = (0..NUM_NODES).map(|_| AtomicBool::new(false)).collect();
+
+        crossbeam_utils::thread::scope(|s| {
+            s.spawn(|_| {
+                for v in (1..NUM_NODES).step_by(3) {
+                    graph.op_delete_vertex(v);
+                    deleted[v].store(true, SeqCst);
+                }
+            });
+
+            for _ in 0..4 {
+                s.spawn(|_| {
+                    for _ in 0..20 {
+                        for v in 1..NUM_NODES {
+                            let observed: Vec<bool> =
+                                deleted.iter().map(|d| d.load(SeqCst)).collect();
+
+                            let guard = &epoch::pin();
+                            let view = graph.read(guard);
+                            let edges = view.out_edges(v).into_iter().flatten();
+                            for e in edges.chain(view.in_edges(v).into_iter().flatten()) {
+                                assert!(!observed[e.node_id], "{} -> {}", v, e.node_id);
+                            }
+                        }
+                    }
+                });
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn reinserted_vertex_has_no_edges() {
+        let edge_list = vec![(1, 2, None), (2, 3, None), (3, 2, None)];
+
+        for directed in &[true, false] {
+            let graph: Graph<usize> = Graph::bulk_load(4, &edge_list, *directed);
+            graph.op_delete_vertex(2);
+            graph.op_add_vertex(2);
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            assert_eq!(view.out_degree(2), Some(0));
+            assert_eq!(view.in_degree(2), Some(0));
+            for v in &[1, 3] {
+                assert!(!view.has_out_edge(*v, 2));
+                assert!(!view.has_in_edge(*v, 2));
+            }
+        }
+    }
+
+    #[test]
+    fn edges_added_during_a_delete_are_deleted_with_it() {
+        const NUM_NODES: usize = 64;
+
+        for directed in &[true, false] {
+            let mut graph: Graph<usize> = Graph::bulk_load(NUM_NODES, &Vec::new(), *directed);
+            graph.set_retry_policy(RetryPolicy::new(1000));
+
+            crossbeam_utils::thread::scope(|s| {
+                s.spawn(|_| {
+                    for v in (1..NUM_NODES).step_by(2) {
+                        graph.op_delete_vertex(v);
+                    }
+                });
+
+                for t in 0..4 {
+                    let graph = &graph;
+                    s.spawn(move |_| {
+                        for i in 0..NUM_NODES * 4 {
+                            let v = (i * 7 + t) % (NUM_NODES - 1) + 1;
+                            let e = (i * 13 + t * 5) % (NUM_NODES - 1) + 1;
+                            graph.op_add_edge(v, e);
+                        }
+                    });
+                }
+            })
+            .unwrap();
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            let present = |e: &EdgeInfo| view.vertex(e.node_id).is_some();
+            for v in view.vertices() {
+                for e in view.out_edges(v.key).into_iter().flatten() {
+                    assert!(present(e), "{} -> {}", v.key, e.node_id);
+                }
+                for e in view.in_edges(v.key).into_iter().flatten() {
+                    assert!(present(e), "{} <- {}", v.key, e.node_id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degrees_match_the_edges() {
+        const NUM_NODES: usize = 32;
+
+        let edge_list: EdgeList = (1..NUM_NODES)
+            .flat_map(|v| (1..4).map(move |i| (v, (v * i * 7) % (NUM_NODES - 1) + 1, None)))
+            .collect();
+
+        for directed in &[true, false] {
+            let graph: Graph<usize> = Graph::bulk_load(NUM_NODES, &edge_list, *directed);
+
+            crossbeam_utils::thread::scope(|s| {
+                for t in 0..4 {
+                    let graph = &graph;
+                    s.spawn(move |_| {
+                        for i in 0..NUM_NODES * 8 {
+                            let v = (i * 5 + t) % (NUM_NODES - 1) + 1;
+                            let e = (i * 11 + t * 3) % (NUM_NODES - 1) + 1;
+                            match i % 8 {
+                                0 => graph.op_delete_vertex(v),
+                                1 => graph.op_add_vertex(v),
+                                2 | 3 | 4 => graph.op_delete_edge(v, e),
+                                _ => graph.op_add_edge(v, e),
+                            }
+                        }
+                    });
+                }
+            })
+            .unwrap();
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            for v in view.vertices() {
+                let out_edges = view.out_edges(v.key).unwrap().count();
+                let in_edges = view.in_edges(v.key).unwrap().count();
+                assert_eq!(view.out_degree(v.key), Some(out_edges), "{}", v.key);
+                assert_eq!(view.in_degree(v.key), Some(in_edges), "{}", v.key);
+            }
+
+            let out_edges: usize = view
+                .vertices()
+                .map(|v| view.out_edges(v.key).unwrap().count())
+                .sum();
+            assert_eq!(graph.num_edges_directed(), out_edges);
+        }
+    }
+
+    fn weighted(node_id: NodeId, weight: usize) -> EdgeInfo {
+        EdgeInfo {
+            node_id,
+            weight: Some(weight),
+        }
+    }
+
+    fn weights(edges: Option<EdgeIter<usize>>) -> Vec<(NodeId, Option<usize>)> {
+        edges
+            .into_iter()
+            .flatten()
+            .map(|e| (e.node_id, e.weight))
+            .collect()
+    }
+
+    #[test]
+    fn graph_without_in_edges_rejects_their_updates() {
+        let edge_list = vec![(1, 2, Some(12)), (2, 3, Some(23)), (3, 1, Some(31))];
+        let graph: Graph<usize> = Graph::bulk_load_without_in_edges(4, &edge_list);
+
+        assert!(graph.add_arc(1, 3, None).is_err());
+        assert!(graph.delete_arc(1, 2).is_err());
+        assert!(graph.delete_vertex(2).is_none());
+        assert!(graph.delete_edge(2, 1, true).is_err());
+
+        // The out-edges can still be updated
+        graph.add_edge(
+            1,
+            EdgeInfo {
+                node_id: 3,
+                weight: Some(13),
+            },
+            false,
+        );
+
+        let guard = &epoch::pin();
+        let view = graph.read(guard);
+        assert!(view.vertex(2).is_some());
+        assert!(view.in_edges(2).is_none());
+        assert!(!view.has_in_edge(2, 1));
+        assert_eq!(
+            weights(view.out_edges(1)),
+            vec![(2, Some(12)), (3, Some(13))]
+        );
+        assert_eq!(weights(view.out_edges(2)), vec![(3, Some(23))]);
+        assert_eq!(view.in_degree(3), Some(0));
+    }
+
+    #[test]
+    fn replace_edges_updates_kept_weights() {
+        let edge_list = vec![(1, 2, Some(2)), (1, 3, Some(3)), (4, 3, Some(4))];
+
+        for directed in &[true, false] {
+            let graph: Graph<usize> = Graph::bulk_load(6, &edge_list, *directed);
+            graph.replace_edges(1, vec![weighted(5, 50), weighted(3, 30)], false);
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            assert_eq!(
+                weights(view.out_edges(1)),
+                vec![(3, Some(30)), (5, Some(50))]
+            );
+            assert_eq!(view.out_degree(1), Some(2));
+            if *directed {
+                assert_eq!(weights(view.in_edges(3)), vec![(1, Some(30)), (4, Some(4))]);
+                assert_eq!(weights(view.in_edges(5)), vec![(1, Some(50))]);
+                assert_eq!(weights(view.in_edges(2)), vec![]);
+            }
+        }
+    }
+
+    #[test]
+    fn aborted_weight_updates_are_undone() {
+        let graph: Graph<usize> = Graph::bulk_load(4, &vec![(1, 2, Some(2))], true);
+
+        let ops = vec![
+            OpType::UpdateEdge(1, 2, weighted(2, 20), false),
+            OpType::UpdateEdge(2, 1, weighted(1, 20), true),
+            OpType::Find(7),
+        ];
+        assert_eq!(graph.execute_ops_with_outcome(ops).0, TxnOutcome::Aborted);
+
+        // A later operation on the edge carries the weight over
+        graph.add_arc(1, 3, None).unwrap();
+        graph.replace_edges(1, vec![weighted(2, 2)], false);
+
+        let guard = &epoch::pin();
+        let view = graph.read(guard);
+        assert_eq!(weights(view.out_edges(1)), vec![(2, Some(2))]);
+        assert_eq!(weights(view.in_edges(2)), vec![(1, Some(2))]);
+
+        let ops = vec![OpType::UpdateEdge(1, 3, weighted(3, 3), false)];
+        assert_eq!(graph.execute_ops_with_outcome(ops).0, TxnOutcome::Aborted);
+    }
+
+    #[test]
+    fn replace_edges_conflicts_with_edges_added_after_the_read() {
+        for reinserted in &[false, true] {
+            let graph: Graph<usize> = Graph::bulk_load(6, &vec![(1, 2, None), (1, 4, None)], true);
+            if *reinserted {
+                graph.delete_arc(1, 4).unwrap();
+            }
+
+            let ops = graph.replace_ops(1, &[weighted(3, 3)], false).unwrap();
+            // Reuses the node of the deleted edge
+            graph
+                .add_arc(1, if *reinserted { 4 } else { 5 }, None)
+                .unwrap();
+
+            let outcome = Transaction::new(&graph.inner, ops).execute_with_outcome().0;
+            assert_eq!(outcome, TxnOutcome::Conflicted);
+
+            graph.replace_edges(1, vec![weighted(3, 3)], false);
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            assert_eq!(weights(view.out_edges(1)), vec![(3, Some(3))]);
+            for e in 2..6 {
+                assert_eq!(view.has_in_edge(e, 1), e == 3, "{}", e);
+            }
+        }
+    }
+
+    #[test]
+    fn concurrent_replaces_keep_both_sides_of_an_edge() {
+        const NUM_NODES: usize = 32;
+
+        let mut graph: Graph<usize> = Graph::bulk_load(NUM_NODES, &Vec::new(), true);
+        graph.set_retry_policy(RetryPolicy::new(1000));
+
+        crossbeam_utils::thread::scope(|s| {
+            for t in 0..2 {
+                let graph = &graph;
+                s.spawn(move |_| {
+                    for i in 0..NUM_NODES * 4 {
+                        let edges = (2..NUM_NODES)
+                            .filter(|e| (e + i + t) % 3 == 0)
+                            .map(|e| weighted(e, i))
+                            .collect();
+                        graph.replace_edges(1, edges, false);
+                    }
+                });
+            }
+
+            let graph = &graph;
+            s.spawn(move |_| {
+                for i in 0..NUM_NODES * 4 {
+                    let e = i % (NUM_NODES - 2) + 2;
+                    if i % 2 == 0 {
+                        graph.add_arc(1, e, Some(e)).ok();
+                    } else {
+                        graph.delete_arc(1, e).ok();
+                    }
+                }
+            });
+        })
+        .unwrap();
+
+        let guard = &epoch::pin();
+        let view = graph.read(guard);
+        let out = weights(view.out_edges(1));
+        assert_eq!(view.out_degree(1), Some(out.len()));
+        for e in 2..NUM_NODES {
+            let weight = out.iter().find(|(x, _)| *x == e).map(|(_, w)| *w);
+            let reverse = weights(view.in_edges(e)).first().map(|(_, w)| *w);
+            assert_eq!(weight, reverse, "{}", e);
+        }
+    }
+
+    #[test]
+    fn edges_owned_by_an_aborted_transaction_are_kept() {
+        let edge_list = vec![(1, 2, None), (1, 4, None), (1, 6, None), (1, 8, None)];
+        let graph: Graph<usize> = Graph::bulk_load(10, &edge_list, true);
+
+        // The inserted edges use the deleted ones as their preds
+        let mut ops: Vec<_> = (1..5)
+            .map(|i| OpType::DeleteEdge(1, i * 2, false))
+            .collect();
+        ops.extend((1..5).map(|i| OpType::InsertEdge(1, i * 2 + 1, None, false)));
+        ops.push(OpType::Find(11));
+        assert_eq!(graph.execute_ops_with_outcome(ops).0, TxnOutcome::Aborted);
+
+        let guard = &epoch::pin();
+        let view = graph.read(guard);
+        let targets: Vec<_> = view.out_edges(1).unwrap().map(|e| e.node_id).collect();
+        assert_eq!(targets, vec![2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn kernels_match_rc() {
+        use crate::graphmodels::rc;
+
+        const NUM_NODES: usize = 64;
+        // Every undirected edge once, without self-edges
+        let edges = |seed: usize| -> EdgeList {
+            let mut edges: Vec<_> = (0..NUM_NODES * 4)
+                .map(|i| {
+                    let v = (i * 31 + seed) % NUM_NODES;
+                    let e = (i * i * 17 + seed * 7) % NUM_NODES;
+                    (v.min(e), v.max(e), None)
+                })
+                .filter(|(v, e, _)| v != e)
+                .collect();
+            edges.sort_by_key(|(v, e, _)| (*v, *e));
+            edges.dedup_by_key(|(v, e, _)| (*v, *e));
+            edges
+        };
+
+        // The epoch graph gets its final edges by replacing those it was built with
+        let (built, replaced) = (edges(3), edges(5));
+        let graph: Graph<usize> = CSRGraph::build_undirected(NUM_NODES, &built);
+        let expected: rc::Graph<usize> = CSRGraph::build_undirected(NUM_NODES, &replaced);
+        for v in 0..NUM_NODES {
+            let neighbours = replaced
+                .iter()
+                .filter_map(|(a, b, _)| match v {
+                    _ if *a == v => Some(*b),
+                    _ if *b == v => Some(*a),
+                    _ => None,
+                })
+                .map(|e| EdgeInfo {
+                    node_id: e,
+                    weight: None,
+                })
+                .collect();
+            graph.replace_out_edges(v, neighbours);
+        }
+
+        let partition = |comp: Vec<NodeId>| {
+            let mut labels = std::collections::HashMap::new();
+            comp.iter()
+                .map(|c| {
+                    let next = labels.len();
+                    *labels.entry(*c).or_insert(next)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            crate::tc::ordered_count(&graph),
+            crate::tc::ordered_count(&expected)
+        );
+        assert_eq!(
+            crate::bfs::do_bfs(&graph, 1),
+            crate::bfs::do_bfs(&expected, 1)
+        );
+        assert_eq!(
+            partition(crate::cc::afforest(&graph, None)),
+            partition(crate::cc::afforest(&expected, None))
+        );
+    }
+
+    #[test]
+    fn vertex_range_bounds() {
+        use std::ops::Bound::*;
+
+        let graph: Graph<usize> = Graph::bulk_load(10, &Vec::new(), true);
+        graph.op_delete_vertex(5);
+
+        let guard = &epoch::pin();
+        let keys = |range: (Bound<usize>, Bound<usize>)| -> Vec<NodeId> {
+            graph.vertex_range(range, guard).map(|v| v.key).collect()
+        };
+
+        assert_eq!(keys((Included(3), Excluded(7))), vec![3, 4, 6]);
+        assert_eq!(keys((Excluded(3), Included(7))), vec![4, 6, 7]);
+        assert_eq!(keys((Unbounded, Excluded(3))), vec![0, 1, 2]);
+        assert_eq!(keys((Included(8), Unbounded)), vec![8, 9]);
+        assert!(keys((Included(4), Excluded(4))).is_empty());
+        assert!(keys((Excluded(9), Unbounded)).is_empty());
+        assert!(keys((Included(usize::max_value()), Unbounded)).is_empty());
+
+        let key = |entry: Option<RefEntry<usize, E>>| entry.map(|v| v.key);
+        assert_eq!(key(graph.successor(4, guard)), Some(6));
+        assert_eq!(key(graph.successor(9, guard)), None);
+        assert_eq!(key(graph.successor(usize::max_value(), guard)), None);
+        assert_eq!(key(graph.predecessor(6, guard)), Some(4));
+        assert_eq!(key(graph.predecessor(1, guard)), Some(0));
+        assert_eq!(key(graph.predecessor(0, guard)), None);
+    }
+
+    #[test]
+    fn bulk_load_drops_out_of_range_edges() {
+        let edge_list = vec![
+            (0, 1, None),
+            (1, 2, Some(3)),
+            (2, 2, None),
+            (2, 3, None),
+            (3, 4, None),
+            (usize::max_value(), 1, None),
+        ];
+
+        for directed in &[true, false] {
+            let graph: Graph<usize> = Graph::bulk_load(4, &edge_list, *directed);
+
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            let vertices: Vec<NodeId> = view.vertices().map(|v| v.key).collect();
+            assert_eq!(vertices, vec![0, 1, 2, 3]);
+
+            let out_edges = |v| -> Vec<(NodeId, Option<Weight>)> {
+                let edges = view.out_edges(v).unwrap();
+                edges.map(|e| (e.node_id, e.weight)).collect()
+            };
+            assert_eq!(out_edges(0), vec![(1, None)]);
+            if *directed {
+                assert_eq!(out_edges(1), vec![(2, Some(3))]);
+                assert_eq!(out_edges(2), vec![(3, None)]);
+                assert_eq!(out_edges(3), vec![]);
+            } else {
+                assert_eq!(out_edges(1), vec![(0, None), (2, Some(3))]);
+                assert_eq!(out_edges(2), vec![(1, Some(3)), (3, None)]);
+                assert_eq!(out_edges(3), vec![(2, None)]);
+            }
+        }
+    }
+
+    #[test]
+    fn bloom_filter_is_rebuilt_without_false_negatives() {
+        const NUM_NODES: usize = 512;
+
+        let mut graph: Graph<usize> = Graph::bulk_load(NUM_NODES, &Vec::new(), true);
+        graph.enable_bloom_filter(0.01);
+
+        // Vertices are inserted above the bulk loaded ones while the others are deleted,
+        // each inserted vertex must be found as soon as its transaction returns
+        crossbeam_utils::thread::scope(|s| {
+            s.spawn(|_| {
+                for v in 0..NUM_NODES {
+                    graph.op_delete_vertex(v);
+                }
+            });
+
+            s.spawn(|_| {
+                for v in NUM_NODES..2 * NUM_NODES {
+                    graph.add_vertex(v, None);
+                    let found = graph.execute_ops_with_outcome(vec![OpType::Find(v)]).0;
+                    assert_eq!(found, TxnOutcome::Committed, "{}", v);
+                }
+            });
+        })
+        .unwrap();
+
+        assert!(graph.bloom_filter_rebuilds().unwrap() > 0);
+
+        let guard = &epoch::pin();
+        let view = graph.read(guard);
+        let vertices: Vec<NodeId> = view.vertices().map(|v| v.key).collect();
+        assert_eq!(vertices, (NUM_NODES..2 * NUM_NODES).collect::<Vec<_>>());
+        assert!((0..NUM_NODES).all(|v| view.vertex(v).is_none()));
+
+        // Deleted vertices are found again once they are inserted
+        for v in 0..NUM_NODES {
+            graph.add_vertex(v, None);
+        }
+        assert!((0..2 * NUM_NODES).all(|v| view.vertex(v).is_some()));
+    }
+
+    const WAL_WRITER: &str = "GAPBS_WAL_WRITER";
+    const WAL_READY: &str = "wal writer ready";
+    const WAL_VERTICES: usize = 16;
+    const WAL_SNAPSHOT_INTERVAL: usize = 64;
+
+    /// Commits transactions to the log in `dir` until the process is killed.
+    ///
+    /// Vertex `v` is inserted in one transaction,
+    /// and connected to vertex 1 in both directions in the next.
+    /// Once a periodic snapshot has been written, and transactions have been
+    /// logged after it, `WAL_READY` is written to stdout.
+    fn wal_writer(dir: &Path) {
+        let mut graph: Graph<usize> = Graph::bulk_load(WAL_VERTICES, &Vec::new(), true);
+        let config = WalConfig {
+            snapshot_interval: WAL_SNAPSHOT_INTERVAL,
+            sync: false,
+        };
+        graph.enable_wal(dir, config).unwrap();
+
+        for v in WAL_VERTICES.. {
+            let edge = |node_id| {
+                Some(EdgeInfo {
+                    node_id,
+                    weight: Some(v),
+                })
+            };
+
+            graph.execute_ops(vec![OpType::Insert(v, Some(v))]);
+            graph.execute_ops(vec![
+                OpType::InsertArc(v, 1, edge(1), edge(v)),
+                OpType::InsertArc(1, v, edge(v), edge(1)),
+            ]);
+
+            // Written directly, as the test harness captures `println!`
+            if v == WAL_VERTICES + WAL_SNAPSHOT_INTERVAL {
+                let mut stdout = std::io::stdout();
+                writeln!(stdout, "{}", WAL_READY).unwrap();
+                stdout.flush().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn transactions_that_cannot_be_logged_are_aborted() {
+        let dir = std::env::temp_dir().join(format!("gapbs-wal-full-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut graph: Graph<usize> = Graph::bulk_load(3, &Vec::new(), true);
+        graph.enable_wal(&dir, WalConfig::default()).unwrap();
+
+        // Every write to the log fails once the next snapshot opens it again
+        std::fs::remove_file(dir.join("wal.log")).unwrap();
+        std::os::unix::fs::symlink("/dev/full", dir.join("wal.log")).unwrap();
+        graph.snapshot().unwrap();
+
+        let outcome = graph
+            .execute_ops_with_outcome(vec![OpType::Insert(5, Some(5))])
+            .0;
+        assert!(matches!(outcome, TxnOutcome::LogFailed(_)), "{:?}", outcome);
+        assert!(graph.read(&epoch::pin()).vertex(5).is_none());
+
+        std::fs::remove_file(dir.join("wal.log")).unwrap();
+        graph.snapshot().unwrap();
+        let outcome = graph
+            .execute_ops_with_outcome(vec![OpType::Insert(5, Some(5))])
+            .0;
+        assert_eq!(outcome, TxnOutcome::Committed);
+        drop(graph);
+
+        let graph: Graph<usize> = Graph::recover(&dir).unwrap();
+        assert_eq!(
+            graph.read(&epoch::pin()).vertex(5).unwrap().value(),
+            Some(5)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn connect_is_rejected_in_transactions() {
+        // The entry is never dereferenced, as the operation fails
+        let entry = RefEntry {
+            node: Shared::null(),
+        };
+        let graph: Graph<usize> = Graph::bulk_load(3, &Vec::new(), true);
+
+        let ops = vec![OpType::Connect(&entry, 2, weighted(2, 2))];
+        let (outcome, res) = graph.execute_ops_with_outcome(ops);
+        assert_eq!(outcome, TxnOutcome::Aborted);
+        assert!(matches!(res.recv().unwrap(), ReturnCode::Fail(_)));
+        assert!(!graph.read(&epoch::pin()).has_out_edge(1, 2));
+    }
+
+    #[test]
+    fn recover_after_killed_writer() {
+        if let Some(dir) = std::env::var_os(WAL_WRITER) {
+            wal_writer(Path::new(&dir));
+            return;
+        }
+
+        let dir = std::env::temp_dir().join(format!("gapbs-wal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut writer = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(&[
+                "graphmodels::epoch::tests::recover_after_killed_writer",
+                "--exact",
+            ])
+            .env(WAL_WRITER, &dir)
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // The harness of the writer prints the name of the test on the same line
+        let stdout = std::io::BufReader::new(writer.stdout.take().unwrap());
+        let ready = stdout
+            .lines()
+            .any(|line| line.map_or(false, |line| line.ends_with(WAL_READY)));
+        writer.kill().unwrap();
+        writer.wait().unwrap();
+        assert!(ready, "The writer exited before it was ready");
+
+        // A record torn by the crash
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join("wal.log"))
+            .unwrap()
+            .write_all(b"999999 0123456789abcdef i 3")
+            .unwrap();
+
+        let graph: Graph<usize> = Graph::recover(&dir).unwrap();
+        let last = {
+            let guard = &epoch::pin();
+            let view = graph.read(guard);
+            let keys: Vec<NodeId> = view.vertices().map(|v| v.key).collect();
+            let last = *keys.last().unwrap();
+            assert!(
+                last >= WAL_VERTICES + WAL_SNAPSHOT_INTERVAL,
+                "Transactions committed before the writer was ready were lost"
+            );
+            assert_eq!(keys, (0..=last).collect::<Vec<_>>());
+
+            for v in WAL_VERTICES..=last {
+                assert_eq!(view.vertex(v).unwrap().value(), Some(v));
+
+                let arcs = [
+                    view.has_out_edge(v, 1),
+                    view.in_edges(1).unwrap().any(|e| e.node_id == v),
+                    view.has_out_edge(1, v),
+                    view.in_edges(v).unwrap().any(|e| e.node_id == 1),
+                ];
+                assert!(arcs.iter().all(|a| *a == arcs[0]), "{} {:?}", v, arcs);
+                assert!(arcs[0] || v == last, "{} is not connected", v);
+            }
+
+            last
+        };
+
+        // The recovered graph keeps logging after the recovered records
+        graph.execute_ops(vec![OpType::Insert(last + 1, Some(0))]);
+        drop(graph);
+
+        let graph: Graph<usize> = Graph::recover(&dir).unwrap();
+        let guard = &epoch::pin();
+        assert_eq!(graph.read(guard).vertex(last + 1).unwrap().value(), Some(0));
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,68 @@
+//! Synchronization primitives used by the lock-free adjacency list.
+//!
+//! When built with `--cfg crossbeam_loom`, they are replaced by primitives of `loom`,
+//! so that the interleavings of small scenarios can be explored exhaustively.
+
+use std::time::Duration;
+
+#[cfg(not(crossbeam_loom))]
+pub use crossbeam_utils::atomic::AtomicCell;
+
+#[cfg(crossbeam_loom)]
+pub use self::model::AtomicCell;
+
+/// Waits before a conflicted transaction is retried
+pub fn backoff(duration: Duration) {
+    #[cfg(not(crossbeam_loom))]
+    std::thread::sleep(duration);
+
+    // The model checker explores the other threads instead
+    #[cfg(crossbeam_loom)]
+    {
+        let _ = duration;
+        loom::thread::yield_now();
+    }
+}
+
+#[cfg(crossbeam_loom)]
+mod model {
+    use loom::sync::Mutex;
+
+    /// An `AtomicCell` that is visible to the model checker.
+    ///
+    /// `crossbeam_utils`'s `AtomicCell` only supports values that fit in a primitive atomic
+    /// under loom, which a vertex value (`Option<T>`) does not.
+    pub struct AtomicCell<T> {
+        value: Mutex<T>,
+    }
+
+    impl<T> AtomicCell<T> {
+        pub fn new(value: T) -> Self {
+            Self {
+                value: Mutex::new(value),
+            }
+        }
+
+        pub fn store(&self, value: T) {
+            *self.value.lock().unwrap() = value;
+        }
+    }
+
+    impl<T: Copy> AtomicCell<T> {
+        pub fn load(&self) -> T {
+            *self.value.lock().unwrap()
+        }
+    }
+
+    impl<T: Copy + Eq> AtomicCell<T> {
+        pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+            let mut value = self.value.lock().unwrap();
+            if *value == current {
+                *value = new;
+                Ok(current)
+            } else {
+                Err(*value)
+            }
+        }
+    }
+}
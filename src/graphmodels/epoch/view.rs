@@ -0,0 +1,181 @@
+use crate::graphmodels::epoch::adjlist::{AdjacencyList, Node, RangeRefEntry};
+use crate::graphmodels::epoch::mdlist;
+use crate::graphmodels::epoch::{EdgeInfo, Graph};
+use crate::types::*;
+use epoch::Guard;
+
+type E = EdgeInfo;
+
+/// A read-only view of an epoch graph.
+///
+/// Everything borrowed from the view lives as long as the `Guard`
+/// it was created with, so the compiler prevents references into the
+/// graph from outliving the pinned epoch.
+///
+/// ```ignore
+/// let guard = &epoch::pin();
+/// let view = graph.read(guard);
+/// for v in view.vertices() {
+///     for e in view.out_edges(v.key).into_iter().flatten() {
+///         // ...
+///     }
+/// }
+/// ```
+pub struct ReadView<'a: 'g, 'g, T: 'a + Copy + Clone + Into<usize>> {
+    graph: &'g Graph<'a, T>,
+    guard: &'g Guard,
+}
+
+/// Iterates the edges stored in a vertex's `MDList` in ascending order,
+/// skipping edges that have been deleted by a committed transaction.
+///
+/// Edges to a vertex are deleted by the transaction that deletes the vertex.
+pub struct EdgeIter<'a: 'g, 'g, T: 'a> {
+    inner: mdlist::Iter<'a, 'g, 'g, E, T>,
+    guard: &'g Guard,
+}
+
+impl<'a: 'g, 'g, T: 'a + Clone> Iterator for EdgeIter<'a, 'g, T> {
+    type Item = &'g E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.inner.next() {
+            if !unsafe { AdjacencyList::<T, E>::is_edge_present(entry.node, self.guard) } {
+                continue;
+            }
+
+            if let Some(edge) = unsafe { AdjacencyList::<T, E>::edge_value(entry.node, self.guard) }
+            {
+                return Some(edge);
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a: 'g, 'g, T: 'a + Copy + Clone + Into<usize>> ReadView<'a, 'g, T> {
+    pub(crate) fn new(graph: &'g Graph<'a, T>, guard: &'g Guard) -> Self {
+        Self { graph, guard }
+    }
+
+    pub fn guard(&self) -> &'g Guard {
+        self.guard
+    }
+
+    /// Iterates all vertices that are logically in the graph, in ascending key order
+    pub fn vertices(&self) -> RangeRefEntry<'a, 'g, 'g, T, E> {
+        self.graph.inner.range(.., self.guard)
+    }
+
+    /// Returns `None` if `v` is not logically in the graph
+    pub fn vertex(&self, v: NodeId) -> Option<&'g Node<'a, T, E>> {
+        self.graph
+            .inner
+            .get_vertex(v, self.guard)
+            .map(|found| unsafe { found.deref() })
+    }
+
+    /// Returns `None` if `v` is not in the graph
+    pub fn out_edges(&self, v: NodeId) -> Option<EdgeIter<'a, 'g, T>> {
+        self.vertex(v).map(|found| EdgeIter {
+            inner: found
+                .out_edges
+                .as_ref()
+                .expect("NO MD LIST")
+                .iter(self.guard),
+            guard: self.guard,
+        })
+    }
+
+    /// Returns `None` if `v` is not in the graph, or the graph was built without in-edges
+    pub fn in_edges(&self, v: NodeId) -> Option<EdgeIter<'a, 'g, T>> {
+        self.vertex(v)
+            .and_then(|found| found.in_edges.as_ref())
+            .map(|edges| EdgeIter {
+                inner: edges.iter(self.guard),
+                guard: self.guard,
+            })
+    }
+
+    /// Checks if `e` is in the out-edges of `v`
+    pub fn has_out_edge(&self, v: NodeId, e: NodeId) -> bool {
+        self.vertex(v)
+            .map_or(false, |found| self.has_edge(&found.out_edges, e))
+    }
+
+    /// Checks if `e` is in the in-edges of `v`
+    pub fn has_in_edge(&self, v: NodeId, e: NodeId) -> bool {
+        self.vertex(v)
+            .map_or(false, |found| self.has_edge(&found.in_edges, e))
+    }
+
+    fn has_edge(&self, edges: &'g Option<mdlist::MDList<'a, E, T>>, e: NodeId) -> bool {
+        unsafe {
+            // A graph built without in-edges has no `MDList` for them
+            let edges = match edges {
+                Some(edges) => edges,
+                None => return false,
+            };
+
+            match edges.get(e, self.guard) {
+                Ok(entry) => {
+                    entry.value().is_some()
+                        && AdjacencyList::<T, E>::is_edge_present(entry.node, self.guard)
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// Returns `None` if `v` is not in the graph.
+    ///
+    /// Degrees are maintained as transactions commit, rather than counted,
+    /// so they can lag behind a concurrent transaction.
+    pub fn out_degree(&self, v: NodeId) -> Option<usize> {
+        self.vertex(v).map(|found| found.degree(false))
+    }
+
+    /// Returns `None` if `v` is not in the graph
+    pub fn in_degree(&self, v: NodeId) -> Option<usize> {
+        self.vertex(v).map(|found| found.degree(true))
+    }
+}
+
+/// An iterator over the graph that keeps its own epoch pinned,
+/// for callers that cannot pass in a `Guard`, e.g. through `CSRGraph`.
+///
+/// Nothing is reclaimed while it is alive, so it should not be held on to.
+pub(crate) struct Pinned<I> {
+    // Declared first, so that it is dropped before the guard it borrows
+    inner: I,
+    _guard: Box<Guard>,
+}
+
+impl<I> Pinned<I> {
+    /// Pins the epoch and creates the iterator from a view of `graph`
+    pub(crate) fn new<'a: 'g, 'g, T, F>(graph: &'g Graph<'a, T>, f: F) -> Self
+    where
+        T: 'a + Copy + Clone + Into<usize>,
+        F: FnOnce(ReadView<'a, 'g, T>) -> I,
+        I: 'g,
+    {
+        let guard = Box::new(epoch::pin());
+        // The guard is boxed, so it stays in place when `Pinned` is moved,
+        // and it outlives `inner`, which cannot outlive `graph`
+        let view = ReadView::new(graph, unsafe { &*(&*guard as *const Guard) });
+
+        Self {
+            inner: f(view),
+            _guard: guard,
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for Pinned<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
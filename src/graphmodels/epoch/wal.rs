@@ -0,0 +1,501 @@
+use crate::graphmodels::epoch::adjlist::AdjacencyList;
+use crate::graphmodels::epoch::lftt::{OpType, Operator};
+use crate::graphmodels::epoch::mdlist::MDListConfig;
+use crate::graphmodels::epoch::EdgeInfo;
+use crate::types::*;
+use epoch::Guard;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, prelude::*, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+const LOG_FILE: &str = "wal.log";
+const SNAPSHOT_MAGIC: &str = "gapbs-epoch-snapshot 1";
+
+#[derive(Clone, Copy, Debug)]
+pub struct WalConfig {
+    /// Number of logged transactions between two snapshots,
+    /// periodic snapshots are disabled if it is `0`
+    pub snapshot_interval: usize,
+    /// Calls `fsync` after every append, so that committed transactions
+    /// survive a crash of the machine, and not only of the process
+    pub sync: bool,
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_interval: 10_000,
+            sync: false,
+        }
+    }
+}
+
+/// The open log file, only accessed while commits are blocked
+pub(crate) struct LogState {
+    log: File,
+    /// The length of the log up to the end of the last record
+    len: u64,
+    seq: u64,
+    since_snapshot: usize,
+    /// Set when a torn or aborted record could not be removed,
+    /// after which nothing is appended until the next snapshot replaces the log
+    broken: bool,
+}
+
+/// Appends the operations of committed transactions to `dir/wal.log`,
+/// and periodically replaces the log with a snapshot in `dir/snapshot`.
+///
+/// Every record is prefixed with its sequence number and a checksum,
+/// so that a record torn by a crash is detected, and everything after it is ignored.
+/// Records with a sequence number covered by the snapshot are skipped,
+/// in case the process crashed after writing the snapshot but before truncating the log.
+pub(crate) struct WriteAheadLog<'a, T, E> {
+    dir: PathBuf,
+    config: WalConfig,
+    header: String,
+    state: Mutex<LogState>,
+    encode_ops: fn(&[Operator<'a, T, E>], &mut String),
+    encode_vertices: fn(&AdjacencyList<'a, T, E>, &Guard, &mut String),
+}
+
+impl<'a, T, E> WriteAheadLog<'a, T, E> {
+    /// Creates `dir` if needed, the log is truncated by the first snapshot
+    pub fn create(
+        dir: &Path,
+        config: WalConfig,
+        header: String,
+        seq: u64,
+        encode_ops: fn(&[Operator<'a, T, E>], &mut String),
+        encode_vertices: fn(&AdjacencyList<'a, T, E>, &Guard, &mut String),
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let len = log.metadata()?.len();
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            config,
+            header,
+            state: Mutex::new(LogState {
+                log,
+                len,
+                seq,
+                since_snapshot: 0,
+                broken: false,
+            }),
+            encode_ops,
+            encode_vertices,
+        })
+    }
+
+    /// Blocks other commits until the returned guard is dropped
+    pub fn lock(&self) -> MutexGuard<LogState> {
+        self.state.lock().expect("Write-ahead log poisoned")
+    }
+
+    /// Appends a transaction that is about to commit, transactions without updates are not logged.
+    ///
+    /// The record is flushed before the transaction is committed, so that no update
+    /// is observed that a crash would lose. Returns where the record starts,
+    /// for `discard` to remove it if the transaction is not committed after all.
+    pub fn append(
+        &self,
+        state: &mut LogState,
+        ops: &[Operator<'a, T, E>],
+    ) -> io::Result<Option<u64>> {
+        if state.broken {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The log holds a record that could not be removed",
+            ));
+        }
+
+        let mut payload = String::new();
+        (self.encode_ops)(ops, &mut payload);
+        if payload.is_empty() {
+            return Ok(None);
+        }
+
+        let start = state.len;
+        let record = format!(
+            "{} {:016x} {}\n",
+            state.seq + 1,
+            checksum(&payload),
+            payload
+        );
+        let written = state
+            .log
+            .write_all(record.as_bytes())
+            .and_then(|_| state.log.flush())
+            .and_then(|_| {
+                if self.config.sync {
+                    state.log.sync_data()
+                } else {
+                    Ok(())
+                }
+            });
+
+        if let Err(err) = written {
+            // A torn record would hide every record appended after it
+            let _ = Self::truncate(state, start);
+            return Err(err);
+        }
+
+        state.len += record.len() as u64;
+        state.seq += 1;
+        state.since_snapshot += 1;
+        Ok(Some(start))
+    }
+
+    /// Removes the last record, which starts at `start`,
+    /// of a transaction that was aborted after it was appended
+    pub fn discard(&self, state: &mut LogState, start: u64) -> io::Result<()> {
+        Self::truncate(state, start)?;
+        state.seq -= 1;
+        state.since_snapshot -= 1;
+        Ok(())
+    }
+
+    fn truncate(state: &mut LogState, len: u64) -> io::Result<()> {
+        let truncated = state.log.set_len(len);
+        match truncated {
+            Ok(()) => state.len = len,
+            Err(_) => state.broken = true,
+        }
+        truncated
+    }
+
+    pub fn snapshot_due(&self, state: &LogState) -> bool {
+        self.config.snapshot_interval > 0 && state.since_snapshot >= self.config.snapshot_interval
+    }
+
+    /// Writes the committed state of `list` and truncates the log.
+    ///
+    /// Must be called while holding the lock,
+    /// so that the snapshot matches the sequence number of the last record.
+    pub fn write_snapshot(
+        &self,
+        state: &mut LogState,
+        list: &AdjacencyList<'a, T, E>,
+        guard: &Guard,
+    ) -> io::Result<()> {
+        let mut contents = String::new();
+        contents.push_str(SNAPSHOT_MAGIC);
+        contents.push('\n');
+        contents.push_str(&format!("seq {}\n", state.seq));
+        contents.push_str(&self.header);
+        (self.encode_vertices)(list, guard, &mut contents);
+        contents.push_str("end\n");
+
+        // The snapshot is replaced atomically, so a crash leaves either the old or the new one
+        let tmp = self.dir.join(SNAPSHOT_TMP_FILE);
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE))?;
+
+        state.log = File::create(self.dir.join(LOG_FILE))?;
+        state.len = 0;
+        state.since_snapshot = 0;
+        state.broken = false;
+        Ok(())
+    }
+}
+
+/// FNV-1a, to detect records torn by a crash
+fn checksum(payload: &str) -> u64 {
+    payload.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The snapshot and the log records that were committed after it
+pub(crate) struct Recovered<'a> {
+    pub seq: u64,
+    pub directed: bool,
+    pub num_nodes: usize,
+    pub md_config: MDListConfig,
+    pub vertices: Vec<(NodeId, Option<usize>)>,
+    /// `(v, e, value, direction_in)`
+    pub edges: Vec<(NodeId, NodeId, Option<EdgeInfo>, bool)>,
+    pub log: Vec<Vec<OpType<'a, usize, EdgeInfo>>>,
+}
+
+pub(crate) fn header(directed: bool, num_nodes: usize, md_config: &MDListConfig) -> String {
+    format!(
+        "directed {}\nnum_nodes {}\nmdlist {} {}\n",
+        directed as u8, num_nodes, md_config.dimension, md_config.basis
+    )
+}
+
+/// Encodes the vertices of a `Graph<usize>`, with their values and edges
+pub(crate) fn encode_vertices(
+    list: &AdjacencyList<usize, EdgeInfo>,
+    guard: &Guard,
+    out: &mut String,
+) {
+    for vertex in list.range(.., guard) {
+        out.push_str(&format!(
+            "v {} {}\n",
+            vertex.key,
+            encode_value(vertex.value())
+        ));
+
+        let lists = [(&vertex.out_edges, 'o'), (&vertex.in_edges, 'n')];
+        for (mdlist, tag) in lists.iter() {
+            // A graph built without in-edges has no `MDList` for them
+            let mdlist = match mdlist {
+                Some(mdlist) => mdlist,
+                None => continue,
+            };
+
+            for entry in mdlist.iter(guard) {
+                let present =
+                    unsafe { AdjacencyList::<usize, EdgeInfo>::is_edge_present(entry.node, guard) };
+                if present {
+                    out.push_str(&format!(
+                        "{} {} {} {}\n",
+                        tag,
+                        vertex.key,
+                        entry.node.key,
+                        encode_edge(unsafe {
+                            AdjacencyList::<usize, EdgeInfo>::edge_value(entry.node, guard)
+                        })
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Encodes the updates of a transaction on a `Graph<usize>`, `Find`s and `FindEdges` are left out
+pub(crate) fn encode_ops(ops: &[Operator<usize, EdgeInfo>], out: &mut String) {
+    let mut encoded = Vec::with_capacity(ops.len());
+    for op in ops {
+        encoded.push(match &op.optype {
+            OpType::Find(..) | OpType::FindEdges(..) => continue,
+            OpType::Insert(key, value) => format!("i {} {}", key, encode_value(*value)),
+            OpType::Delete(key) => format!("d {}", key),
+            OpType::InsertEdge(v, e, value, direction_in) => format!(
+                "ie {} {} {} {}",
+                v,
+                e,
+                *direction_in as u8,
+                encode_edge(value.as_ref())
+            ),
+            OpType::DeleteEdge(v, e, direction_in) => {
+                format!("de {} {} {}", v, e, *direction_in as u8)
+            }
+            OpType::InsertArc(v, e, out_value, in_value) => format!(
+                "ia {} {} {} {}",
+                v,
+                e,
+                encode_edge(out_value.as_ref()),
+                encode_edge(in_value.as_ref())
+            ),
+            OpType::DeleteArc(v, e) => format!("da {} {}", v, e),
+            OpType::UpdateEdge(v, e, value, direction_in) => format!(
+                "ue {} {} {} {}",
+                v,
+                e,
+                *direction_in as u8,
+                encode_edge(Some(value))
+            ),
+            OpType::Connect(..) => unreachable!("Connect is not allowed in transactions"),
+        });
+    }
+
+    out.push_str(&encoded.join(";"));
+}
+
+fn encode_value(value: Option<usize>) -> String {
+    value.map_or_else(|| "-".into(), |value| value.to_string())
+}
+
+fn encode_edge(edge: Option<&EdgeInfo>) -> String {
+    match edge {
+        Some(edge) => format!("{}:{}", edge.node_id, encode_value(edge.weight)),
+        None => "-".into(),
+    }
+}
+
+fn decode_value(s: &str) -> Option<Option<usize>> {
+    match s {
+        "-" => Some(None),
+        s => s.parse().ok().map(Some),
+    }
+}
+
+fn decode_edge(s: &str) -> Option<Option<EdgeInfo>> {
+    if s == "-" {
+        return Some(None);
+    }
+
+    let mut parts = s.splitn(2, ':');
+    let node_id = parts.next()?.parse().ok()?;
+    let weight = decode_value(parts.next()?)?;
+    Some(Some(EdgeInfo { node_id, weight }))
+}
+
+fn decode_ops<'a>(payload: &str) -> Option<Vec<OpType<'a, usize, EdgeInfo>>> {
+    payload
+        .split(';')
+        .map(|op| {
+            let parts: Vec<_> = op.split(' ').collect();
+            let id = |i: usize| parts.get(i).and_then(|x| x.parse::<usize>().ok());
+            let flag = |i: usize| parts.get(i).map(|x| *x == "1");
+
+            Some(match parts[0] {
+                "i" => OpType::Insert(id(1)?, decode_value(parts.get(2)?)?),
+                "d" => OpType::Delete(id(1)?),
+                "ie" => OpType::InsertEdge(id(1)?, id(2)?, decode_edge(parts.get(4)?)?, flag(3)?),
+                "de" => OpType::DeleteEdge(id(1)?, id(2)?, flag(3)?),
+                "ia" => OpType::InsertArc(
+                    id(1)?,
+                    id(2)?,
+                    decode_edge(parts.get(3)?)?,
+                    decode_edge(parts.get(4)?)?,
+                ),
+                "da" => OpType::DeleteArc(id(1)?, id(2)?),
+                "ue" => OpType::UpdateEdge(id(1)?, id(2)?, decode_edge(parts.get(4)?)??, flag(3)?),
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+/// `v <key> <value>`
+fn decode_vertex(parts: &[&str]) -> Option<(NodeId, Option<usize>)> {
+    Some((parts.get(1)?.parse().ok()?, decode_value(parts.get(2)?)?))
+}
+
+/// `o <v> <e> <edge>` for out-edges, `n <v> <e> <edge>` for in-edges
+fn decode_snapshot_edge(parts: &[&str]) -> Option<(NodeId, NodeId, Option<EdgeInfo>, bool)> {
+    Some((
+        parts.get(1)?.parse().ok()?,
+        parts.get(2)?.parse().ok()?,
+        decode_edge(parts.get(3)?)?,
+        parts[0] == "n",
+    ))
+}
+
+/// Reads the snapshot in `dir`, and every intact log record committed after it
+pub(crate) fn read_dir<'a>(dir: &Path) -> io::Result<Recovered<'a>> {
+    let snapshot = BufReader::new(File::open(dir.join(SNAPSHOT_FILE))?);
+    let mut lines = snapshot.lines();
+    let mut next_line = || -> io::Result<String> {
+        lines
+            .next()
+            .unwrap_or_else(|| Err(invalid_data("Snapshot is truncated")))
+    };
+
+    if next_line()? != SNAPSHOT_MAGIC {
+        return Err(invalid_data("Not an epoch graph snapshot"));
+    }
+
+    let mut field = |name: &str| -> io::Result<Vec<usize>> {
+        let line = next_line()?;
+        let mut parts = line.split(' ');
+        if parts.next() != Some(name) {
+            return Err(invalid_data("Malformed snapshot header"));
+        }
+
+        parts
+            .map(|x| {
+                x.parse()
+                    .map_err(|_| invalid_data("Malformed snapshot header"))
+            })
+            .collect()
+    };
+
+    let seq = field("seq")?[0] as u64;
+    let directed = field("directed")?[0] == 1;
+    let num_nodes = field("num_nodes")?[0];
+    let md_config = match field("mdlist")?.as_slice() {
+        [dimension, basis] => MDListConfig::new(*dimension, *basis),
+        _ => return Err(invalid_data("Malformed snapshot header")),
+    };
+
+    let mut recovered = Recovered {
+        seq,
+        directed,
+        num_nodes,
+        md_config,
+        vertices: Vec::new(),
+        edges: Vec::new(),
+        log: Vec::new(),
+    };
+
+    loop {
+        let line = next_line()?;
+        let parts: Vec<_> = line.split(' ').collect();
+
+        let parsed = match parts[0] {
+            "end" => break,
+            "v" => decode_vertex(&parts).map(|vertex| recovered.vertices.push(vertex)),
+            "o" | "n" => decode_snapshot_edge(&parts).map(|edge| recovered.edges.push(edge)),
+            _ => None,
+        };
+
+        if parsed.is_none() {
+            return Err(invalid_data("Malformed snapshot entry"));
+        }
+    }
+
+    let log = match File::open(dir.join(LOG_FILE)) {
+        Ok(log) => log,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(recovered),
+        Err(err) => return Err(err),
+    };
+
+    let mut expected_seq = seq + 1;
+    for line in BufReader::new(log).split(b'\n') {
+        // The last record may be partially written, or not written at all
+        let line = match String::from_utf8(line?) {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        let mut parts = line.splitn(3, ' ');
+        let record_seq = parts.next().and_then(|x| x.parse::<u64>().ok());
+        let record_checksum = parts.next().and_then(|x| u64::from_str_radix(x, 16).ok());
+        let payload = parts.next();
+
+        let ops = match (record_seq, record_checksum, payload) {
+            (Some(record_seq), Some(record_checksum), Some(payload))
+                if record_checksum == checksum(payload) =>
+            {
+                if record_seq < expected_seq {
+                    // Already contained in the snapshot
+                    continue;
+                }
+                if record_seq > expected_seq {
+                    break;
+                }
+                decode_ops(payload)
+            }
+            _ => None,
+        };
+
+        match ops {
+            Some(ops) => recovered.log.push(ops),
+            None => break,
+        }
+
+        expected_seq += 1;
+    }
+
+    recovered.seq = expected_seq - 1;
+    Ok(recovered)
+}
@@ -1,8 +1,14 @@
 use crate::graph::{CSRGraph, Range};
+use crate::memory::{self, MemoryUsage};
 use crate::types::*;
 use gc::{Finalize, Gc, GcCell, Trace};
 use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::mem::size_of;
+
+/// Bytes that `Gc` stores in front of the value: the root count,
+/// the pointer to the next box of the heap and the mark flag
+const GC_HEADER: usize = 4 * size_of::<usize>();
 
 #[derive(Trace, Finalize)]
 pub struct Node<T: 'static + Trace> {
@@ -96,33 +102,43 @@ impl<T: Trace> Node<T> {
 pub struct Graph<T: Trace + 'static> {
     vertices: RefCell<BTreeMap<usize, Gc<GcCell<Node<T>>>>>,
     n_edges: Cell<usize>,
-    num_edges: usize,
     directed: bool,
 }
 
 impl<'a, T: Clone + Trace> CSRGraph<Gc<GcCell<Node<T>>>, Gc<GcCell<Node<T>>>> for Graph<T> {
     fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(true);
+        let graph = Graph::new(true);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
 
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, true);
-            graph.num_edges += 1;
         }
         graph
     }
 
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        let graph = Graph::new(true);
+        for v in 0..num_nodes {
+            graph.add_vertex(v, None);
+        }
+
+        for (v, e, w) in edge_list {
+            graph.add_out_edge(*v, *e, w);
+        }
+
+        graph
+    }
+
     fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
-        let mut graph = Graph::new(false);
+        let graph = Graph::new(false);
         // println!("Building undirected, with {} nodes", num_nodes);
         for v in 0..num_nodes {
             graph.add_vertex(v, None);
         }
         for (v, e, w) in edge_list {
             graph.add_edge(*v, *e, w, false);
-            graph.num_edges += 1;
         }
 
         graph
@@ -142,9 +158,9 @@ impl<'a, T: Clone + Trace> CSRGraph<Gc<GcCell<Node<T>>>, Gc<GcCell<Node<T>>>> fo
 
     fn num_edges_directed(&self) -> usize {
         if self.directed {
-            self.num_edges
+            self.num_edges()
         } else {
-            self.num_edges * 2
+            self.num_edges() * 2
         }
     }
 
@@ -157,7 +173,10 @@ impl<'a, T: Clone + Trace> CSRGraph<Gc<GcCell<Node<T>>>, Gc<GcCell<Node<T>>>> fo
     }
 
     fn in_degree(&self, v: NodeId) -> usize {
-        println!("Graph inversion is probably disabled... in in_degree()");
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
         if let Some(found) = self.vertices.borrow().get(&v) {
             found.borrow().in_edges.len()
         } else {
@@ -179,6 +198,10 @@ impl<'a, T: Clone + Trace> CSRGraph<Gc<GcCell<Node<T>>>, Gc<GcCell<Node<T>>>> fo
     }
 
     fn in_neigh(&self, v: NodeId) -> Range<Gc<GcCell<Node<T>>>> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
         if let Some(vertex) = self.vertices.borrow().get(&v) {
             let mut edges = Vec::new();
             for edge in vertex.borrow().in_edges.values() {
@@ -198,6 +221,32 @@ impl<'a, T: Clone + Trace> CSRGraph<Gc<GcCell<Node<T>>>, Gc<GcCell<Node<T>>>> fo
         println!("---------------------------");
     }
 
+    fn memory_usage(&self) -> MemoryUsage {
+        let vertices = self.vertices.borrow();
+        let mut usage = MemoryUsage::default();
+
+        let (index, overhead) = memory::btree_map::<usize, Gc<GcCell<Node<T>>>>(vertices.len());
+        usage.vertices += index;
+        usage.overhead += overhead;
+
+        for node in vertices.values() {
+            let node = node.borrow();
+            usage.vertices += size_of::<Node<T>>();
+            usage.overhead += memory::ALLOCATION_HEADER;
+            // The root count, mark and list pointer of the `Gc`, and the flags of the `GcCell`
+            usage.descriptors += size_of::<GcCell<Node<T>>>() - size_of::<Node<T>>() + GC_HEADER;
+
+            for edges in &[&node.in_edges, &node.out_edges] {
+                let (bytes, overhead) =
+                    memory::btree_map::<usize, Gc<GcCell<Node<T>>>>(edges.len());
+                usage.edges += bytes;
+                usage.overhead += overhead;
+            }
+        }
+
+        usage
+    }
+
     fn vertices(&self) -> Range<Gc<GcCell<Node<T>>>> {
         let mut edges = Vec::new();
         for edge in self.vertices.borrow().values() {
@@ -260,7 +309,6 @@ impl<T: Trace> Graph<T> {
         Graph {
             vertices: RefCell::new(BTreeMap::new()),
             n_edges: Cell::new(0),
-            num_edges: 0,
             directed,
         }
     }
@@ -297,6 +345,18 @@ impl<T: Trace> Graph<T> {
         }
     }
 
+    /// Adds the out-edge `vertex -> edge` of a directed graph, without its in-edge
+    pub fn add_out_edge(&self, vertex: usize, edge: usize, weight: &Option<usize>) {
+        if let (Some(vertex_node), Some(edge_node)) = (
+            self.vertices.borrow().get(&vertex),
+            self.vertices.borrow().get(&edge),
+        ) {
+            if Node::add_out_edge(vertex_node, edge_node, weight) {
+                self.n_edges.update(|x| x + 1);
+            }
+        }
+    }
+
     pub fn connect(
         &self,
         vertex_node: &Gc<GcCell<Node<T>>>,
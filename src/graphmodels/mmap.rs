@@ -0,0 +1,519 @@
+use crate::builder::{BuilderBase, BuilderConfig, Csr};
+use crate::formats::gap::{self, Header, Serialized, HEADER_SIZE, ID_SIZE, OFFSET_SIZE};
+use crate::graph::{CSRGraph, Range};
+use crate::memory::MemoryUsage;
+use crate::types::*;
+use memmap2::Mmap;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of graphs that have been built into temporary files by this process
+static NUM_TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A vertex, or the target of an edge and its weight, decoded from the mapped file
+#[derive(Clone, Copy, Debug)]
+pub struct MmapNode {
+    node_id: NodeId,
+    weight: Option<Weight>,
+}
+
+impl AsNode for MmapNode {
+    fn as_node(&self) -> NodeId {
+        self.node_id
+    }
+}
+
+impl WeightedEdge for MmapNode {
+    fn get_weight(&self) -> usize {
+        self.weight.expect("Weights must be assigned before used")
+    }
+
+    fn set_weight(&mut self, weight: usize) {
+        self.weight.replace(weight);
+    }
+}
+
+/// Byte positions of the offsets and neighbours of the out- or in-edges in the file
+#[derive(Clone, Copy, Debug)]
+struct Section {
+    offsets: usize,
+    neighs: usize,
+}
+
+/// A read-only graph in a serialized `.sg` or `.wsg` file, that is mapped into memory.
+///
+/// The offsets and neighbours are read from the mapped pages when they are used,
+/// so the graph can be larger than RAM, and opening it does not copy or parse its edges.
+pub struct Graph<T> {
+    map: Mmap,
+    directed: bool,
+    weighted: bool,
+    num_nodes: usize,
+    /// Number of out-edges, every edge of an undirected graph is stored in both directions
+    num_edges: usize,
+    out_edges: Section,
+    in_edges: Option<Section>,
+    /// A file that was written by `build_from_csr`, and is removed when the graph is dropped
+    temp_file: Option<PathBuf>,
+    values: PhantomData<T>,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_offset(bytes: &[u8]) -> usize {
+    let mut offset = [0; OFFSET_SIZE];
+    offset.copy_from_slice(&bytes[..OFFSET_SIZE]);
+    i64::from_le_bytes(offset) as usize
+}
+
+/// Reads a neighbour, or its weight, which were checked to be non-negative when the file was opened
+fn read_id(bytes: &[u8]) -> usize {
+    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
+
+impl<T> Graph<T> {
+    /// Maps a `.sg` or `.wsg` file, the extension decides if the edges have weights.
+    ///
+    /// The file must not be changed while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let weighted = gap::extension(&path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "expected a .sg or .wsg file")
+        })?;
+
+        let file = File::open(&path)?;
+        // Changes to the file by other processes would be visible through the map,
+        // the caller promises that there are none
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_map(map, weighted)
+    }
+
+    /// Checks the header, the offsets and the neighbours, nothing is copied
+    fn from_map(map: Mmap, weighted: bool) -> io::Result<Self> {
+        if map.len() < HEADER_SIZE {
+            return Err(invalid_data("the graph is truncated"));
+        }
+
+        let Header {
+            directed,
+            num_edges,
+            num_nodes,
+        } = Header::parse(&map)?;
+
+        let neigh_size = gap::neigh_size(weighted);
+        let section_size = num_edges
+            .checked_mul(neigh_size)
+            .and_then(|size| size.checked_add((num_nodes + 1) * OFFSET_SIZE))
+            .ok_or_else(|| invalid_data("the size of the file does not match its header"))?;
+        let out_edges = Section {
+            offsets: HEADER_SIZE,
+            neighs: HEADER_SIZE + (num_nodes + 1) * OFFSET_SIZE,
+        };
+
+        let in_edges = if map.len() - HEADER_SIZE == section_size {
+            None
+        } else if directed && section_size.checked_mul(2) == Some(map.len() - HEADER_SIZE) {
+            Some(Section {
+                offsets: HEADER_SIZE + section_size,
+                neighs: HEADER_SIZE + section_size + (num_nodes + 1) * OFFSET_SIZE,
+            })
+        } else {
+            return Err(invalid_data(
+                "the size of the file does not match its header",
+            ));
+        };
+
+        let graph = Self {
+            map,
+            directed,
+            weighted,
+            num_nodes,
+            num_edges,
+            out_edges,
+            in_edges,
+            temp_file: None,
+            values: PhantomData,
+        };
+
+        // Offsets that are out of order would make the neighbour slices panic
+        for section in std::iter::once(graph.out_edges).chain(graph.in_edges) {
+            let offsets = &graph.map[section.offsets..section.neighs];
+            if read_offset(offsets) != 0
+                || read_offset(&offsets[num_nodes * OFFSET_SIZE..]) != num_edges
+                || offsets
+                    .par_chunks_exact(OFFSET_SIZE)
+                    .zip(offsets[OFFSET_SIZE..].par_chunks_exact(OFFSET_SIZE))
+                    .any(|(start, end)| read_offset(start) > read_offset(end))
+            {
+                return Err(invalid_data(
+                    "the offsets do not increase from 0 to the number of edges",
+                ));
+            }
+
+            // Neighbours that are not vertices would index out of bounds in the algorithms
+            graph.map[section.neighs..section.neighs + num_edges * neigh_size]
+                .par_chunks_exact(neigh_size)
+                .try_for_each(|bytes| {
+                    gap::read_id(bytes, num_nodes)?;
+                    if weighted {
+                        gap::read_weight(bytes)?;
+                    }
+                    Ok::<_, io::Error>(())
+                })?;
+        }
+
+        Ok(graph)
+    }
+
+    fn neigh_size(&self) -> usize {
+        gap::neigh_size(self.weighted)
+    }
+
+    fn offset(&self, section: Section, v: NodeId) -> usize {
+        let start = section.offsets + v * OFFSET_SIZE;
+        read_offset(&self.map[start..start + OFFSET_SIZE])
+    }
+
+    fn degree(&self, section: Section, v: NodeId) -> usize {
+        self.offset(section, v + 1) - self.offset(section, v)
+    }
+
+    /// The bytes of the neighbours of `v`, borrowed from the map
+    fn neigh_bytes(&self, section: Section, v: NodeId) -> &[u8] {
+        let size = self.neigh_size();
+        let start = section.neighs + self.offset(section, v) * size;
+        let end = section.neighs + self.offset(section, v + 1) * size;
+        &self.map[start..end]
+    }
+
+    fn neighs(&self, section: Section, v: NodeId) -> Range<MmapNode> {
+        let weighted = self.weighted;
+        Box::new(
+            self.neigh_bytes(section, v)
+                .chunks_exact(self.neigh_size())
+                .map(move |bytes| MmapNode {
+                    node_id: read_id(bytes),
+                    weight: if weighted {
+                        Some(read_id(&bytes[ID_SIZE..]))
+                    } else {
+                        None
+                    },
+                }),
+        )
+    }
+
+    pub fn find_vertex(&self, v: NodeId) -> Option<MmapNode> {
+        if v < self.num_nodes {
+            Some(MmapNode {
+                node_id: v,
+                weight: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn bfs(&self, start: NodeId, goal: Option<NodeId>) -> usize {
+        let mut queue = VecDeque::new();
+        let mut discovered = vec![false; self.num_nodes];
+
+        discovered[start] = true;
+        queue.push_back(start);
+        let mut num_discovered = 1;
+
+        while let Some(v) = queue.pop_front() {
+            for e in self.neighs(self.out_edges, v) {
+                let e = e.as_node();
+                if goal == Some(e) {
+                    return num_discovered;
+                }
+
+                if !discovered[e] {
+                    discovered[e] = true;
+                    num_discovered += 1;
+                    queue.push_back(e);
+                }
+            }
+        }
+
+        num_discovered
+    }
+
+    fn from_edge_list(
+        num_nodes: usize,
+        edge_list: &EdgeList,
+        directed: bool,
+        invert: bool,
+    ) -> Self {
+        let config = BuilderConfig::new()
+            .symmetrize(!directed)
+            .needs_weights(false);
+        let mut builder = BuilderBase::with_config(config);
+        builder.set_num_nodes(num_nodes);
+
+        let out_edges = builder.make_csr(edge_list, false).squish();
+        let in_edges = if directed && invert {
+            Some(builder.make_csr(edge_list, true).squish())
+        } else {
+            None
+        };
+
+        Self::build_from_csr(out_edges, in_edges, directed)
+    }
+
+    /// Serializes the edges to a temporary file, and maps it
+    fn from_serialized(graph: &Serialized) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "gapbs-{}-{}.{}",
+            std::process::id(),
+            NUM_TEMP_FILES.fetch_add(1, Ordering::Relaxed),
+            if graph.is_weighted() { "wsg" } else { "sg" }
+        ));
+
+        let result = File::create(&path)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                gap::write(&mut writer, graph, graph.is_weighted())?;
+                writer.flush()
+            })
+            .and_then(|_| Self::open(&path));
+
+        match result {
+            Ok(mut graph) => {
+                graph.temp_file = Some(path);
+                Ok(graph)
+            }
+            Err(err) => {
+                let _ = fs::remove_file(&path);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<T> Drop for Graph<T> {
+    fn drop(&mut self) {
+        if let Some(path) = self.temp_file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl<T> CSRGraph<MmapNode, MmapNode> for Graph<T> {
+    const BUILDS_FROM_CSR: bool = true;
+
+    fn build_directed(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, true, true)
+    }
+
+    fn build_undirected(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, false, false)
+    }
+
+    fn build_directed_without_in_edges(num_nodes: usize, edge_list: &EdgeList) -> Self {
+        Self::from_edge_list(num_nodes, edge_list, true, false)
+    }
+
+    /// Writes the edges to a temporary file that is mapped, and removed when the graph is dropped
+    fn build_from_csr(out_edges: Csr, in_edges: Option<Csr>, directed: bool) -> Self {
+        let graph = Serialized {
+            directed,
+            out_edges,
+            in_edges,
+        };
+
+        Self::from_serialized(&graph)
+            .unwrap_or_else(|err| panic!("Could not map a temporary graph: {}", err))
+    }
+
+    fn directed(&self) -> bool {
+        self.directed
+    }
+
+    fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    fn num_edges(&self) -> usize {
+        if self.directed {
+            self.num_edges
+        } else {
+            self.num_edges / 2
+        }
+    }
+
+    fn num_edges_directed(&self) -> usize {
+        self.num_edges
+    }
+
+    fn out_degree(&self, v: NodeId) -> usize {
+        self.degree(self.out_edges, v)
+    }
+
+    fn in_degree(&self, v: NodeId) -> usize {
+        if !self.directed {
+            return self.out_degree(v);
+        }
+
+        self.in_edges.map_or(0, |in_edges| self.degree(in_edges, v))
+    }
+
+    fn in_neigh(&self, v: NodeId) -> Range<MmapNode> {
+        if !self.directed {
+            return self.out_neigh(v);
+        }
+
+        match self.in_edges {
+            Some(in_edges) => self.neighs(in_edges, v),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn out_neigh(&self, v: NodeId) -> Range<MmapNode> {
+        self.neighs(self.out_edges, v)
+    }
+
+    fn print_stats(&self) {
+        println!("---------- GRAPH ----------");
+        println!("  Num Nodes          - {:?}", self.num_nodes());
+        println!("  Num Edges          - {:?}", self.num_edges_directed());
+        println!("---------------------------");
+    }
+
+    /// The mapped pages are counted, although the OS may not keep all of them in memory
+    fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        let num_sections = if self.in_edges.is_some() { 2 } else { 1 };
+
+        usage.vertices += num_sections * (self.num_nodes + 1) * OFFSET_SIZE;
+        usage.edges += num_sections * self.num_edges * self.neigh_size();
+        usage.overhead += HEADER_SIZE;
+
+        usage
+    }
+
+    fn vertices(&self) -> Range<MmapNode> {
+        Box::new((0..self.num_nodes).map(|node_id| MmapNode {
+            node_id,
+            weight: None,
+        }))
+    }
+
+    fn old_bfs(&self, v: NodeId) {
+        self.bfs(v, None);
+    }
+
+    fn replace_out_edges(&self, v: NodeId, edges: Vec<MmapNode>) {
+        panic!("The edges of a memory-mapped graph cannot be changed");
+    }
+
+    fn replace_in_edges(&self, v: NodeId, edges: Vec<MmapNode>) {
+        panic!("The edges of a memory-mapped graph cannot be changed");
+    }
+
+    fn op_add_vertex(&self, v: NodeId) {
+        panic!("A memory-mapped graph cannot be changed");
+    }
+
+    fn op_add_edge(&self, v: NodeId, e: NodeId) {
+        panic!("A memory-mapped graph cannot be changed");
+    }
+
+    fn op_delete_edge(&self, v: NodeId, e: NodeId) {
+        panic!("A memory-mapped graph cannot be changed");
+    }
+
+    fn op_delete_vertex(&self, v: NodeId) {
+        panic!("A memory-mapped graph cannot be changed");
+    }
+
+    fn op_find_vertex(&self, v: NodeId) {
+        self.find_vertex(v);
+    }
+}
+
+#[cfg(all(test, not(crossbeam_loom)))]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gapbs-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn opens_serialized_files() {
+        let edge_list = vec![
+            (0, 1, Some(3)),
+            (1, 2, Some(1)),
+            (2, 0, Some(2)),
+            (2, 3, Some(4)),
+        ];
+        let graph: Graph<usize> = Graph::from_edge_list(4, &edge_list, true, true);
+        let mut bytes = Vec::new();
+        gap::write(&mut bytes, &Serialized::from_weighted_graph(&graph), true).unwrap();
+
+        let path = write_temp("opens.wsg", &bytes);
+        let graph: Graph<usize> = Graph::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(graph.directed());
+        assert_eq!(graph.num_edges(), 4);
+        assert_eq!(graph.in_degree(0), 1);
+        assert_eq!(graph.bfs(0, None), 4);
+        let neighs: Vec<_> = graph
+            .out_neigh(2)
+            .map(|e| (e.as_node(), e.get_weight()))
+            .collect();
+        assert_eq!(neighs, vec![(0, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let edge_list = vec![(0, 1, None), (1, 2, None)];
+        let graph: Graph<usize> = Graph::from_edge_list(3, &edge_list, false, false);
+        let mut bytes = Vec::new();
+        gap::write(&mut bytes, &Serialized::from_graph(&graph), false).unwrap();
+
+        let path = write_temp("truncated.sg", &bytes[..bytes.len() - 1]);
+        let err = Graph::<usize>::open(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The second offset is larger than the third
+        bytes[HEADER_SIZE + OFFSET_SIZE] = 4;
+        let path = write_temp("unsorted.sg", &bytes);
+        let err = Graph::<usize>::open(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "the offsets do not increase from 0 to the number of edges"
+        );
+    }
+
+    #[test]
+    fn rejects_neighbours_that_are_not_vertices() {
+        let edge_list = vec![(0, 1, None), (1, 2, None)];
+        let graph: Graph<usize> = Graph::from_edge_list(3, &edge_list, true, false);
+        let mut bytes = Vec::new();
+        gap::write(&mut bytes, &Serialized::from_graph(&graph), false).unwrap();
+
+        // The first neighbour of the out-edges
+        let neighs = HEADER_SIZE + 4 * OFFSET_SIZE;
+        for id in [3i32, -1].iter() {
+            bytes[neighs..neighs + ID_SIZE].copy_from_slice(&id.to_le_bytes());
+            let path = write_temp("out_of_range.sg", &bytes);
+            let err = Graph::<usize>::open(&path).err().unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(err.to_string(), "a neighbour is not a vertex of the graph");
+        }
+    }
+}
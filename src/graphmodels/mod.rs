@@ -1,7 +1,9 @@
 pub mod arc;
 pub mod arena;
 pub mod cc;
+pub mod csr;
 pub mod epoch;
 pub mod gc;
+pub mod mmap;
 pub mod rc;
 pub mod rcsorted;
@@ -0,0 +1,184 @@
+use crate::graph::CSRGraph;
+use crate::graphmodels;
+use crate::memory::{self, Bytes, MemoryUsage};
+use crate::types::*;
+use std::fmt;
+
+/// Number of times the threads of the `rayon` pool flush their garbage,
+/// as the pool defers garbage of its own while running a flush
+const POOL_FLUSHES: usize = 8;
+
+/// Number of times the epoch is advanced after every flush of the pool,
+/// each advance only collects a few bags of garbage
+const EPOCH_FLUSHES: usize = 16;
+
+/// Memory that is still allocated after a graph has been dropped
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Leak {
+    pub bytes: usize,
+    pub allocations: usize,
+}
+
+impl Leak {
+    pub fn is_empty(&self) -> bool {
+        self.bytes == 0 && self.allocations == 0
+    }
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "no leaks")
+        } else {
+            write!(
+                f,
+                "leaked {} in {} allocations",
+                Bytes(self.bytes),
+                self.allocations
+            )
+        }
+    }
+}
+
+/// What a graph held before it was dropped, and what it left behind
+#[derive(Debug, Clone, Copy)]
+pub struct LeakReport {
+    pub name: &'static str,
+    pub usage: MemoryUsage,
+    pub leak: Leak,
+    /// Transaction descriptors that the epoch model allocated while the graph was built,
+    /// which it never frees
+    pub descriptors: Leak,
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} {} of {}",
+            self.name,
+            self.leak,
+            Bytes(self.usage.total())
+        )?;
+        if !self.descriptors.is_empty() {
+            write!(f, ", descriptors {}", self.descriptors)?;
+        }
+        Ok(())
+    }
+}
+
+/// A ring `v -> v + 1` with a chord `v -> v + 7` from every vertex,
+/// so that every vertex is part of a cycle, even in a directed graph
+pub fn cyclic_edge_list(num_nodes: usize) -> EdgeList {
+    let ring = (0..num_nodes).map(|v| (v, (v + 1) % num_nodes, None));
+    let chords = (0..num_nodes).map(|v| (v, (v + 7) % num_nodes, None));
+    ring.chain(chords).collect()
+}
+
+/// Frees the garbage that is deferred by the epoch model,
+/// both by the current thread and by the threads of the `rayon` pool
+pub fn flush_epoch() {
+    for _ in 0..POOL_FLUSHES {
+        // Moves the garbage of every thread to the global queue,
+        // which is then collected as the epoch advances
+        rayon::broadcast(|_| epoch::pin().flush());
+        for _ in 0..EPOCH_FLUSHES {
+            epoch::pin().flush();
+        }
+    }
+}
+
+/// Reclaims the garbage cycles of the `Cc` model
+pub fn collect_cycles() {
+    bacon_rajan_cc::collect_cycles();
+}
+
+/// Runs a full collection of the `Gc` model
+pub fn force_collect() {
+    gc::force_collect();
+}
+
+/// Builds a graph from `edge_list`, drops it, and calls `collect` to reclaim its garbage.
+/// Returns the bytes and allocations that are still live compared to before the graph was built.
+///
+/// The allocations are counted by the `CountingAllocator`, which has to be installed
+/// as the global allocator. It counts every thread, so nothing else should allocate
+/// while a graph is checked.
+pub fn check<V, E, G: CSRGraph<V, E>>(
+    name: &'static str,
+    num_nodes: usize,
+    edge_list: &EdgeList,
+    directed: bool,
+    collect: impl Fn(),
+) -> LeakReport {
+    let build = || {
+        if directed {
+            G::build_directed(num_nodes, edge_list)
+        } else {
+            G::build_undirected(num_nodes, edge_list)
+        }
+    };
+
+    // The first graph initializes thread pools, thread locals and buffers of the collectors,
+    // which are kept for the rest of the program
+    drop(build());
+    collect();
+
+    let allocated = memory::allocated();
+    let allocations = memory::allocations();
+    let (descriptor_bytes, descriptor_allocations) = graphmodels::epoch::allocated_descriptors();
+
+    let graph = build();
+    let usage = graph.memory_usage();
+    drop(graph);
+    collect();
+
+    let descriptors = graphmodels::epoch::allocated_descriptors();
+    LeakReport {
+        name,
+        usage,
+        leak: Leak {
+            bytes: memory::allocated().saturating_sub(allocated),
+            allocations: memory::allocations().saturating_sub(allocations),
+        },
+        descriptors: Leak {
+            bytes: descriptors.0 - descriptor_bytes,
+            allocations: descriptors.1 - descriptor_allocations,
+        },
+    }
+}
+
+/// Checks every graph model with a cyclic graph of `num_nodes` vertices
+pub fn check_models(num_nodes: usize, directed: bool) -> Vec<LeakReport> {
+    let edge_list = cyclic_edge_list(num_nodes);
+    let none = || {};
+
+    vec![
+        check::<_, _, graphmodels::epoch::Graph<usize>>(
+            "EPOCH",
+            num_nodes,
+            &edge_list,
+            directed,
+            flush_epoch,
+        ),
+        check::<_, _, graphmodels::arc::Graph<usize>>("ARC", num_nodes, &edge_list, directed, none),
+        check::<_, _, graphmodels::rc::Graph<usize>>("RC", num_nodes, &edge_list, directed, none),
+        check::<_, _, graphmodels::cc::Graph<usize>>(
+            "CC",
+            num_nodes,
+            &edge_list,
+            directed,
+            collect_cycles,
+        ),
+        check::<_, _, graphmodels::gc::Graph<usize>>(
+            "GC",
+            num_nodes,
+            &edge_list,
+            directed,
+            force_collect,
+        ),
+        check::<_, _, graphmodels::arena::Graph<usize>>(
+            "ARENA", num_nodes, &edge_list, directed, none,
+        ),
+    ]
+}
@@ -14,23 +14,36 @@ extern crate gc;
 
 extern crate crossbeam_epoch as epoch;
 extern crate crossbeam_utils as utils;
+#[cfg(crossbeam_loom)]
+extern crate loom_crate as loom;
 
 /// Performs the benchmarks
 pub mod benchmark;
 /// Builds and squishes a graph (removes self-references and parallel edges)
 pub mod builder;
+/// Reads and writes edge lists in the file formats of graph collections
+pub mod formats;
 /// Generates or loads edge lists `Vec<(v, e)>` of different distributions
 pub mod generator;
 /// Graph trait that is used in all implementations, any memory model is required to implement it
 pub mod graph;
 /// Different graph models, `Rc`, `Gc`, `Cc`, `Epoch`, `Arena`...
 pub mod graphmodels;
+/// Builds and drops a graph with each model, and reports the memory that is not freed
+#[cfg(all(feature = "testing", not(crossbeam_loom)))]
+pub mod leaks;
+/// Records histories of concurrent graph operations and checks that they are linearizable
+#[cfg(all(test, not(crossbeam_loom)))]
+mod linearizability;
+/// Estimates the memory held by the graph models, and counts the bytes allocated by benchmarks
+pub mod memory;
 /// A sliding queue implementation using iterators
 pub mod slidingqueue;
 /// Common type sfor edges, vertices and collections of them.
 pub mod types;
 
 /// # Betweenness Centrality (BC) - Brandes
+#[cfg(not(crossbeam_loom))]
 pub mod bc;
 /// # Breadth-First Search (BFS) - direction optimizing
 pub mod bfs;
@@ -43,6 +56,8 @@ pub mod sssp;
 /// # Triangle Counting (TC) - Order invariant with possible relabelling
 pub mod tc;
 
+// Spawns scoped threads, which are not available to the model checker
+#[cfg(not(crossbeam_loom))]
 pub mod ops;
 
 mod timer;
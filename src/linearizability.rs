@@ -0,0 +1,538 @@
+use crate::graphmodels::{arc, epoch};
+use crate::types::*;
+use rand::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// An operation on a graph
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Op {
+    AddVertex(NodeId),
+    DeleteVertex(NodeId),
+    AddEdge(NodeId, NodeId),
+    DeleteEdge(NodeId, NodeId),
+    FindVertex(NodeId),
+    HasEdge(NodeId, NodeId),
+}
+
+impl Op {
+    fn vertices(&self) -> (NodeId, Option<NodeId>) {
+        match *self {
+            Op::AddVertex(v) | Op::DeleteVertex(v) | Op::FindVertex(v) => (v, None),
+            Op::AddEdge(v, e) | Op::DeleteEdge(v, e) | Op::HasEdge(v, e) => (v, Some(e)),
+        }
+    }
+
+    /// Checks if the operation is executed as a transaction,
+    /// which can be aborted by a transaction on the same vertices
+    fn is_transaction(&self) -> bool {
+        !matches!(self, Op::HasEdge(..))
+    }
+
+    /// Checks if the transactions of both operations can meet on a vertex or its edges.
+    /// A deleted vertex is unlinked from the edge lists of every other vertex.
+    fn may_conflict(&self, other: &Op) -> bool {
+        if !self.is_transaction() || !other.is_transaction() {
+            return false;
+        }
+
+        let shares = |op: &Op, v: NodeId| {
+            let (a, b) = op.vertices();
+            a == v || b == Some(v)
+        };
+        let (a, b) = self.vertices();
+        matches!(self, Op::DeleteVertex(_))
+            || matches!(other, Op::DeleteVertex(_))
+            || shares(other, a)
+            || matches!(b, Some(b) if shares(other, b))
+    }
+}
+
+/// What an operation observed
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Ret {
+    /// The operation completed, without reporting anything about the graph
+    Done,
+    /// The operation was aborted by a conflicting transaction, and had no effect.
+    /// Only admitted if a transaction that may conflict with it ran concurrently.
+    Aborted,
+    Bool(bool),
+}
+
+impl Ret {
+    /// Checks if the observed value is allowed when the model returns `expected`
+    fn admits(&self, expected: &Ret) -> bool {
+        match self {
+            Ret::Done => true,
+            observed => observed == expected,
+        }
+    }
+}
+
+/// A completed operation, with logical timestamps of its invocation and response
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub thread: usize,
+    pub op: Op,
+    pub ret: Ret,
+    pub invoke: u64,
+    pub response: u64,
+}
+
+/// A graph whose operations are recorded
+pub trait Subject: Sync {
+    fn apply(&self, op: &Op) -> Ret;
+}
+
+/// A sequential specification of a graph
+pub trait Model: Clone + Eq + Hash {
+    fn step(&self, op: &Op) -> (Self, Ret);
+}
+
+/// Differences in the semantics of the graph models
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Spec {
+    /// `AddEdge(v, e)` also adds `(e, v)`, `DeleteEdge(v, e)` only deletes `(v, e)`
+    pub symmetric: bool,
+    pub self_loops: bool,
+    /// Adding a vertex that is already present succeeds, and keeps its edges
+    pub upsert: bool,
+    /// Deleting a vertex also deletes the edges pointing to it,
+    /// otherwise they are kept as ordinary edges
+    pub cascade: bool,
+}
+
+/// A sequential reference graph, the out-edges of every vertex in a `BTreeMap`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GraphModel {
+    spec: Spec,
+    vertices: BTreeMap<NodeId, BTreeSet<NodeId>>,
+}
+
+impl GraphModel {
+    pub fn new(spec: Spec, vertices: impl IntoIterator<Item = NodeId>) -> Self {
+        Self {
+            spec,
+            vertices: vertices.into_iter().map(|v| (v, BTreeSet::new())).collect(),
+        }
+    }
+}
+
+impl Model for GraphModel {
+    fn step(&self, op: &Op) -> (Self, Ret) {
+        let mut next = self.clone();
+        let ret = match *op {
+            Op::AddVertex(v) => {
+                next.vertices.entry(v).or_insert_with(BTreeSet::new);
+                Ret::Bool(self.spec.upsert || !self.vertices.contains_key(&v))
+            }
+            Op::DeleteVertex(v) => {
+                let found = next.vertices.remove(&v).is_some();
+                if found && self.spec.cascade {
+                    for edges in next.vertices.values_mut() {
+                        edges.remove(&v);
+                    }
+                }
+                Ret::Bool(found)
+            }
+            Op::AddEdge(v, e) => {
+                let allowed = self.spec.self_loops || v != e;
+                if allowed && self.vertices.contains_key(&v) && self.vertices.contains_key(&e) {
+                    if self.spec.symmetric {
+                        next.vertices.get_mut(&e).unwrap().insert(v);
+                    }
+                    Ret::Bool(next.vertices.get_mut(&v).unwrap().insert(e))
+                } else {
+                    Ret::Bool(false)
+                }
+            }
+            Op::DeleteEdge(v, e) => Ret::Bool(
+                next.vertices
+                    .get_mut(&v)
+                    .map_or(false, |edges| edges.remove(&e)),
+            ),
+            Op::FindVertex(v) => Ret::Bool(self.vertices.contains_key(&v)),
+            Op::HasEdge(v, e) => Ret::Bool(
+                self.vertices
+                    .get(&v)
+                    .map_or(false, |edges| edges.contains(&e)),
+            ),
+        };
+
+        (next, ret)
+    }
+}
+
+/// Records the operations of concurrent threads,
+/// using a shared counter as the clock
+#[derive(Default)]
+pub struct Recorder {
+    clock: AtomicU64,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn call<S: Subject + ?Sized>(&self, thread: usize, subject: &S, op: Op) -> Ret {
+        let invoke = self.clock.fetch_add(1, Ordering::SeqCst);
+        let ret = subject.apply(&op);
+        let response = self.clock.fetch_add(1, Ordering::SeqCst);
+
+        self.entries.lock().unwrap().push(Entry {
+            thread,
+            op,
+            ret,
+            invoke,
+            response,
+        });
+
+        ret
+    }
+
+    /// The recorded operations, ordered by invocation
+    pub fn into_history(self) -> Vec<Entry> {
+        let mut history = self.entries.into_inner().unwrap();
+        history.sort_by_key(|entry| entry.invoke);
+        history
+    }
+}
+
+/// Draws operations with the same mix of updates as `ops::ops_mt`,
+/// with lookups of vertices and edges mixed in to observe them.
+///
+/// If `ordered_reads` is set, edges are only looked up from the lower to the higher id.
+pub fn ops_mt_op<R: Rng>(rng: &mut R, num_nodes: usize, ordered_reads: bool) -> Op {
+    let mut rnd_id = || rng.gen_range(1, num_nodes + 1);
+    let (v, e) = (rnd_id(), rnd_id());
+
+    match rng.gen_range(1, 130) {
+        1..=40 => Op::AddVertex(v),
+        41..=80 => Op::DeleteVertex(v),
+        81..=90 => Op::AddEdge(v, e),
+        91..=100 => Op::DeleteEdge(v, e),
+        101..=110 => Op::FindVertex(v),
+        _ if ordered_reads => Op::HasEdge(std::cmp::min(v, e), std::cmp::max(v, e)),
+        _ => Op::HasEdge(v, e),
+    }
+}
+
+/// Runs `ops_per_thread` operations from `ops_mt_op` on each of `threads` threads,
+/// on the vertices `1..=num_nodes`, and returns the recorded history
+pub fn record_ops_mt<S: Subject>(
+    subject: &S,
+    threads: usize,
+    ops_per_thread: usize,
+    num_nodes: usize,
+    ordered_reads: bool,
+) -> Vec<Entry> {
+    let recorder = Recorder::new();
+
+    crossbeam_utils::thread::scope(|s| {
+        for thread in 0..threads {
+            let recorder = &recorder;
+            s.spawn(move |_| {
+                let mut rng = thread_rng();
+                for _ in 0..ops_per_thread {
+                    let op = ops_mt_op(&mut rng, num_nodes, ordered_reads);
+                    recorder.call(thread, subject, op);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    recorder.into_history()
+}
+
+/// Searches for a linearization of `history`, in the style of Wing & Gong,
+/// with Lowe's memoization of the explored (linearized operations, model) states.
+///
+/// Returns the indices of `history` in linearization order,
+/// or `None` if the history is not linearizable with respect to `init`.
+pub fn check<M: Model>(history: &[Entry], init: &M) -> Option<Vec<usize>> {
+    if !aborts_are_explained(history) {
+        return None;
+    }
+
+    let mut search = Search {
+        history,
+        visited: HashSet::new(),
+        order: Vec::with_capacity(history.len()),
+    };
+
+    let mut linearized = vec![0u64; (history.len() + 63) / 64];
+    if search.search(&mut linearized, init) {
+        Some(search.order)
+    } else {
+        None
+    }
+}
+
+/// Checks that every aborted operation ran concurrently with an operation
+/// of another thread that may have conflicted with it
+fn aborts_are_explained(history: &[Entry]) -> bool {
+    history
+        .iter()
+        .filter(|entry| entry.ret == Ret::Aborted)
+        .all(|aborted| {
+            aborted.op.is_transaction()
+                && history.iter().any(|other| {
+                    other.thread != aborted.thread
+                        && other.invoke < aborted.response
+                        && aborted.invoke < other.response
+                        && aborted.op.may_conflict(&other.op)
+                })
+        })
+}
+
+struct Search<'h, M> {
+    history: &'h [Entry],
+    visited: HashSet<(Vec<u64>, M)>,
+    order: Vec<usize>,
+}
+
+impl<'h, M: Model> Search<'h, M> {
+    fn search(&mut self, linearized: &mut Vec<u64>, model: &M) -> bool {
+        if self.order.len() == self.history.len() {
+            return true;
+        }
+
+        if !self.visited.insert((linearized.clone(), model.clone())) {
+            return false;
+        }
+
+        let is_linearized =
+            |linearized: &[u64], i: usize| linearized[i / 64] & (1 << (i % 64)) != 0;
+
+        // An operation can only be linearized next if it was invoked
+        // before every remaining operation had responded
+        let horizon = (0..self.history.len())
+            .filter(|i| !is_linearized(linearized, *i))
+            .map(|i| self.history[i].response)
+            .min()
+            .unwrap_or(u64::max_value());
+
+        for i in 0..self.history.len() {
+            let entry = &self.history[i];
+            if is_linearized(linearized, i) || entry.invoke > horizon {
+                continue;
+            }
+
+            let next = if entry.ret == Ret::Aborted {
+                model.clone()
+            } else {
+                let (next, expected) = model.step(&entry.op);
+                if !entry.ret.admits(&expected) {
+                    continue;
+                }
+                next
+            };
+
+            linearized[i / 64] |= 1 << (i % 64);
+            self.order.push(i);
+            if self.search(linearized, &next) {
+                return true;
+            }
+            self.order.pop();
+            linearized[i / 64] &= !(1 << (i % 64));
+        }
+
+        false
+    }
+}
+
+/// Updates are transactions on a directed graph, edges are inserted with `InsertArc`
+impl<'a> Subject for epoch::Graph<'a, usize> {
+    fn apply(&self, op: &Op) -> Ret {
+        let txn = match *op {
+            Op::AddVertex(v) => epoch::OpType::Insert(v, None),
+            Op::DeleteVertex(v) => epoch::OpType::Delete(v),
+            Op::AddEdge(v, e) => {
+                let edge = |node_id| {
+                    Some(epoch::EdgeInfo {
+                        node_id,
+                        weight: None,
+                    })
+                };
+                epoch::OpType::InsertArc(v, e, edge(e), edge(v))
+            }
+            Op::DeleteEdge(v, e) => epoch::OpType::DeleteArc(v, e),
+            Op::FindVertex(v) => epoch::OpType::Find(v),
+            Op::HasEdge(v, e) => {
+                let guard = &crossbeam_epoch::pin();
+                return Ret::Bool(self.read(guard).has_out_edge(v, e));
+            }
+        };
+
+        // The transaction holds a single operation, which decides its outcome
+        match self.execute_ops_with_outcome(vec![txn]).0 {
+            epoch::TxnOutcome::Conflicted => Ret::Aborted,
+            outcome => Ret::Bool(outcome == epoch::TxnOutcome::Committed),
+        }
+    }
+}
+
+impl Subject for arc::Graph<usize> {
+    fn apply(&self, op: &Op) -> Ret {
+        use crate::graph::CSRGraph;
+
+        match *op {
+            Op::AddVertex(v) => self.op_add_vertex(v),
+            Op::DeleteVertex(v) => self.op_delete_vertex(v),
+            Op::AddEdge(v, e) => self.op_add_edge(v, e),
+            Op::DeleteEdge(v, e) => self.op_delete_edge(v, e),
+            Op::FindVertex(v) => return Ret::Bool(self.find_vertex(v).is_some()),
+            Op::HasEdge(v, e) => return Ret::Bool(self.has_out_edge(v, e)),
+        }
+
+        Ret::Done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::CSRGraph;
+
+    const NUM_NODES: usize = 3;
+    const THREADS: usize = 4;
+    const OPS_PER_THREAD: usize = 10;
+    const ROUNDS: usize = 500;
+
+    fn entry(thread: usize, op: Op, ret: Ret, invoke: u64, response: u64) -> Entry {
+        Entry {
+            thread,
+            op,
+            ret,
+            invoke,
+            response,
+        }
+    }
+
+    #[test]
+    fn rejects_stale_read() {
+        let spec = Spec {
+            symmetric: false,
+            self_loops: true,
+            upsert: true,
+            cascade: true,
+        };
+        let model = GraphModel::new(spec, vec![1]);
+
+        // The vertex is deleted before it is found
+        let history = vec![
+            entry(0, Op::DeleteVertex(1), Ret::Bool(true), 0, 1),
+            entry(1, Op::FindVertex(1), Ret::Bool(true), 2, 3),
+        ];
+        assert!(check(&history, &model).is_none());
+
+        // The operations overlap, so the find may take effect first
+        let history = vec![
+            entry(0, Op::DeleteVertex(1), Ret::Bool(true), 0, 2),
+            entry(1, Op::FindVertex(1), Ret::Bool(true), 1, 3),
+        ];
+        assert_eq!(check(&history, &model), Some(vec![1, 0]));
+    }
+
+    #[test]
+    fn aborts_need_a_concurrent_conflict() {
+        let spec = Spec {
+            symmetric: false,
+            self_loops: true,
+            upsert: true,
+            cascade: true,
+        };
+        let model = GraphModel::new(spec, vec![1, 2, 3]);
+
+        // Nothing else ran while the edge was added
+        let history = vec![
+            entry(0, Op::AddEdge(1, 2), Ret::Aborted, 0, 1),
+            entry(1, Op::AddVertex(3), Ret::Bool(true), 2, 3),
+        ];
+        assert!(check(&history, &model).is_none());
+
+        // A concurrent transaction on another vertex cannot abort it
+        let history = vec![
+            entry(0, Op::AddEdge(1, 2), Ret::Aborted, 0, 2),
+            entry(1, Op::AddVertex(3), Ret::Bool(true), 1, 3),
+        ];
+        assert!(check(&history, &model).is_none());
+
+        // Lookups of edges are not transactions
+        let history = vec![
+            entry(0, Op::AddEdge(1, 2), Ret::Aborted, 0, 2),
+            entry(1, Op::HasEdge(1, 2), Ret::Bool(false), 1, 3),
+        ];
+        assert!(check(&history, &model).is_none());
+
+        let history = vec![
+            entry(0, Op::AddEdge(1, 2), Ret::Aborted, 0, 2),
+            entry(1, Op::DeleteEdge(2, 1), Ret::Bool(false), 1, 3),
+        ];
+        assert!(check(&history, &model).is_some());
+    }
+
+    #[test]
+    fn deleted_vertices_take_their_edges() {
+        let spec = Spec {
+            symmetric: false,
+            self_loops: true,
+            upsert: true,
+            cascade: true,
+        };
+        let model = GraphModel::new(spec, vec![1, 2]);
+
+        let history = vec![
+            entry(0, Op::AddEdge(1, 2), Ret::Bool(true), 0, 1),
+            entry(0, Op::DeleteVertex(2), Ret::Bool(true), 2, 3),
+            entry(0, Op::AddVertex(2), Ret::Bool(true), 4, 5),
+            entry(0, Op::HasEdge(1, 2), Ret::Bool(true), 6, 7),
+        ];
+        assert!(check(&history, &model).is_none());
+    }
+
+    #[test]
+    fn epoch_ops_mt_is_linearizable() {
+        let spec = Spec {
+            symmetric: false,
+            self_loops: true,
+            upsert: true,
+            cascade: true,
+        };
+
+        for _ in 0..ROUNDS {
+            let mut graph: epoch::Graph<usize> =
+                epoch::Graph::bulk_load(NUM_NODES + 1, &Vec::new(), true);
+            graph.set_retry_policy(epoch::RetryPolicy::new(16));
+
+            let history = record_ops_mt(&graph, THREADS, OPS_PER_THREAD, NUM_NODES, false);
+            let model = GraphModel::new(spec, 1..=NUM_NODES);
+            assert!(check(&history, &model).is_some(), "{:#?}", history);
+        }
+    }
+
+    #[test]
+    fn arc_ops_mt_is_linearizable() {
+        // Both halves of an undirected edge are added one at a time,
+        // so only one of them is looked up
+        let spec = Spec {
+            symmetric: true,
+            self_loops: false,
+            upsert: false,
+            cascade: false,
+        };
+
+        for _ in 0..ROUNDS {
+            let graph: arc::Graph<usize> = arc::Graph::build_directed(NUM_NODES + 1, &Vec::new());
+
+            let history = record_ops_mt(&graph, THREADS, OPS_PER_THREAD, NUM_NODES, true);
+            let model = GraphModel::new(spec, 0..=NUM_NODES);
+            assert!(check(&history, &model).is_some(), "{:#?}", history);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashSet;
+use std::fmt;
+use std::mem::size_of;
+use std::ops::{Add, AddAssign};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+/// Bookkeeping that the system allocator stores next to every allocation,
+/// e.g. the chunk header of glibc's malloc
+pub const ALLOCATION_HEADER: usize = size_of::<usize>();
+
+/// Number of entries in a node of `std`'s `BTreeMap`
+const BTREE_CAPACITY: usize = 11;
+
+/// Entries in a `BTreeMap` node on average, maps that are built
+/// by inserting keys in ascending order leave their nodes about half full
+const BTREE_FILL: usize = 6;
+
+/// Control bytes that `hashbrown` allocates in addition to one per bucket
+const HASH_GROUP_WIDTH: usize = 16;
+
+/// Bytes held by a graph, split by what they are used for.
+///
+/// The sizes are estimated from the number of elements in each collection
+/// and the size of their types. Memory that the allocator keeps after it is freed,
+/// e.g. because it is not returned to the OS, is not included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The vertices, and any structure used to look them up
+    pub vertices: usize,
+    /// The edges, stored as entries in the adjacency collections
+    pub edges: usize,
+    /// Metadata of the memory model: reference counts, borrow flags and locks,
+    /// generations of an arena, or transaction descriptors
+    pub descriptors: usize,
+    /// Unused capacity of collections, sentinel nodes and allocation headers
+    pub overhead: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.vertices + self.edges + self.descriptors + self.overhead
+    }
+}
+
+impl Add for MemoryUsage {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.vertices += other.vertices;
+        self.edges += other.edges;
+        self.descriptors += other.descriptors;
+        self.overhead += other.overhead;
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "vertices {}, edges {}, descriptors {}, overhead {} (total {})",
+            Bytes(self.vertices),
+            Bytes(self.edges),
+            Bytes(self.descriptors),
+            Bytes(self.overhead),
+            Bytes(self.total())
+        )
+    }
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.50 MiB`
+#[derive(Debug, Clone, Copy)]
+pub struct Bytes(pub usize);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut size = self.0 as f64;
+        let mut unit = UNITS[0];
+        for u in UNITS.iter() {
+            size /= 1024.0;
+            unit = u;
+            if size < 1024.0 {
+                break;
+            }
+        }
+
+        write!(f, "{:.2} {}", size, unit)
+    }
+}
+
+/// Estimates a `BTreeMap<K, V>` with `len` entries,
+/// returns the bytes of the entries and the overhead of its nodes
+pub fn btree_map<K, V>(len: usize) -> (usize, usize) {
+    if len == 0 {
+        return (0, 0);
+    }
+
+    let entry = size_of::<K>() + size_of::<V>();
+    // Small maps are a single leaf, larger maps are dominated by their leaves
+    let nodes = if len <= BTREE_CAPACITY {
+        1
+    } else {
+        (len + BTREE_FILL - 1) / BTREE_FILL
+    };
+    // Parent pointer, index and length of every node
+    let header = size_of::<usize>() + 2 * size_of::<u16>();
+    let unused = nodes * BTREE_CAPACITY - len;
+
+    (
+        len * entry,
+        unused * entry + nodes * (header + ALLOCATION_HEADER),
+    )
+}
+
+/// Estimates a `HashSet<T>` from its capacity,
+/// returns the bytes of the elements and the overhead of its table
+pub fn hash_set<T>(set: &HashSet<T>) -> (usize, usize) {
+    let buckets = match set.capacity() {
+        0 => return (0, 0),
+        capacity if capacity < 8 => (capacity + 1).next_power_of_two(),
+        capacity => (capacity * 8 / 7).next_power_of_two(),
+    };
+
+    let element = size_of::<T>();
+    let unused = buckets - set.len();
+
+    (
+        set.len() * element,
+        unused * element + buckets + HASH_GROUP_WIDTH + ALLOCATION_HEADER,
+    )
+}
+
+/// Estimates a `Vec<T>` with `len` elements and `capacity` slots,
+/// returns the bytes of the elements and of the unused capacity
+pub fn vec<T>(len: usize, capacity: usize) -> (usize, usize) {
+    if capacity == 0 {
+        return (0, 0);
+    }
+
+    let element = size_of::<T>();
+    (
+        len * element,
+        (capacity - len) * element + ALLOCATION_HEADER,
+    )
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps the system allocator, and counts the bytes that are allocated.
+///
+/// Binaries install it with `gapbs::counting_allocator!();`.
+/// Without it, `allocated` and `peak` always return 0.
+pub struct CountingAllocator;
+
+/// Installs the `CountingAllocator` as the global allocator of a binary
+#[macro_export]
+macro_rules! counting_allocator {
+    () => {
+        #[global_allocator]
+        static ALLOCATOR: $crate::memory::CountingAllocator = $crate::memory::CountingAllocator;
+    };
+}
+
+impl CountingAllocator {
+    #[inline]
+    fn record_alloc(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Relaxed) + size;
+        PEAK.fetch_max(allocated, Relaxed);
+    }
+
+    #[inline]
+    fn record_dealloc(size: usize) {
+        ALLOCATED.fetch_sub(size, Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Relaxed);
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Relaxed);
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATIONS.fetch_sub(1, Relaxed);
+        Self::record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                Self::record_alloc(new_size - layout.size());
+            } else {
+                Self::record_dealloc(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Bytes that are currently allocated through the `CountingAllocator`
+pub fn allocated() -> usize {
+    ALLOCATED.load(Relaxed)
+}
+
+/// Number of allocations that are currently live
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Relaxed)
+}
+
+/// The highest number of bytes allocated at once, since the last `reset_peak`
+pub fn peak() -> usize {
+    PEAK.load(Relaxed)
+}
+
+/// Starts tracking a new peak from the bytes that are allocated now
+pub fn reset_peak() {
+    PEAK.store(ALLOCATED.load(Relaxed), Relaxed);
+}
+
+/// Measures the memory allocated while it is alive, relative to when it was created.
+///
+/// The peak is tracked globally, so allocations made by other
+/// threads during the measurement are included as well.
+pub struct MemoryScope {
+    baseline: usize,
+}
+
+impl MemoryScope {
+    pub fn new() -> Self {
+        reset_peak();
+        Self {
+            baseline: allocated(),
+        }
+    }
+
+    /// The highest number of bytes allocated at once since the scope was created
+    pub fn peak(&self) -> usize {
+        peak().saturating_sub(self.baseline)
+    }
+
+    /// The bytes allocated since the scope was created, that have not been freed
+    pub fn retained(&self) -> usize {
+        allocated().saturating_sub(self.baseline)
+    }
+
+    /// Prints the peak and retained bytes
+    pub fn report(&self, name: &str) {
+        println!(
+            "{:<12} peak {}, retained {}",
+            name,
+            Bytes(self.peak()),
+            Bytes(self.retained())
+        );
+    }
+}
@@ -155,3 +155,36 @@ pub fn ops_epoch_mt(graph: &crate::graphmodels::epoch::Graph<usize>) {
         graph.execute_ops(ops);
     });
 }
+
+/// Executes single-operation transactions where nine out of ten
+/// target vertices that were never inserted into the graph
+pub fn ops_epoch_misses(graph: &crate::graphmodels::epoch::Graph<usize>) {
+    let num_nodes = graph.num_nodes();
+    let rnd_id = || thread_rng().gen_range(1, num_nodes);
+    let rnd_missing_id = || thread_rng().gen_range(num_nodes, 10 * num_nodes);
+
+    (0..1_000).into_iter().for_each(|_| {
+        let mut rng = thread_rng();
+        let v = if rng.gen_range(0, 10) == 0 {
+            rnd_id()
+        } else {
+            rnd_missing_id()
+        };
+
+        let op = match rng.gen_range(1, 100) {
+            1..=40 => crate::graphmodels::epoch::OpType::Find(v),
+            41..=80 => crate::graphmodels::epoch::OpType::Delete(v),
+            _ => {
+                let e = rnd_id();
+                let edge_info = crate::graphmodels::epoch::EdgeInfo {
+                    node_id: e,
+                    weight: None,
+                };
+
+                crate::graphmodels::epoch::OpType::InsertEdge(v, e, Some(edge_info), false)
+            }
+        };
+
+        graph.execute_ops(vec![op]);
+    });
+}
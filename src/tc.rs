@@ -32,7 +32,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Has been manually verified,
 /// Only works on undirected, with sorted nodes
-fn ordered_count<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(graph: &G) -> usize {
+pub(crate) fn ordered_count<'a, V: AsNode, E: AsNode, G: CSRGraph<V, E>>(graph: &G) -> usize {
     let mut total = 0;
     for u in 0..graph.num_nodes() {
         for v in graph.out_neigh(u) {
@@ -0,0 +1,43 @@
+use gapbs::leaks::{self, LeakReport};
+
+gapbs::counting_allocator!();
+
+const NUM_NODES: usize = 1000;
+
+/// Prints the models that leak, and checks that each one only leaks what it is known to:
+/// Rc and Arc leak every vertex, as it is kept alive by the cycle it is part of,
+/// and Epoch leaks its transaction descriptors, which are never reclaimed
+fn report_leak(report: &LeakReport) {
+    if !report.leak.is_empty() {
+        eprintln!("LEAK {}", report);
+    }
+
+    match report.name {
+        "RC" | "ARC" => assert!(
+            report.leak.allocations >= NUM_NODES,
+            "expected {} to leak its vertices, {}",
+            report.name,
+            report.leak
+        ),
+        "EPOCH" => {
+            assert!(!report.descriptors.is_empty(), "{}", report);
+            assert_eq!(
+                report.leak, report.descriptors,
+                "{} leaked more than its descriptors",
+                report.name
+            )
+        }
+        _ => assert!(report.leak.is_empty(), "{}", report),
+    }
+}
+
+// The allocator counts every thread, so all models are checked
+// from a single test rather than from tests that run concurrently
+#[test]
+fn models_free_cyclic_graphs() {
+    for &directed in &[true, false] {
+        for report in &leaks::check_models(NUM_NODES, directed) {
+            report_leak(report);
+        }
+    }
+}